@@ -0,0 +1,110 @@
+use sprs::CsMat;
+
+/// Symmetric strength of connection: `j != i` is a strong neighbour of `i`
+/// when `|a_ij| >= θ sqrt(|a_ii a_jj|)`.
+pub fn strength(a: &CsMat<f64>, diag: &[f64], theta: f64) -> Vec<Vec<usize>> {
+    (0..a.rows())
+        .map(|i| {
+            a.outer_view(i)
+                .unwrap()
+                .iter()
+                .filter(|&(j, &v)| j != i && v.abs() >= theta * (diag[i] * diag[j]).abs().sqrt())
+                .map(|(j, _)| j)
+                .collect()
+        })
+        .collect()
+}
+
+/// Greedy aggregation of Vaněk, Mandel and Brezina on the strength graph `s`.
+/// Returns the aggregate of each node and the number of aggregates. Nodes
+/// without strong neighbours are left out; smoothing alone handles them.
+///
+/// 1. a node whose strong neighbours are all free forms an aggregate with them;
+/// 2. a remaining node joins an aggregate of phase 1 it is strongly connected to;
+/// 3. what is left forms aggregates with its free strong neighbours.
+pub fn aggregate(s: &[Vec<usize>]) -> (Vec<Option<usize>>, usize) {
+    let n = s.len();
+    let mut agg: Vec<Option<usize>> = vec![None; n];
+    let mut nagg = 0;
+    for i in 0..n {
+        if agg[i].is_none() && !s[i].is_empty() && s[i].iter().all(|&j| agg[j].is_none()) {
+            agg[i] = Some(nagg);
+            for &j in &s[i] {
+                agg[j] = Some(nagg);
+            }
+            nagg += 1;
+        }
+    }
+    let phase1 = agg.clone();
+    for i in 0..n {
+        if agg[i].is_none() {
+            agg[i] = s[i].iter().filter_map(|&j| phase1[j]).next();
+        }
+    }
+    for i in 0..n {
+        if agg[i].is_none() && !s[i].is_empty() {
+            agg[i] = Some(nagg);
+            for &j in &s[i] {
+                if agg[j].is_none() {
+                    agg[j] = Some(nagg);
+                }
+            }
+            nagg += 1;
+        }
+    }
+    (agg, nagg)
+}
+
+/// Tentative prolongator interpolating the constant vector: column `k` is
+/// the normalized indicator of aggregate `k`.
+pub fn tentative(agg: &[Option<usize>], nagg: usize) -> CsMat<f64> {
+    let mut size = vec![0usize; nagg];
+    for &k in agg.iter().flatten() {
+        size[k] += 1;
+    }
+    let mut indptr = vec![0];
+    for k in agg {
+        indptr.push(indptr.last().unwrap() + k.iter().count());
+    }
+    let indices: Vec<usize> = agg.iter().flatten().copied().collect();
+    let data = indices
+        .iter()
+        .map(|&k| 1.0 / (size[k] as f64).sqrt())
+        .collect();
+    CsMat::new((agg.len(), nagg), indptr, indices, data)
+}
+
+/// Damped Jacobi smoothing of the tentative prolongator,
+/// `P = (I - ω D^-1 A) T` with `ω = 4/3 / ρ(D^-1 A)`. `ρ` is bounded from
+/// above by the Gershgorin disks, so `ω` errs on the safe side.
+pub fn smooth_prolongator(a: &CsMat<f64>, diag: &[f64], t: &CsMat<f64>) -> CsMat<f64> {
+    let rho = (0..a.rows())
+        .map(|i| {
+            a.outer_view(i)
+                .unwrap()
+                .iter()
+                .map(|(_, &v)| v.abs())
+                .sum::<f64>()
+                / diag[i].abs()
+        })
+        .fold(0.0, f64::max);
+    let omega = 4.0 / 3.0 / rho;
+    let mut at = a * t;
+    let indptr = at.indptr().to_vec();
+    let data = at.data_mut();
+    for (i, &d) in diag.iter().enumerate() {
+        for v in &mut data[indptr[i]..indptr[i + 1]] {
+            *v *= omega / d;
+        }
+    }
+    t - &at
+}
+
+/// Floating point operations of the sparse product `x y`.
+pub fn spgemm_flops(x: &CsMat<f64>, y: &CsMat<f64>) -> usize {
+    let y = y.to_csr();
+    x.to_csr()
+        .iter()
+        .map(|(_, (_, k))| 2 * y.outer_view(k).unwrap().nnz())
+        .sum()
+}
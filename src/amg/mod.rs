@@ -0,0 +1,340 @@
+//! Smoothed aggregation algebraic multigrid for symmetric positive definite
+//! `sprs::CsMat<f64>` matrices, e.g. discretized Poisson problems. `Amg` is
+//! both a `LinearOperator` (one cycle from a zero initial guess, for use as
+//! `M`) and an `IterativeSolver` (repeated cycles).
+#![allow(non_snake_case)]
+
+pub mod aggregation;
+pub mod smoother;
+
+pub use smoother::Smoother;
+
+use crate::monitor::{Monitor, MonitorAction};
+use crate::operator::{check_shape, LinearOperator};
+use crate::precond::{csr_diag, square_csr, PrecondErr, SetupInfo};
+use crate::qr_solve;
+use crate::solver::{IterativeSolver, SolveReport};
+use crate::stopping::{IterInfo, StopReason, StoppingCriterion};
+use crate::utils::{norm, sprs2dense};
+use aggregation::{aggregate, smooth_prolongator, spgemm_flops, strength, tentative};
+use ndarray::{Array1, Array2, ArrayView1};
+use sprs::CsMat;
+use std::time::Instant;
+
+/// Number of visits to the next coarser level per cycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cycle {
+    V,
+    W,
+}
+
+/// Parameters of the hierarchy and of the cycle.
+#[derive(Debug, Clone, Copy)]
+pub struct AmgParams {
+    /// strength of connection threshold `θ` on the finest level; it is
+    /// halved on each coarser level
+    pub theta: f64,
+    /// levels including the finest and the coarsest one
+    pub max_levels: usize,
+    /// coarsening stops once a level has at most this many unknowns
+    pub max_coarse: usize,
+    pub cycle: Cycle,
+    pub smoother: Smoother,
+    pub presmooth: usize,
+    pub postsmooth: usize,
+}
+
+impl AmgParams {
+    /// `θ = 0.08`, up to 10 levels down to 100 unknowns, V(1, 1) cycles with
+    /// Gauss–Seidel
+    pub fn new() -> AmgParams {
+        AmgParams {
+            theta: 0.08,
+            max_levels: 10,
+            max_coarse: 100,
+            cycle: Cycle::V,
+            smoother: Smoother::GaussSeidel,
+            presmooth: 1,
+            postsmooth: 1,
+        }
+    }
+
+    pub fn with_theta(mut self, theta: f64) -> AmgParams {
+        self.theta = theta;
+        self
+    }
+
+    pub fn with_max_levels(mut self, max_levels: usize) -> AmgParams {
+        self.max_levels = max_levels;
+        self
+    }
+
+    pub fn with_max_coarse(mut self, max_coarse: usize) -> AmgParams {
+        self.max_coarse = max_coarse;
+        self
+    }
+
+    pub fn with_cycle(mut self, cycle: Cycle) -> AmgParams {
+        self.cycle = cycle;
+        self
+    }
+
+    pub fn with_smoother(
+        mut self,
+        smoother: Smoother,
+        presmooth: usize,
+        postsmooth: usize,
+    ) -> AmgParams {
+        self.smoother = smoother;
+        self.presmooth = presmooth;
+        self.postsmooth = postsmooth;
+        self
+    }
+}
+
+impl Default for AmgParams {
+    fn default() -> AmgParams {
+        AmgParams::new()
+    }
+}
+
+/// A level above the coarsest one.
+pub struct Level {
+    pub a: CsMat<f64>,
+    pub diag: Vec<f64>,
+    /// prolongator to this level from the next coarser one
+    pub p: CsMat<f64>,
+    /// restriction, `P^T`
+    pub r: CsMat<f64>,
+}
+
+/// A smoothed aggregation hierarchy. Level `k + 1` has the Galerkin operator
+/// `P_k^T A_k P_k`; the coarsest one is solved densely by `qr_solve::solve`.
+pub struct Amg {
+    pub params: AmgParams,
+    pub levels: Vec<Level>,
+    pub coarse: Array2<f64>,
+    pub info: SetupInfo,
+}
+
+impl Amg {
+    /// Fails on a non-square matrix, a zero diagonal entry on any level or
+    /// invalid parameters. Coarsening also stops early when aggregation no
+    /// longer reduces the number of unknowns.
+    pub fn new(a: &CsMat<f64>, params: AmgParams) -> Result<Amg, PrecondErr> {
+        if params.max_levels == 0 {
+            return Err(PrecondErr::InvalidParameter("max_levels must be positive"));
+        }
+        if !(params.theta >= 0.0 && params.theta <= 1.0) {
+            return Err(PrecondErr::InvalidParameter("theta must be in [0, 1]"));
+        }
+        if let Smoother::Jacobi(w) = params.smoother {
+            if !(w > 0.0 && w <= 1.0) {
+                return Err(PrecondErr::InvalidParameter(
+                    "Jacobi weight must be in (0, 1]",
+                ));
+            }
+        }
+        let start = Instant::now();
+        let mut a = square_csr(a)?;
+        let mut levels = Vec::new();
+        let mut flops = 0;
+        let mut theta = params.theta;
+        while levels.len() + 1 < params.max_levels && a.rows() > params.max_coarse {
+            let diag = csr_diag(&a)?;
+            let (agg, nagg) = aggregate(&strength(&a, &diag, theta));
+            if nagg == 0 || nagg == a.rows() {
+                break;
+            }
+            let t = tentative(&agg, nagg);
+            let p = smooth_prolongator(&a, &diag, &t);
+            let r = p.transpose_view().to_csr();
+            let ap = &a * &p;
+            let ac = &r * &ap;
+            flops += 2 * a.nnz() + p.nnz() + spgemm_flops(&a, &p) + spgemm_flops(&r, &ap);
+            levels.push(Level { a, diag, p, r });
+            a = ac;
+            theta *= 0.5;
+        }
+        let coarse = sprs2dense(&a);
+        let info = SetupInfo {
+            nnz: levels
+                .iter()
+                .map(|l| l.a.nnz() + 2 * l.p.nnz())
+                .sum::<usize>()
+                + coarse.len(),
+            flops,
+            time: start.elapsed(),
+        };
+        Ok(Amg {
+            params,
+            levels,
+            coarse,
+            info,
+        })
+    }
+
+    /// number of levels, the coarsest included
+    pub fn num_levels(&self) -> usize {
+        self.levels.len() + 1
+    }
+
+    /// `sum_k nnz(A_k) / nnz(A_0)`, counting the coarsest level as stored
+    pub fn operator_complexity(&self) -> f64 {
+        let coarse = self.coarse.iter().filter(|&&x| x != 0.0).count();
+        let nnz: Vec<usize> = self.levels.iter().map(|l| l.a.nnz()).collect();
+        let fine = nnz.first().copied().unwrap_or(coarse);
+        (nnz.iter().sum::<usize>() + coarse) as f64 / fine as f64
+    }
+
+    /// `sum_k n_k / n_0`
+    pub fn grid_complexity(&self) -> f64 {
+        let n: Vec<usize> = self.levels.iter().map(|l| l.a.rows()).collect();
+        let coarse = self.coarse.nrows();
+        let fine = n.first().copied().unwrap_or(coarse);
+        (n.iter().sum::<usize>() + coarse) as f64 / fine as f64
+    }
+
+    fn smooth(&self, level: &Level, b: ArrayView1<f64>, x: &mut Array1<f64>, forward: bool) {
+        match self.params.smoother {
+            Smoother::Jacobi(w) => smoother::jacobi(&level.a, &level.diag, w, b, x),
+            Smoother::GaussSeidel => smoother::gauss_seidel(&level.a, &level.diag, forward, b, x),
+        }
+    }
+
+    /// One cycle on `A_k x = b` starting from `x`.
+    pub fn cycle(&self, k: usize, b: ArrayView1<f64>, x: &mut Array1<f64>) {
+        let level = match self.levels.get(k) {
+            Some(level) => level,
+            None => {
+                *x = qr_solve::solve(self.coarse.view(), b);
+                return;
+            }
+        };
+        for _ in 0..self.params.presmooth {
+            self.smooth(level, b, x, true);
+        }
+        let r = &b - &level.a.apply(x.view());
+        let rc = level.r.apply(r.view());
+        let mut xc = Array1::<f64>::zeros(rc.len());
+        let visits = match self.params.cycle {
+            // the coarsest level is solved exactly, once is enough
+            Cycle::W if k + 1 < self.levels.len() => 2,
+            _ => 1,
+        };
+        for _ in 0..visits {
+            self.cycle(k + 1, rc.view(), &mut xc);
+        }
+        *x += &level.p.apply(xc.view());
+        for _ in 0..self.params.postsmooth {
+            self.smooth(level, b, x, false);
+        }
+    }
+}
+
+/// One cycle from a zero initial guess. The cycle is symmetric if `A` is and
+/// `presmooth == postsmooth`, so `Amg` may precondition CG and MINRES.
+impl LinearOperator<f64> for Amg {
+    fn shape(&self) -> (usize, usize) {
+        let n = self
+            .levels
+            .first()
+            .map_or(self.coarse.nrows(), |l| l.a.rows());
+        (n, n)
+    }
+
+    fn apply(&self, x: ArrayView1<f64>) -> Array1<f64> {
+        let mut y = Array1::<f64>::zeros(x.len());
+        self.cycle(0, x, &mut y);
+        y
+    }
+
+    fn apply_adjoint(&self, x: ArrayView1<f64>) -> Array1<f64> {
+        self.apply(x)
+    }
+}
+
+pub struct AmgState {
+    pub x: Array1<f64>,
+    /// recursively updated residual
+    pub res: Array1<f64>,
+}
+
+impl AmgState {
+    pub fn new(A: &dyn LinearOperator<f64>, x: ArrayView1<f64>, b: ArrayView1<f64>) -> AmgState {
+        check_shape(A, b.len(), x.len());
+        let res = &b - &A.apply(x);
+        AmgState {
+            x: x.to_owned(),
+            res,
+        }
+    }
+
+    /// `x += M (b - A x)` with `M` one cycle of `amg`
+    pub fn next(&mut self, A: &dyn LinearOperator<f64>, amg: &Amg) -> Option<()> {
+        let dx = amg.apply(self.res.view());
+        let res = &self.res - &A.apply(dx.view());
+        if dx.iter().chain(res.iter()).all(|x| x.is_finite()) {
+            self.x += &dx;
+            self.res = res;
+            Some(())
+        } else {
+            None
+        }
+    }
+}
+
+/// Multigrid cycles as a stationary iteration. `A` is normally the matrix the
+/// hierarchy was built from.
+impl IterativeSolver<f64, f64> for Amg {
+    type State = AmgState;
+
+    fn solve_with_monitor(
+        &self,
+        A: &dyn LinearOperator<f64>,
+        b: ArrayView1<f64>,
+        x0: ArrayView1<f64>,
+        criterion: &StoppingCriterion<f64>,
+        monitor: &mut dyn Monitor<AmgState, f64>,
+    ) -> SolveReport<f64, f64> {
+        let start = Instant::now();
+        let b_norm = norm(b);
+        let mut state = AmgState::new(A, x0, b);
+        let r0_norm = norm(state.res.view());
+        let mut history = vec![r0_norm];
+        let mut iterations = 0;
+        let reason = loop {
+            let info = IterInfo::new(
+                iterations,
+                history[iterations],
+                r0_norm,
+                b_norm,
+                start.elapsed(),
+            );
+            if let Some(reason) = criterion.check(&info) {
+                break reason;
+            }
+            if state.next(A, self).is_none() {
+                break StopReason::Breakdown;
+            }
+            iterations += 1;
+            let r = norm(state.res.view());
+            history.push(r);
+            if monitor.observe(iterations, r, &state) == MonitorAction::Abort {
+                break StopReason::Aborted;
+            }
+        };
+        let resid_norm = norm((&b - &A.apply(state.x.view())).view());
+        let reason = match reason {
+            StopReason::Breakdown => criterion.breakdown_reason(resid_norm, r0_norm, b_norm),
+            _ => reason,
+        };
+        SolveReport {
+            x: state.x,
+            iterations,
+            resid_norm,
+            reason,
+            history,
+        }
+    }
+}
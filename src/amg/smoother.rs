@@ -0,0 +1,34 @@
+use crate::stationary::{self, Sweep};
+use ndarray::{Array1, ArrayView1};
+use sprs::CsMat;
+
+/// Relaxation applied before and after each coarse-grid correction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Smoother {
+    /// damped Jacobi with this weight, typically 2/3
+    Jacobi(f64),
+    /// forward sweeps before and backward sweeps after the correction, so
+    /// that the cycle stays symmetric
+    GaussSeidel,
+}
+
+/// `x += ω D^-1 (b - A x)`
+pub fn jacobi(a: &CsMat<f64>, diag: &[f64], omega: f64, b: ArrayView1<f64>, x: &mut Array1<f64>) {
+    stationary::jacobi_sweep(a, diag, omega, b, x);
+}
+
+/// One Gauss–Seidel sweep, in increasing row order if `forward`.
+pub fn gauss_seidel(
+    a: &CsMat<f64>,
+    diag: &[f64],
+    forward: bool,
+    b: ArrayView1<f64>,
+    x: &mut Array1<f64>,
+) {
+    let sweep = if forward {
+        Sweep::Forward
+    } else {
+        Sweep::Backward
+    };
+    stationary::sor_sweep(a, diag, 1.0, sweep, b, x);
+}
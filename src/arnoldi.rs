@@ -1,13 +1,19 @@
 #![allow(non_snake_case)]
 
-use crate::utils::{norm, ComplexOrReal, HasAbs};
+use crate::operator::{check_shape, LinearOperator};
+use crate::utils::{csign, inner, norm, ComplexOrReal, HasAbs};
 use ndarray::{s, Array1, Array2, ArrayView1, ArrayView2};
 use num_traits::Float;
 use std::marker::PhantomData;
 
 #[derive(Debug, Clone, Copy)]
 pub enum ArnoldiErr {
+    /// Lucky breakdown: the new vector lies in the span of `Q` (up to
+    /// `breakdown_tol`), so `Q` spans an invariant subspace and
+    /// `A Q = Q H` with the square `get_H`. The column of `H` is kept with
+    /// `H[k][k+1]` set to zero, but no vector is added to `Q`.
     HMM1Zero,
+    /// the space cannot be extended after a breakdown
     NoMoreBase,
 }
 
@@ -22,6 +28,67 @@ impl std::fmt::Display for ArnoldiErr {
 
 impl std::error::Error for ArnoldiErr {}
 
+/// How `ArnoldiSpace::extend` orthogonalizes a new vector against `Q`.
+///
+/// `Mgs` is one sweep of modified Gram–Schmidt, cheap but orthogonality
+/// degrades like the condition number of the Krylov basis. The other
+/// choices keep `Q` orthonormal to working precision:
+/// - `Cgs2`: classical Gram–Schmidt applied twice ("twice is enough");
+/// - `Dgks`: classical Gram–Schmidt, repeated only when the norm of the
+///   vector drops below `1/sqrt(2)` of its norm before the sweep (Daniel,
+///   Gragg, Kaufman and Stewart);
+/// - `Selective`: one MGS sweep, followed by a second one only if the
+///   largest `|q_j^H q|` of the new vector exceeds `sqrt(eps)`;
+/// - `Householder`: Walker's Householder Arnoldi, where every basis vector
+///   is generated by a product of reflectors, at about twice the cost of
+///   MGS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orthogonalization {
+    Mgs,
+    Cgs2,
+    Dgks,
+    Selective,
+    Householder,
+}
+
+// `#[default]` on enum variants needs Rust 1.62
+#[allow(clippy::derivable_impls)]
+impl Default for Orthogonalization {
+    fn default() -> Self {
+        Orthogonalization::Mgs
+    }
+}
+
+/// Householder vector `w` (unit norm) and `alpha` with
+/// `(I - 2 w w^H) t = alpha e1`; `None` if `t` vanishes.
+fn householder_vector<T, U>(t: ArrayView1<T>) -> Option<(Array1<T>, T)>
+where
+    T: ComplexOrReal<U>,
+    U: Float,
+{
+    let nt = norm(t);
+    if nt == U::zero() {
+        return None;
+    }
+    let alpha = -csign(t[0]) * T::from(nt);
+    let mut w = t.to_owned();
+    w[0] = w[0] - alpha;
+    let nw = norm(w.view());
+    Some((w / T::from(nw), alpha))
+}
+
+/// applies the reflector `I - 2 w w^H`, with `w` zero above row `j`, to `v`
+fn reflect<T, U>(w: &Array1<T>, j: usize, v: &mut Array1<T>)
+where
+    T: ComplexOrReal<U>,
+    U: Float,
+{
+    let mut tail = v.slice_mut(s![j..]);
+    let h = inner(w.view(), tail.view());
+    let two = T::one() + T::one();
+    tail.zip_mut_with(w, |x, &y| *x = *x - y * h * two);
+}
+
 #[derive(Clone)]
 pub struct ArnoldiSpace<T, U>
 where
@@ -30,6 +97,20 @@ where
 {
     pub Q: Vec<Array1<T>>,
     pub H: Vec<Array1<T>>,
+    pub orth: Orthogonalization,
+    /// with `Householder`, the reflectors `P_j = I - 2 w_j w_j^H` stored
+    /// compactly: `W[j]` holds the nonzero part `w_j[j..]`
+    pub W: Vec<Array1<T>>,
+    /// phases with `Q[j] = sigma[j] P_0 ... P_j e_j`
+    pub sigma: Vec<T>,
+    /// whether `extend` records `loss`
+    pub measure_loss: bool,
+    /// with `measure_loss`, `‖I - Q^H Q‖_F` after each `extend`
+    pub loss: Vec<U>,
+    /// `extend` reports a breakdown when the orthogonalized vector has at
+    /// most this norm relative to the vector passed in; zero by default, so
+    /// only an exactly vanishing remainder counts
+    pub breakdown_tol: U,
     pub phantom: PhantomData<U>,
 }
 
@@ -43,6 +124,12 @@ where
         ArnoldiSpace {
             Q: vec![q],
             H: Vec::new(),
+            orth: Orthogonalization::Mgs,
+            W: Vec::new(),
+            sigma: Vec::new(),
+            measure_loss: false,
+            loss: Vec::new(),
+            breakdown_tol: U::zero(),
             phantom: PhantomData,
         }
     }
@@ -51,32 +138,206 @@ where
         ArnoldiSpace {
             Q: Vec::new(),
             H: Vec::new(),
+            orth: Orthogonalization::Mgs,
+            W: Vec::new(),
+            sigma: Vec::new(),
+            measure_loss: false,
+            loss: Vec::new(),
+            breakdown_tol: U::zero(),
             phantom: PhantomData,
         }
     }
 
+    pub fn with_orthogonalization(mut self, orth: Orthogonalization) -> ArnoldiSpace<T, U> {
+        self.set_orthogonalization(orth);
+        self
+    }
+
+    /// Switches the orthogonalization of subsequent `extend` calls; `Q` is
+    /// assumed orthonormal.
+    pub fn set_orthogonalization(&mut self, orth: Orthogonalization) {
+        self.orth = orth;
+        self.rebuild_reflectors();
+    }
+
+    /// Turns the recording of the loss of orthogonality in `loss` on or
+    /// off. Each measurement costs one more inner product with every
+    /// basis vector.
+    pub fn with_loss_measurement(mut self, on: bool) -> ArnoldiSpace<T, U> {
+        self.measure_loss = on;
+        self
+    }
+
+    pub fn with_breakdown_tol(mut self, tol: U) -> ArnoldiSpace<T, U> {
+        self.breakdown_tol = tol;
+        self
+    }
+
+    /// Restarts from `b`, keeping the orthogonalization, measurement and
+    /// breakdown settings; `loss` starts over.
     pub fn reset(&mut self, b: ArrayView1<T>) {
+        let orth = self.orth;
+        let measure_loss = self.measure_loss;
+        let breakdown_tol = self.breakdown_tol;
         *self = Self::new(b)
+            .with_orthogonalization(orth)
+            .with_loss_measurement(measure_loss)
+            .with_breakdown_tol(breakdown_tol)
+    }
+
+    /// true after a breakdown, when `Q` spans an invariant subspace
+    pub fn is_invariant(&self) -> bool {
+        !self.H.is_empty() && self.Q.len() == self.H.len()
     }
 
-    pub fn iter(
-        &mut self,
-        A: &dyn Fn(ArrayView1<T>) -> Array1<T>,
-    ) -> std::result::Result<(), ArnoldiErr> {
+    /// After a breakdown, the basis `V` of the invariant subspace and `H`
+    /// with `A V = V H`; the eigenvalues of `H` are eigenvalues of `A`.
+    pub fn invariant_subspace(&self) -> Option<(Array2<T>, Array2<T>)> {
+        if self.is_invariant() {
+            Some((self.get_Q(), self.get_H()))
+        } else {
+            None
+        }
+    }
+
+    /// `‖I - Q^H Q‖_F` over all vectors of `Q`
+    pub fn loss_of_orthogonality(&self) -> U {
+        let mut sum = U::zero();
+        for (i, qi) in self.Q.iter().enumerate() {
+            let d = norm(qi.view());
+            sum = sum + (d * d - U::one()).powi(2);
+            for qj in self.Q.iter().take(i) {
+                let c = inner(qj.view(), qi.view()).abs();
+                sum = sum + (c * c + c * c);
+            }
+        }
+        sum.sqrt()
+    }
+
+    /// appends the loss of orthogonality after `q` was added to `Q`,
+    /// updating the last value instead of recomputing it
+    fn measure(&mut self) {
+        let (q, rest) = self.Q.split_last().unwrap();
+        let last = match self.loss.last() {
+            Some(&l) => l,
+            None => {
+                let l = self.loss_of_orthogonality();
+                self.loss.push(l);
+                return;
+            }
+        };
+        let d = norm(q.view());
+        let mut sum = last * last + (d * d - U::one()).powi(2);
+        for qj in rest {
+            let c = inner(qj.view(), q.view()).abs();
+            sum = sum + (c * c + c * c);
+        }
+        self.loss.push(sum.sqrt());
+    }
+
+    /// Recomputes the reflectors from `Q`, which must be orthonormal, e.g.
+    /// after `put_Q`. Does nothing unless `orth` is `Householder`.
+    pub fn rebuild_reflectors(&mut self) {
+        self.W.clear();
+        self.sigma.clear();
+        if self.orth != Orthogonalization::Householder {
+            return;
+        }
+        for q in self.Q.iter() {
+            let j = self.W.len();
+            let mut x = q.clone();
+            for (i, w) in self.W.iter().enumerate() {
+                reflect(w, i, &mut x);
+            }
+            let (w, alpha) = householder_vector(x.slice(s![j..])).expect("Q is not orthonormal");
+            self.W.push(w);
+            self.sigma.push(csign(alpha));
+        }
+    }
+
+    pub fn iter(&mut self, A: &dyn LinearOperator<T>) -> std::result::Result<(), ArnoldiErr> {
         let m = self.Q[0].len();
-        if self.Q.len() >= m {
+        check_shape(A, m, m);
+        if self.is_invariant() {
             return Err(ArnoldiErr::NoMoreBase);
         }
         //let mut v=self.A.dot(self.Q.last().unwrap());
-        let mut v = A(self.Q.last().unwrap().view());
+        let v = A.apply(self.Q.last().unwrap().view());
+        self.extend(v)
+    }
+
+    /// Orthogonalizes `v` against `Q`, appends the coefficients as a new column
+    /// of `H` and the normalized remainder to `Q`. `iter` passes `v = A q_k`;
+    /// flexible variants pass `A z_k` for a preconditioned direction `z_k`.
+    /// Once `Q` spans the whole space the remainder vanishes, so this always
+    /// ends in a breakdown.
+    pub fn extend(&mut self, v: Array1<T>) -> std::result::Result<(), ArnoldiErr> {
+        if self.is_invariant() {
+            return Err(ArnoldiErr::NoMoreBase);
+        }
+        let result = match self.orth {
+            Orthogonalization::Householder => self.extend_householder(v),
+            _ => self.extend_gram_schmidt(v),
+        };
+        if result.is_ok() && self.measure_loss {
+            self.measure();
+        }
+        result
+    }
+
+    /// one modified Gram–Schmidt sweep of `v` against `Q[..=k]`, adding the
+    /// coefficients to `H[k]`
+    fn mgs_sweep(&mut self, k: usize, v: &mut Array1<T>) {
+        for j in 0..=k {
+            let h = self.Q[j].map(|&x| x.conj()).dot(v);
+            *v = &*v - &(&self.Q[j] * h);
+            self.H[k][j] = self.H[k][j] + h;
+        }
+    }
+
+    /// one classical Gram–Schmidt sweep: all coefficients are taken from
+    /// the same `v`
+    fn cgs_sweep(&mut self, k: usize, v: &mut Array1<T>) {
+        let h: Vec<T> = (0..=k).map(|j| inner(self.Q[j].view(), v.view())).collect();
+        for (j, &h) in h.iter().enumerate() {
+            *v = &*v - &(&self.Q[j] * h);
+            self.H[k][j] = self.H[k][j] + h;
+        }
+    }
+
+    fn extend_gram_schmidt(&mut self, mut v: Array1<T>) -> std::result::Result<(), ArnoldiErr> {
         let k = self.H.len();
+        let before = norm(v.view());
         self.H.push(Array1::zeros(k + 2));
-        //self.Q.push(Array1::zeros(self.q.len()));
-        for j in 0..=k {
-            self.H[k][j] = self.Q[j].map(|&x| x.conj()).dot(&v);
-            v = &v - &(&self.Q[j] * self.H[k][j]);
+        match self.orth {
+            Orthogonalization::Cgs2 => {
+                self.cgs_sweep(k, &mut v);
+                self.cgs_sweep(k, &mut v);
+            }
+            Orthogonalization::Dgks => {
+                self.cgs_sweep(k, &mut v);
+                if norm(v.view()) < before / U::from(2).unwrap().sqrt() {
+                    self.cgs_sweep(k, &mut v);
+                }
+            }
+            Orthogonalization::Selective => {
+                self.mgs_sweep(k, &mut v);
+                let nv = norm(v.view());
+                let worst = self.Q[..=k]
+                    .iter()
+                    .map(|q| inner(q.view(), v.view()).abs())
+                    .fold(U::zero(), U::max);
+                if worst > U::epsilon().sqrt() * nv {
+                    self.mgs_sweep(k, &mut v);
+                }
+            }
+            _ => self.mgs_sweep(k, &mut v),
         }
-        self.H[k][k + 1] = <T as From<U>>::from(norm(v.view()));
+        let h = norm(v.view());
+        if self.Q.len() >= v.len() || h <= self.breakdown_tol * before {
+            return Result::Err(ArnoldiErr::HMM1Zero);
+        }
+        self.H[k][k + 1] = <T as From<U>>::from(h);
         let q = v / self.H[k][k + 1];
         if q.iter().all(|x| HasAbs::abs(x).is_finite()) {
             self.Q.push(q);
@@ -86,6 +347,48 @@ where
         }
     }
 
+    /// Walker's Householder Arnoldi step: `P_k ... P_0 v` has the
+    /// coefficients of `v` in `Q` on top, a new reflector `P_{k+1}` zeroes
+    /// the rest below row `k + 1`, and the new basis vector is
+    /// `P_0 ... P_{k+1} e_{k+1}`, rescaled so that `H[k][k+1]` is real and
+    /// positive as with MGS.
+    fn extend_householder(&mut self, mut v: Array1<T>) -> std::result::Result<(), ArnoldiErr> {
+        let k = self.H.len();
+        let before = norm(v.view());
+        for (j, w) in self.W.iter().enumerate() {
+            reflect(w, j, &mut v);
+        }
+        let mut h = Array1::zeros(k + 2);
+        for j in 0..=k {
+            h[j] = self.sigma[j].conj() * v[j];
+        }
+        let next = if k + 1 < v.len() && norm(v.slice(s![k + 1..])) > self.breakdown_tol * before {
+            householder_vector(v.slice(s![k + 1..]))
+        } else {
+            None
+        };
+        let (w, alpha) = match next {
+            Some(wa) => wa,
+            None => {
+                self.H.push(h);
+                return Result::Err(ArnoldiErr::HMM1Zero);
+            }
+        };
+        h[k + 1] = <T as From<U>>::from(alpha.abs());
+        let sigma = csign(alpha);
+        let mut q = Array1::zeros(v.len());
+        q[k + 1] = sigma;
+        reflect(&w, k + 1, &mut q);
+        for (j, w) in self.W.iter().enumerate().rev() {
+            reflect(w, j, &mut q);
+        }
+        self.H.push(h);
+        self.W.push(w);
+        self.sigma.push(sigma);
+        self.Q.push(q);
+        Result::Ok(())
+    }
+
     pub fn get_H(&self) -> Array2<T> {
         let n = self.H.len();
         let mut result = Array2::zeros([n, n]);
@@ -131,6 +434,8 @@ where
             //result.slice_mut(s![.., i]).assign(q);
             q.assign(&Q.slice(s![.., i]));
         }
+        self.rebuild_reflectors();
+        self.loss.clear();
     }
 
     pub fn get_Q_star(&self) -> Array2<T> {
@@ -156,5 +461,7 @@ where
         let v = &f / T::from(nn);
         self.Q.last_mut().unwrap().assign(&v);
         self.H[n - 1][n] = nn.into();
+        self.rebuild_reflectors();
+        self.loss.clear();
     }
 }
@@ -1,52 +1,68 @@
 #![allow(non_snake_case)]
 #![allow(clippy::many_single_char_names)]
 
-use ndarray::ScalarOperand;
+use crate::monitor::{Monitor, MonitorAction};
+use crate::operator::{check_shape, LinearOperator};
+use crate::solver::{IterativeSolver, SolveReport};
+use crate::stopping::{IterInfo, StopReason, StoppingCriterion};
+use crate::utils::{inner, norm, ComplexOrReal};
 use ndarray::{Array1, ArrayView1};
 use num_traits::Float;
+use std::time::Instant;
 
-pub struct BiCGStabState<T>
+/// State of BiCGStab for `A x = b`.
+///
+/// `converged` is set once `‖res‖ < tol`. Before `StoppingCriterion` was
+/// introduced the test was `|res . res_prime| < tol^2`, against the shadow
+/// residual; callers that drive the state directly now get the plain
+/// residual norm test.
+pub struct BiCGStabState<T, U>
 where
-    T: Copy + Default + Float + ScalarOperand + 'static + std::fmt::Debug,
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
 {
     pub res: Array1<T>,
     pub res_prime: Array1<T>,
     pub p: Array1<T>,
     pub x: Array1<T>,
-    pub tol: T,
+    /// absolute threshold on `‖res‖`
+    pub tol: U,
     pub converged: bool,
 }
 
-pub fn bicgstab_iter<T>(
-    lhs: &dyn Fn(ArrayView1<T>) -> Array1<T>,
-    s_last: &BiCGStabState<T>,
-) -> BiCGStabState<T>
+/// One BiCGStab step. For complex `T` the inner products are the Hermitian
+/// ones, `<x, y> = x^H y`, with the shadow residual `res_prime` on the left.
+pub fn bicgstab_iter<T, U>(
+    lhs: &dyn LinearOperator<T>,
+    s_last: &BiCGStabState<T, U>,
+) -> BiCGStabState<T, U>
 where
-    T: Float + Copy + Default + ScalarOperand + std::fmt::Debug,
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
 {
-    let ap = lhs(s_last.p.view());
+    let ap = lhs.apply(s_last.p.view());
 
-    let alpha = s_last.res.dot(&s_last.res_prime) / (ap.dot(&s_last.res_prime));
+    let res_res_prime = inner(s_last.res_prime.view(), s_last.res.view());
+    let alpha = res_res_prime / inner(s_last.res_prime.view(), ap.view());
     let s = (&s_last.res) - &((&ap) * alpha);
-    let a_s = lhs(s.view());
-    let a_s_norm = a_s.dot(&a_s);
+    let a_s = lhs.apply(s.view());
+    let a_s_norm = inner(a_s.view(), a_s.view());
     let w = if a_s_norm == T::zero() {
         T::one()
     } else {
-        a_s.dot(&s) / a_s_norm
+        inner(a_s.view(), s.view()) / a_s_norm
     };
     let x = &s_last.x + &((&s_last.p) * alpha) + &(&s * w);
     let res = &s - &(&a_s * w);
-    let res_res_prime = s_last.res.dot(&s_last.res_prime);
     let beta = (alpha / w)
         * if res_res_prime == T::zero() {
             T::one()
         } else {
-            res.dot(&s_last.res_prime) / res_res_prime
+            inner(s_last.res_prime.view(), res.view()) / res_res_prime
         };
 
     let p = &res + &(&(&s_last.p - &(&ap * w)) * beta);
-    let (p, res_prime, converged) = if res.dot(&s_last.res_prime).abs() < s_last.tol.powi(2) {
+    let (p, res_prime, converged) = if norm::<T, U>(res.view()) < s_last.tol {
         (res.clone(), res.clone(), true)
     } else {
         (p, s_last.res_prime.clone(), false)
@@ -62,18 +78,20 @@ where
     }
 }
 
-impl<T> BiCGStabState<T>
+impl<T, U> BiCGStabState<T, U>
 where
-    T: Copy + Default + Float + ScalarOperand + 'static + std::fmt::Debug,
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
 {
     pub fn new(
-        lhs: &dyn Fn(ArrayView1<T>) -> Array1<T>,
+        lhs: &dyn LinearOperator<T>,
         x: ArrayView1<T>,
         b: ArrayView1<T>,
-        tol: T,
-    ) -> BiCGStabState<T> {
+        tol: U,
+    ) -> BiCGStabState<T, U> {
         assert!(x.len() == b.len());
-        let res = &b - &lhs(x.view());
+        check_shape(lhs, b.len(), x.len());
+        let res = &b - &lhs.apply(x.view());
         let res_prime = res.clone();
         let p = res.clone();
         BiCGStabState {
@@ -86,7 +104,7 @@ where
         }
     }
 
-    pub fn next(&mut self, lhs: &dyn Fn(ArrayView1<T>) -> Array1<T>) -> std::option::Option<()> {
+    pub fn next(&mut self, lhs: &dyn LinearOperator<T>) -> std::option::Option<()> {
         let ns = bicgstab_iter(lhs, self);
         if ns.valid() {
             *self = ns;
@@ -96,24 +114,79 @@ where
         }
     }
 
-    pub fn calc_resid(&self, lhs: &dyn Fn(ArrayView1<T>) -> Array1<T>, b: &Array1<T>) -> Array1<T> {
-        b - &lhs(self.x.view())
+    pub fn calc_resid(&self, lhs: &dyn LinearOperator<T>, b: &Array1<T>) -> Array1<T> {
+        b - &lhs.apply(self.x.view())
     }
 
-    pub fn converged(
-        &self,
-        lhs: &dyn Fn(ArrayView1<T>) -> Array1<T>,
-        b: &Array1<T>,
-        th: T,
-    ) -> bool {
+    pub fn converged(&self, lhs: &dyn LinearOperator<T>, b: &Array1<T>, th: U) -> bool {
         let res = self.calc_resid(lhs, b);
-        res.dot(&res) < th * th
+        norm::<T, U>(res.view()) < th
     }
 
     pub fn valid(&self) -> bool {
-        self.res.iter().all(|x| x.is_finite())
-            && self.res_prime.iter().all(|x| x.is_finite())
-            && self.p.iter().all(|x| x.is_finite())
-            && self.x.iter().all(|x| x.is_finite())
+        let finite = |v: &Array1<T>| v.iter().all(|x| x.abs().is_finite());
+        finite(&self.res) && finite(&self.res_prime) && finite(&self.p) && finite(&self.x)
+    }
+}
+
+/// BiCGStab as an `IterativeSolver`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BiCGStab;
+
+impl<T, U> IterativeSolver<T, U> for BiCGStab
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    type State = BiCGStabState<T, U>;
+
+    fn solve_with_monitor(
+        &self,
+        A: &dyn LinearOperator<T>,
+        b: ArrayView1<T>,
+        x0: ArrayView1<T>,
+        criterion: &StoppingCriterion<U>,
+        monitor: &mut dyn Monitor<BiCGStabState<T, U>, U>,
+    ) -> SolveReport<T, U> {
+        let start = Instant::now();
+        let b = b.to_owned();
+        let b_norm = norm(b.view());
+        let mut state = BiCGStabState::new(A, x0, b.view(), U::zero());
+        let r0_norm = norm(state.res.view());
+        let mut history = vec![r0_norm];
+        let mut iterations = 0;
+        let reason = loop {
+            let info = IterInfo::new(
+                iterations,
+                history[iterations],
+                r0_norm,
+                b_norm,
+                start.elapsed(),
+            );
+            if let Some(reason) = criterion.check(&info) {
+                break reason;
+            }
+            if state.next(A).is_none() {
+                break StopReason::Breakdown;
+            }
+            iterations += 1;
+            let r = norm(state.res.view());
+            history.push(r);
+            if monitor.observe(iterations, r, &state) == MonitorAction::Abort {
+                break StopReason::Aborted;
+            }
+        };
+        let resid_norm = norm(state.calc_resid(A, &b).view());
+        let reason = match reason {
+            StopReason::Breakdown => criterion.breakdown_reason(resid_norm, r0_norm, b_norm),
+            _ => reason,
+        };
+        SolveReport {
+            x: state.x,
+            iterations,
+            resid_norm,
+            reason,
+            history,
+        }
     }
 }
@@ -0,0 +1,283 @@
+#![allow(non_snake_case)]
+#![allow(clippy::many_single_char_names)]
+
+use crate::monitor::{Monitor, MonitorAction};
+use crate::operator::{check_shape, LinearOperator};
+use crate::solver::{IterativeSolver, SolveReport};
+use crate::stopping::{IterInfo, StopReason, StoppingCriterion};
+use crate::utils::{inner, norm, ComplexOrReal};
+use ndarray::{Array1, ArrayView1};
+use num_traits::Float;
+use std::time::Instant;
+
+/// State of BiCGStab(ℓ) (Sleijpen and Fokkema), which replaces the degree-one
+/// minimal residual polynomial of BiCGStab by one of degree `ell`. This
+/// avoids the stagnation of BiCGStab when `A` has eigenvalues with large
+/// imaginary parts. One step does `ell` BiCG steps, i.e. `2 ell` products
+/// with `A`. The preconditioner `M ≈ A^-1` is applied from the right, so
+/// `res` is the residual of the original system.
+pub struct BiCGStabLState<T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    pub ell: usize,
+    pub x: Array1<T>,
+    pub res: Array1<T>,
+    /// the shadow residual `r~`
+    pub res_shadow: Array1<T>,
+    /// search direction, in the preconditioned space
+    pub u: Array1<T>,
+    pub rho: T,
+    pub alpha: T,
+    pub omega: T,
+    pub tol: U,
+    pub converged: bool,
+}
+
+fn apply_precond<T>(M: Option<&dyn LinearOperator<T>>, x: Array1<T>) -> Array1<T> {
+    match M {
+        Some(M) => M.apply(x.view()),
+        None => x,
+    }
+}
+
+pub fn bicgstabl_iter<T, U>(
+    lhs: &dyn LinearOperator<T>,
+    M: Option<&dyn LinearOperator<T>>,
+    s_last: &BiCGStabLState<T, U>,
+) -> Option<BiCGStabLState<T, U>>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    let ell = s_last.ell;
+    // `A M` applied to `v`
+    let op = |v: &Array1<T>| lhs.apply(apply_precond(M, v.clone()).view());
+    let rs = &s_last.res_shadow;
+    let mut r = vec![s_last.res.clone()];
+    let mut u = vec![s_last.u.clone()];
+    // the update of x in the preconditioned space
+    let mut y = Array1::<T>::zeros(s_last.x.len());
+    let mut rho0 = -s_last.omega * s_last.rho;
+    let mut alpha = s_last.alpha;
+
+    // BiCG part
+    for j in 0..ell {
+        let rho1 = inner(rs.view(), r[j].view());
+        if rho0 == T::zero() {
+            return None;
+        }
+        let beta = alpha * rho1 / rho0;
+        rho0 = rho1;
+        for i in 0..=j {
+            u[i] = &r[i] - &(&u[i] * beta);
+        }
+        u.push(op(&u[j]));
+        let gamma = inner(rs.view(), u[j + 1].view());
+        if gamma == T::zero() {
+            return None;
+        }
+        alpha = rho0 / gamma;
+        for i in 0..=j {
+            r[i] = &r[i] - &(&u[i + 1] * alpha);
+        }
+        r.push(op(&r[j]));
+        y = y + &(&u[0] * alpha);
+    }
+
+    // MR part: modified Gram-Schmidt on r[1..=ell]
+    let mut tau = vec![vec![T::zero(); ell + 1]; ell + 1];
+    let mut sigma = vec![T::zero(); ell + 1];
+    let mut gamma1 = vec![T::zero(); ell + 1];
+    for j in 1..=ell {
+        for i in 1..j {
+            tau[i][j] = inner(r[i].view(), r[j].view()) / sigma[i];
+            r[j] = &r[j] - &(&r[i] * tau[i][j]);
+        }
+        sigma[j] = inner(r[j].view(), r[j].view());
+        if sigma[j] == T::zero() {
+            return None;
+        }
+        gamma1[j] = inner(r[j].view(), r[0].view()) / sigma[j];
+    }
+    let mut gamma = vec![T::zero(); ell + 1];
+    gamma[ell] = gamma1[ell];
+    for j in (1..ell).rev() {
+        gamma[j] = gamma1[j];
+        for i in j + 1..=ell {
+            gamma[j] = gamma[j] - tau[j][i] * gamma[i];
+        }
+    }
+    let mut gamma2 = vec![T::zero(); ell + 1];
+    for j in 1..ell {
+        gamma2[j] = gamma[j + 1];
+        for i in j + 1..ell {
+            gamma2[j] = gamma2[j] + tau[j][i] * gamma[i + 1];
+        }
+    }
+    let omega = gamma[ell];
+
+    y = y + &(&r[0] * gamma[1]);
+    let mut res = &r[0] - &(&r[ell] * gamma1[ell]);
+    let mut u0 = &u[0] - &(&u[ell] * gamma[ell]);
+    for j in 1..ell {
+        u0 = u0 - &(&u[j] * gamma[j]);
+        y = y + &(&r[j] * gamma2[j]);
+        res = res - &(&r[j] * gamma1[j]);
+    }
+    let x = &s_last.x + &apply_precond(M, y);
+    let converged = norm(res.view()) < s_last.tol;
+
+    Some(BiCGStabLState {
+        ell,
+        x,
+        res,
+        res_shadow: s_last.res_shadow.clone(),
+        u: u0,
+        rho: rho0,
+        alpha,
+        omega,
+        tol: s_last.tol,
+        converged,
+    })
+}
+
+impl<T, U> BiCGStabLState<T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    pub fn new(
+        lhs: &dyn LinearOperator<T>,
+        x: ArrayView1<T>,
+        b: ArrayView1<T>,
+        ell: usize,
+        tol: U,
+    ) -> BiCGStabLState<T, U> {
+        assert!(ell >= 1, "BiCGStab(ℓ) needs ℓ >= 1");
+        check_shape(lhs, b.len(), x.len());
+        let res = &b - &lhs.apply(x);
+        let converged = norm(res.view()) < tol;
+        BiCGStabLState {
+            ell,
+            x: x.to_owned(),
+            res_shadow: res.clone(),
+            u: Array1::zeros(res.len()),
+            res,
+            rho: T::one(),
+            alpha: T::zero(),
+            omega: T::one(),
+            tol,
+            converged,
+        }
+    }
+
+    /// Returns `None` (and keeps the old state) on a breakdown of the BiCG or
+    /// the minimal residual part.
+    pub fn next(
+        &mut self,
+        lhs: &dyn LinearOperator<T>,
+        M: Option<&dyn LinearOperator<T>>,
+    ) -> Option<()> {
+        let ns = bicgstabl_iter(lhs, M, self)?;
+        if ns.valid() {
+            *self = ns;
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    pub fn calc_resid(&self, lhs: &dyn LinearOperator<T>, b: &Array1<T>) -> Array1<T> {
+        b - &lhs.apply(self.x.view())
+    }
+
+    pub fn converged(&self, lhs: &dyn LinearOperator<T>, b: &Array1<T>, th: U) -> bool {
+        norm(self.calc_resid(lhs, b).view()) < th
+    }
+
+    pub fn valid(&self) -> bool {
+        self.x.iter().all(|x| x.abs().is_finite())
+            && self.res.iter().all(|x| x.abs().is_finite())
+            && self.u.iter().all(|x| x.abs().is_finite())
+            && self.omega.abs().is_finite()
+    }
+}
+
+/// BiCGStab(ℓ) as an `IterativeSolver`; an iteration is one step of
+/// `BiCGStabLState`.
+pub struct BiCGStabL<'a, T> {
+    pub ell: usize,
+    pub M: Option<&'a dyn LinearOperator<T>>,
+}
+
+impl<'a, T> BiCGStabL<'a, T> {
+    pub fn new(ell: usize) -> BiCGStabL<'a, T> {
+        BiCGStabL { ell, M: None }
+    }
+
+    /// right preconditioning by `M`
+    pub fn with_preconditioner(mut self, M: &'a dyn LinearOperator<T>) -> BiCGStabL<'a, T> {
+        self.M = Some(M);
+        self
+    }
+}
+
+impl<'a, T, U> IterativeSolver<T, U> for BiCGStabL<'a, T>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    type State = BiCGStabLState<T, U>;
+
+    fn solve_with_monitor(
+        &self,
+        A: &dyn LinearOperator<T>,
+        b: ArrayView1<T>,
+        x0: ArrayView1<T>,
+        criterion: &StoppingCriterion<U>,
+        monitor: &mut dyn Monitor<BiCGStabLState<T, U>, U>,
+    ) -> SolveReport<T, U> {
+        let start = Instant::now();
+        let b = b.to_owned();
+        let b_norm = norm(b.view());
+        let mut state = BiCGStabLState::new(A, x0, b.view(), self.ell, U::zero());
+        let r0_norm = norm(state.res.view());
+        let mut history = vec![r0_norm];
+        let mut iterations = 0;
+        let reason = loop {
+            let info = IterInfo::new(
+                iterations,
+                history[iterations],
+                r0_norm,
+                b_norm,
+                start.elapsed(),
+            );
+            if let Some(reason) = criterion.check(&info) {
+                break reason;
+            }
+            if state.next(A, self.M).is_none() {
+                break StopReason::Breakdown;
+            }
+            iterations += 1;
+            let r = norm(state.res.view());
+            history.push(r);
+            if monitor.observe(iterations, r, &state) == MonitorAction::Abort {
+                break StopReason::Aborted;
+            }
+        };
+        let resid_norm = norm(state.calc_resid(A, &b).view());
+        let reason = match reason {
+            StopReason::Breakdown => criterion.breakdown_reason(resid_norm, r0_norm, b_norm),
+            _ => reason,
+        };
+        SolveReport {
+            x: state.x,
+            iterations,
+            resid_norm,
+            reason,
+            history,
+        }
+    }
+}
@@ -8,6 +8,8 @@ extern crate sprs;
 use linear_solver::io::RawMM;
 use linear_solver::minres::agmres::agmres;
 use linear_solver::minres::agmres::AGmresState;
+use linear_solver::minres::Preconditioning;
+use linear_solver::operator::FnOperator;
 use linear_solver::utils::sp_mul_a1;
 use ndarray::Array1;
 use ndarray::{array, ArrayView1};
@@ -26,17 +28,32 @@ fn main() {
     let b = sp_mul_a1(&a, x0.view());
     println!("{:?}", b);
 
-    let A = |x: ArrayView1<Complex<f64>>| -> Array1<Complex<f64>> {
-        //a.dot(&x.to_owned())
-        sp_mul_a1(&a, x)
-    };
+    let A = FnOperator::square(
+        a.cols(),
+        |x: ArrayView1<Complex<f64>>| -> Array1<Complex<f64>> {
+            //a.dot(&x.to_owned())
+            sp_mul_a1(&a, x)
+        },
+    );
     let mut x = Array1::<Complex<f64>>::from(vec![Complex::from(10.0); a.cols()]);
-    let M = |x: ArrayView1<Complex<f64>>| -> Array1<Complex<f64>> { x.to_owned() };
-    let tol = 1e-20;
+    let M = FnOperator::square(
+        a.cols(),
+        |x: ArrayView1<Complex<f64>>| -> Array1<Complex<f64>> { x.to_owned() },
+    );
+    let tol = 1e-10;
 
     //let r=agmres(&A, &mut x, b.view(), &M, 925, 10, 1, 1, 0.4, &mut tol);
-    let mut ags =
-        AGmresState::<Complex<f64>, f64>::new(&A, x.view(), b.view(), Some(&M), 30, 1, 1, 0.4, tol);
+    let mut ags = AGmresState::<Complex<f64>, f64>::new(
+        &A,
+        x.view(),
+        b.view(),
+        Preconditioning::Left(&M),
+        30,
+        1,
+        1,
+        0.4,
+        tol,
+    );
 
     x.fill(Complex::<f64>::new(100., 0.));
     let mut cnt = 0;
@@ -45,7 +62,7 @@ fn main() {
         if cnt % 100 == 0 {
             println!("{}", ags.resid);
         }
-        ags.next(&A, Some(&M));
+        ags.next(&A, Preconditioning::Left(&M));
     }
 
     //println!("r={}", r);
@@ -8,6 +8,8 @@ extern crate sprs;
 use linear_solver::io::RawMM;
 use linear_solver::minres::agmres::agmres;
 use linear_solver::minres::agmres::AGmresState;
+use linear_solver::minres::Preconditioning;
+use linear_solver::operator::FnOperator;
 use linear_solver::utils::sp_mul_a1;
 use ndarray::Array1;
 use ndarray::{array, ArrayView1};
@@ -21,17 +23,28 @@ fn main() {
     let b = a.dot(&x0);
     println!("{:?}", b);
 
-    let A = |x: ArrayView1<f64>| -> Array1<f64> {
+    let A = FnOperator::square(a.ncols(), |x: ArrayView1<f64>| -> Array1<f64> {
         //a.dot(&x.to_owned())
         a.dot(&x)
-    };
+    });
     let x = Array1::<f64>::from(vec![1.0; a.ncols()]);
-    let M = |x: ArrayView1<f64>| -> Array1<f64> { x.to_owned() };
-    let tol = 1e-20;
+    let M = FnOperator::square(a.ncols(), |x: ArrayView1<f64>| -> Array1<f64> {
+        x.to_owned()
+    });
+    let tol = 1e-10;
 
     //let r=agmres(&A, &mut x, b.view(), &M, 925, 10, 1, 1, 0.4, &mut tol);
-    let mut ags =
-        AGmresState::<f64, f64>::new(&A, x.view(), b.view(), Some(&M), 30, 1, 1, 0.4, tol);
+    let mut ags = AGmresState::<f64, f64>::new(
+        &A,
+        x.view(),
+        b.view(),
+        Preconditioning::Left(&M),
+        30,
+        1,
+        1,
+        0.4,
+        tol,
+    );
 
     //x.fill(100.);
     let mut cnt = 0;
@@ -40,7 +53,7 @@ fn main() {
         if cnt % 10 == 0 {
             println!("{} {}", ags.tol, ags.resid);
         }
-        ags.next(&A, Some(&M));
+        ags.next(&A, Preconditioning::Left(&M));
     }
 
     //println!("r={}", r);
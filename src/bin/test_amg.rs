@@ -0,0 +1,76 @@
+extern crate linear_solver;
+extern crate ndarray;
+extern crate sprs;
+
+use linear_solver::amg::{Amg, AmgParams, Cycle, Smoother};
+use linear_solver::cg::Cg;
+use linear_solver::solver::IterativeSolver;
+use linear_solver::stopping::StoppingCriterion;
+use ndarray::Array1;
+
+fn main() {
+    // 3D Laplacian on a 20x20x20 grid
+    let m = 20;
+    let n = m * m * m;
+    let mut a = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..m {
+        for j in 0..m {
+            for k in 0..m {
+                let p = (i * m + j) * m + k;
+                a.add_triplet(p, p, 6.0);
+                for &(q, ok) in &[
+                    (p.wrapping_sub(m * m), i > 0),
+                    (p + m * m, i + 1 < m),
+                    (p.wrapping_sub(m), j > 0),
+                    (p + m, j + 1 < m),
+                    (p.wrapping_sub(1), k > 0),
+                    (p + 1, k + 1 < m),
+                ] {
+                    if ok {
+                        a.add_triplet(p, q, -1.0);
+                    }
+                }
+            }
+        }
+    }
+    let a = a.to_csr();
+    let b = Array1::<f64>::from(vec![1.0; n]);
+    let x0 = Array1::<f64>::zeros(n);
+    let criterion = StoppingCriterion::new(200).with_rel_tol(1e-8);
+
+    for &(cycle, smoother) in &[
+        (Cycle::V, Smoother::GaussSeidel),
+        (Cycle::W, Smoother::GaussSeidel),
+        (Cycle::V, Smoother::Jacobi(2.0 / 3.0)),
+    ] {
+        let amg = Amg::new(
+            &a,
+            AmgParams::new()
+                .with_cycle(cycle)
+                .with_smoother(smoother, 1, 1),
+        )
+        .unwrap();
+        let report = amg.solve(&a, b.view(), x0.view(), &criterion);
+        println!(
+            "{:?} {:?}: {} levels, operator complexity {:.2}, grid complexity {:.2}",
+            cycle,
+            smoother,
+            amg.num_levels(),
+            amg.operator_complexity(),
+            amg.grid_complexity()
+        );
+        println!(
+            "    standalone: {} iterations, {}, resid={:e}",
+            report.iterations, report.reason, report.resid_norm
+        );
+        let report = Cg::new()
+            .with_preconditioner(&amg)
+            .solve(&a, b.view(), x0.view(), &criterion);
+        println!(
+            "    CG: {} iterations, {}, resid={:e}",
+            report.iterations, report.reason, report.resid_norm
+        );
+    }
+    let report = Cg::new().solve(&a, b.view(), x0.view(), &criterion);
+    println!("plain CG: {} iterations", report.iterations);
+}
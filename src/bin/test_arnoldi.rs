@@ -2,13 +2,16 @@
 extern crate linear_solver;
 use linear_solver::arnoldi::ArnoldiSpace;
 use linear_solver::io::RawMM;
+use linear_solver::operator::FnOperator;
 use num_complex::Complex;
 pub fn main() {
     let Am = RawMM::<f64>::from_file("A.mtx")
         .to_array2()
         .map(|&x| Complex::from(x));
 
-    let A = |x: ndarray::ArrayView1<Complex<f64>>| Am.dot(&x);
+    let A = FnOperator::square(Am.nrows(), |x: ndarray::ArrayView1<Complex<f64>>| {
+        Am.dot(&x)
+    });
 
     let b = RawMM::<f64>::from_file("b.mtx")
         .to_array1()
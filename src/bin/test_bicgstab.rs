@@ -3,7 +3,6 @@ extern crate ndarray;
 extern crate sprs;
 
 use linear_solver::bicgstab::BiCGStabState;
-use linear_solver::utils::sp_mul_a1;
 use ndarray::{Array1, ArrayView1};
 
 fn main() {
@@ -21,15 +20,10 @@ fn main() {
     println!("{:?}", b);
     println!("{}", (x0.dot(&x0)).sqrt());
 
-    let mut aa = BiCGStabState::new(
-        &|x| sp_mul_a1(&a, x.view()),
-        ArrayView1::from(&[1., 1.]),
-        b.view(),
-        1e-25,
-    );
+    let mut aa = BiCGStabState::new(&a, ArrayView1::from(&[1., 1.]), b.view(), 1e-25);
 
     while !aa.converged {
-        let result = aa.next(&|x| sp_mul_a1(&a, x.view()));
+        let result = aa.next(&a);
 
         if result.is_none() {
             break;
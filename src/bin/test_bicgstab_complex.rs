@@ -0,0 +1,47 @@
+extern crate linear_solver;
+extern crate ndarray;
+extern crate num_complex;
+extern crate sprs;
+
+use linear_solver::bicgstab::BiCGStab;
+use linear_solver::minres::gmres::Gmres;
+use linear_solver::solver::IterativeSolver;
+use linear_solver::stopping::StoppingCriterion;
+use ndarray::Array1;
+use num_complex::Complex64;
+
+fn main() {
+    // 1D Helmholtz -u'' - k^2 u with a complex shift and a convection term,
+    // complex non-Hermitian
+    let n = 200;
+    let h = 1.0 / (n + 1) as f64;
+    let k = 10.0;
+    let diag = Complex64::new(2.0 - (k * h).powi(2), 0.5 * (k * h).powi(2));
+    let mut a = sprs::TriMat::<Complex64>::new((n, n));
+    for i in 0..n {
+        a.add_triplet(i, i, diag);
+        if i + 1 < n {
+            a.add_triplet(i, i + 1, Complex64::new(-1.0, 0.2));
+            a.add_triplet(i + 1, i, Complex64::new(-1.0, -0.1));
+        }
+    }
+    let a = a.to_csr();
+    let b = Array1::from(
+        (0..n)
+            .map(|i| Complex64::new(1.0, i as f64 * h))
+            .collect::<Vec<_>>(),
+    );
+    let x0 = Array1::<Complex64>::zeros(n);
+    let criterion = StoppingCriterion::new(2000).with_rel_tol(1e-10);
+
+    let report = BiCGStab.solve(&a, b.view(), x0.view(), &criterion);
+    println!(
+        "bicgstab: {} iterations, {}, resid={:e}",
+        report.iterations, report.reason, report.resid_norm
+    );
+    let reference = Gmres::new(n).solve(&a, b.view(), x0.view(), &criterion);
+    let diff = (&report.x - &reference.x)
+        .iter()
+        .fold(0.0f64, |m, d| m.max(d.norm()));
+    println!("max |x_bicgstab - x_gmres| = {:e}", diff);
+}
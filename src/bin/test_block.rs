@@ -0,0 +1,132 @@
+#![allow(non_snake_case)]
+extern crate linear_solver;
+extern crate ndarray;
+extern crate sprs;
+
+use linear_solver::block::{BlockCg, BlockGmres, BlockIterativeSolver, BlockSolveReport};
+use linear_solver::cg::Cg;
+use linear_solver::minres::Gmres;
+use linear_solver::operator::LinearOperator;
+use linear_solver::solver::IterativeSolver;
+use linear_solver::stopping::StoppingCriterion;
+use ndarray::{Array1, Array2};
+
+/// 2D convection-diffusion on an m x m grid, central differences with
+/// convection `c` in x; symmetric positive definite for `c = 0`
+fn convection_diffusion(m: usize, c: f64) -> sprs::CsMat<f64> {
+    let n = m * m;
+    let mut a = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..m {
+        for j in 0..m {
+            let p = i * m + j;
+            a.add_triplet(p, p, 4.0);
+            if i > 0 {
+                a.add_triplet(p, p - m, -1.0);
+            }
+            if i + 1 < m {
+                a.add_triplet(p, p + m, -1.0);
+            }
+            if j > 0 {
+                a.add_triplet(p, p - 1, -1.0 - c);
+            }
+            if j + 1 < m {
+                a.add_triplet(p, p + 1, -1.0 + c);
+            }
+        }
+    }
+    a.to_csr()
+}
+
+fn random_block(n: usize, p: usize) -> Array2<f64> {
+    let mut seed: u64 = 2718;
+    Array2::from_shape_fn((n, p), |_| {
+        seed = seed
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (seed >> 11) as f64 / (1u64 << 53) as f64 - 0.5
+    })
+}
+
+fn summary(name: &str, r: &BlockSolveReport<f64, f64>) {
+    let worst = r.resid_norms.iter().cloned().fold(0.0, f64::max);
+    println!(
+        "{}: {} block iterations, {} operator applications, converged: {}, max resid={:e}",
+        name,
+        r.iterations,
+        r.applications,
+        r.converged(),
+        worst
+    );
+}
+
+fn main() {
+    let m = 32;
+    let n = m * m;
+    let p = 16;
+    let criterion = StoppingCriterion::new(2000).with_rel_tol(1e-8);
+    let B = random_block(n, p);
+    let X0 = Array2::<f64>::zeros((n, p));
+    let x0 = Array1::<f64>::zeros(n);
+
+    // symmetric positive definite: one CG per column against block CG
+    let a = convection_diffusion(m, 0.0);
+    let applications: usize = B
+        .gencolumns()
+        .into_iter()
+        .map(|b| Cg::new().solve(&a, b, x0.view(), &criterion).iterations + 1)
+        .sum();
+    println!(
+        "cg column by column: {} operator applications",
+        applications
+    );
+    summary(
+        "block cg",
+        &BlockCg::new().solve(&a, B.view(), X0.view(), &criterion),
+    );
+
+    // the second half of the right hand sides depends on the first half;
+    // deflation keeps the search block at rank 8
+    let mut Bdep = B.clone();
+    for j in p / 2..p {
+        let col = &B.column(j - p / 2) * 2.0 - B.column(j - p / 2 + 1);
+        Bdep.column_mut(j).assign(&col);
+    }
+    summary(
+        "block cg, dependent rhs",
+        &BlockCg::new().solve(&a, Bdep.view(), X0.view(), &criterion),
+    );
+
+    // nonsymmetric: GMRES(30) per column against block GMRES(30)
+    let a = convection_diffusion(m, 0.5);
+    let applications: usize = B
+        .gencolumns()
+        .into_iter()
+        .map(|b| {
+            Gmres::new(30)
+                .solve(&a, b, x0.view(), &criterion)
+                .iterations
+        })
+        .sum();
+    println!(
+        "gmres(30) column by column: {} operator applications",
+        applications
+    );
+    summary(
+        "block gmres(30)",
+        &BlockGmres::new(30).solve(&a, B.view(), X0.view(), &criterion),
+    );
+    summary(
+        "block gmres(30), dependent rhs",
+        &BlockGmres::new(30).solve(&a, Bdep.view(), X0.view(), &criterion),
+    );
+
+    // an exact start without a residual test needs no iterations
+    let X = BlockGmres::new(30)
+        .solve(&a, B.view(), X0.view(), &criterion)
+        .X;
+    let B = a.apply_block(X.view());
+    let r = BlockGmres::new(30).solve(&a, B.view(), X.view(), &StoppingCriterion::new(5));
+    summary("block gmres(30), exact start", &r);
+    assert!(r.iterations == 0 && r.converged());
+    assert!(r.resid_norms.iter().all(|&r| r == 0.0));
+}
@@ -0,0 +1,179 @@
+#![allow(non_snake_case)]
+extern crate linear_solver;
+extern crate ndarray;
+extern crate num_complex;
+extern crate sprs;
+
+use linear_solver::arnoldi::ArnoldiSpace;
+use linear_solver::eigen::qr::qr_naive_eigvals;
+use linear_solver::minres::{AGmres, Fgmres, GcroDr, Gmres, GmresDr};
+use linear_solver::operator::{FnOperator, LinearOperator};
+use linear_solver::solver::IterativeSolver;
+use linear_solver::stopping::{StopReason, StoppingCriterion};
+use ndarray::{Array1, ArrayView1};
+use num_complex::Complex;
+
+fn main() {
+    // diagonal 1..=n; a right hand side with 5 nonzero entries lies in a
+    // 5-dimensional invariant subspace, so the Arnoldi process breaks down
+    // after 5 steps with the exact solution
+    let n = 200;
+    let mut a = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..n {
+        a.add_triplet(i, i, (i + 1) as f64);
+    }
+    let a = a.to_csr();
+    let mut b = Array1::<f64>::zeros(n);
+    for &i in &[3, 17, 50, 120, 199] {
+        b[i] = 1.0;
+    }
+    let x0 = Array1::<f64>::zeros(n);
+
+    // an eigenvector: h_21 is exactly zero after the first step
+    let mut e = Array1::<f64>::zeros(n);
+    e[7] = 1.0;
+    let criterion = StoppingCriterion::new(1000).with_rel_tol(0.0);
+    let r = Gmres::new(30).solve(&a, e.view(), x0.view(), &criterion);
+    println!(
+        "gmres(30), eigenvector rhs: {} iterations, {}, x[7]={}, resid={:e}",
+        r.iterations, r.reason, r.x[7], r.resid_norm
+    );
+
+    let criterion = StoppingCriterion::new(1000).with_rel_tol(1e-14);
+
+    let r = Gmres::new(30).solve(&a, b.view(), x0.view(), &criterion);
+    println!(
+        "gmres(30): {} iterations, {}, resid={:e}",
+        r.iterations, r.reason, r.resid_norm
+    );
+    let r = AGmres::new(30, 5, 5, 0.9).solve(&a, b.view(), x0.view(), &criterion);
+    println!(
+        "agmres(30): {} iterations, {}, resid={:e}",
+        r.iterations, r.reason, r.resid_norm
+    );
+
+    // started at the exact solution with no residual test: no iterations
+    let x = Array1::<f64>::from(vec![1.0; n]);
+    let bx = a.apply(x.view());
+    let max5 = StoppingCriterion::new(5);
+    let reports = [
+        (
+            "gmres(30)",
+            Gmres::new(30).solve(&a, bx.view(), x.view(), &max5),
+        ),
+        (
+            "agmres(30)",
+            AGmres::new(30, 5, 5, 0.9).solve(&a, bx.view(), x.view(), &max5),
+        ),
+    ];
+    for (name, r) in reports.iter() {
+        println!(
+            "{}, exact start: {} iterations, {}, resid={:e}",
+            name, r.iterations, r.reason, r.resid_norm
+        );
+        assert!(r.iterations == 0 && r.reason == StopReason::ExactSolution);
+    }
+
+    // a restart length beyond the problem size ends in a breakdown once the
+    // basis fills the whole space
+    let b = Array1::<f64>::from(vec![1.0; n]);
+    let criterion = StoppingCriterion::new(1000).with_rel_tol(0.0);
+    let r = Gmres::new(2 * n).solve(&a, b.view(), x0.view(), &criterion);
+    println!(
+        "gmres(2n) with rel_tol 0: {} iterations, {}, resid={:e}",
+        r.iterations, r.reason, r.resid_norm
+    );
+
+    // a tiny component outside the invariant subspace: a nonzero breakdown
+    // threshold stops there instead of resolving it
+    let mut b = Array1::<f64>::zeros(n);
+    for &i in &[3, 17, 50, 120, 199] {
+        b[i] = 1.0;
+    }
+    b[80] = 1e-12;
+    let criterion = StoppingCriterion::new(1000).with_rel_tol(1e-15);
+    for &tol in &[0.0, 1e-10] {
+        let r = Gmres::new(30)
+            .with_breakdown_tol(tol)
+            .solve(&a, b.view(), x0.view(), &criterion);
+        println!(
+            "gmres(30), breakdown_tol {:e}: {} iterations, {}, resid={:e}",
+            tol, r.iterations, r.reason, r.resid_norm
+        );
+    }
+
+    // the restarted variants stop at the breakdown as well
+    for &tol in &[0.0, 1e-10] {
+        let r =
+            GmresDr::new(30, 5)
+                .with_breakdown_tol(tol)
+                .solve(&a, b.view(), x0.view(), &criterion);
+        println!(
+            "gmres-dr(30, 5), breakdown_tol {:e}: {} iterations, {}, resid={:e}",
+            tol, r.iterations, r.reason, r.resid_norm
+        );
+        let r =
+            GcroDr::new(30, 5)
+                .with_breakdown_tol(tol)
+                .solve(&a, b.view(), x0.view(), &criterion);
+        println!(
+            "gcro-dr(30, 5), breakdown_tol {:e}: {} iterations, {}, resid={:e}",
+            tol, r.iterations, r.reason, r.resid_norm
+        );
+        let r = Fgmres::new(30)
+            .with_breakdown_tol(tol)
+            .solve(&a, b.view(), x0.view(), &criterion);
+        println!(
+            "fgmres(30), breakdown_tol {:e}: {} iterations, {}, resid={:e}",
+            tol, r.iterations, r.reason, r.resid_norm
+        );
+    }
+
+    // the same with a left preconditioner: the inexact breakdown must not be
+    // reported as convergence of the preconditioned residual
+    let M = FnOperator::square(n, |x: ArrayView1<f64>| {
+        Array1::from_shape_fn(n, |i| x[i] / (1.0 + 0.5 * i as f64 / n as f64))
+    });
+    for &tol in &[0.0, 1e-10] {
+        let r = Gmres::new(30)
+            .with_preconditioner(&M)
+            .with_breakdown_tol(tol)
+            .solve(&a, b.view(), x0.view(), &criterion);
+        println!(
+            "left preconditioned gmres(30), breakdown_tol {:e}: {} iterations, {}, resid={:e}",
+            tol, r.iterations, r.reason, r.resid_norm
+        );
+        let r = AGmres::new(30, 5, 5, 0.9)
+            .with_preconditioner(&M)
+            .with_breakdown_tol(tol)
+            .solve(&a, b.view(), x0.view(), &criterion);
+        println!(
+            "left preconditioned agmres(30), breakdown_tol {:e}: {} iterations, {}, resid={:e}",
+            tol, r.iterations, r.reason, r.resid_norm
+        );
+    }
+
+    // implicitly restarted Arnoldi stops at the invariant subspace; its
+    // Ritz values are exact eigenvalues
+    let ac = a.map(|&x| Complex::new(x, 0.0));
+    let mut arn = ArnoldiSpace::<f64, f64>::new(b.view())
+        .with_breakdown_tol(1e-10)
+        .to_complex();
+    for _ in 0..3 {
+        arn.iter(&ac).unwrap();
+    }
+    for _ in 0..5 {
+        arn.restart(&ac, 4, 1e-12).unwrap();
+    }
+    let (V, H) = arn.invariant_subspace().expect("no invariant subspace");
+    let mut eigs: Vec<f64> = qr_naive_eigvals(H.view(), 1e-12)
+        .iter()
+        .map(|l| l.re)
+        .collect();
+    eigs.sort_by(|x, y| x.partial_cmp(y).unwrap());
+    println!(
+        "iram: invariant subspace of dimension {}, eigenvalues {:?}",
+        V.ncols(),
+        eigs
+    );
+}
@@ -0,0 +1,27 @@
+extern crate linear_solver;
+extern crate ndarray;
+extern crate sprs;
+
+use linear_solver::cg::CgState;
+use ndarray::{Array1, ArrayView1};
+
+fn main() {
+    let a = sprs::CsMatI::<f64, usize>::new(
+        (3, 3),
+        vec![0, 2, 5, 7],
+        vec![0, 1, 0, 1, 2, 1, 2],
+        vec![4.0, 1.0, 1.0, 3.0, -1.0, -1.0, 2.0],
+    );
+    let b = Array1::<f64>::from(vec![1., 2., 3.]);
+    println!("{:?}", b);
+
+    let mut cg = CgState::new(&a, ArrayView1::from(&[0., 0., 0.]), b.view(), None, 1e-12);
+
+    while !cg.converged {
+        if cg.next(&a, None).is_none() {
+            break;
+        }
+        println!("{:?}", cg.x);
+    }
+    println!("{:?}", cg.eig_estimate());
+}
@@ -0,0 +1,126 @@
+extern crate linear_solver;
+extern crate ndarray;
+extern crate sprs;
+
+use linear_solver::cg::Cg;
+use linear_solver::cgls::Cgls;
+use linear_solver::cgne::Cgne;
+use linear_solver::craig::Craig;
+use linear_solver::lsqr::Lsqr;
+use linear_solver::operator::{LinearOperator, NormalOperator};
+use linear_solver::qr_solve;
+use linear_solver::solver::IterativeSolver;
+use linear_solver::stopping::StoppingCriterion;
+use ndarray::{Array1, Array2};
+
+fn sparse(m: usize, n: usize) -> sprs::CsMat<f64> {
+    let mut seed: u64 = 12345;
+    let mut rand = || {
+        seed = seed
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (seed >> 11) as f64 / (1u64 << 53) as f64
+    };
+    let mut a = sprs::TriMat::<f64>::new((m, n));
+    for i in 0..m {
+        for j in 0..n {
+            if i == j || rand() < 0.1 {
+                a.add_triplet(i, j, rand() - 0.5 + if i == j { 2.0 } else { 0.0 });
+            }
+        }
+    }
+    a.to_csr()
+}
+
+fn dense(a: &sprs::CsMat<f64>) -> Array2<f64> {
+    let mut d = Array2::zeros(a.shape());
+    for (&v, (i, j)) in a.iter() {
+        d[(i, j)] = v;
+    }
+    d
+}
+
+fn max_diff(x: &Array1<f64>, y: &Array1<f64>) -> f64 {
+    (x - y).iter().fold(0.0f64, |m, &v| m.max(v.abs()))
+}
+
+fn main() {
+    // overdetermined least squares: CGLS, LSQR and CG on A^T A agree
+    let (m, n) = (80, 40);
+    let a = sparse(m, n);
+    let b = Array1::from((0..m).map(|i| (i as f64).cos()).collect::<Vec<_>>());
+    let x0 = Array1::<f64>::zeros(n);
+    let ad = dense(&a);
+    let x_ls = qr_solve::solve(ad.t().dot(&ad).view(), ad.t().dot(&b).view());
+
+    let criterion = StoppingCriterion::new(500).with_paige_saunders(1e-12, 1e-12, 0.0);
+    let cgls = Cgls::new().solve(&a, b.view(), x0.view(), &criterion);
+    let lsqr = Lsqr::new().solve(&a, b.view(), x0.view(), &criterion);
+    let ata = NormalOperator::ata(&a);
+    let atb = a.apply_adjoint(b.view());
+    let cg = Cg::new().solve(
+        &ata,
+        atb.view(),
+        x0.view(),
+        &StoppingCriterion::rhs_relative(1e-12, 500),
+    );
+    for (name, report) in [("cgls", &cgls), ("lsqr", &lsqr), ("cg on A^T A", &cg)].iter() {
+        println!(
+            "{}: {} iterations, {}, max |x - x_ls| = {:e}",
+            name,
+            report.iterations,
+            report.reason,
+            max_diff(&report.x, &x_ls)
+        );
+    }
+    let damp = 0.5;
+    let cgls = Cgls::new()
+        .with_damping(damp)
+        .solve(&a, b.view(), x0.view(), &criterion);
+    let lsqr = Lsqr::new()
+        .with_damping(damp)
+        .solve(&a, b.view(), x0.view(), &criterion);
+    println!(
+        "damped: cgls {} iterations, lsqr {} iterations, max |x_cgls - x_lsqr| = {:e}",
+        cgls.iterations,
+        lsqr.iterations,
+        max_diff(&cgls.x, &lsqr.x)
+    );
+
+    // underdetermined consistent system: the minimum-norm solution
+    // x = A^T (A A^T)^-1 b
+    let (m, n) = (40, 90);
+    let a = sparse(m, n);
+    let b = Array1::from((0..m).map(|i| 1.0 + (i % 3) as f64).collect::<Vec<_>>());
+    let x0 = Array1::<f64>::zeros(n);
+    let ad = dense(&a);
+    let x_mn = ad
+        .t()
+        .dot(&qr_solve::solve(ad.dot(&ad.t()).view(), b.view()));
+    let criterion = StoppingCriterion::rhs_relative(1e-12, 500);
+    let cgne = Cgne.solve(&a, b.view(), x0.view(), &criterion);
+    let craig = Craig.solve(&a, b.view(), x0.view(), &criterion);
+    for (name, report) in [("cgne", &cgne), ("craig", &craig)].iter() {
+        println!(
+            "{}: {} iterations, {}, ‖r‖ = {:e}, max |x - x_min_norm| = {:e}",
+            name,
+            report.iterations,
+            report.reason,
+            report.resid_norm,
+            max_diff(&report.x, &x_mn)
+        );
+    }
+
+    // from x0 != 0 CRAIG gives the solution closest to x0
+    let x0 = Array1::from_elem(n, 1.0);
+    let craig = Craig.solve(&a, b.view(), x0.view(), &criterion);
+    let r0 = &b - &a.apply(x0.view());
+    let x_closest = &x0
+        + &ad
+            .t()
+            .dot(&qr_solve::solve(ad.dot(&ad.t()).view(), r0.view()));
+    println!(
+        "craig from x0 = 1: max |x - x_closest| = {:e}",
+        max_diff(&craig.x, &x_closest)
+    );
+}
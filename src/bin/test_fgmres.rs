@@ -0,0 +1,59 @@
+extern crate linear_solver;
+extern crate ndarray;
+extern crate sprs;
+
+use linear_solver::bicgstab::BiCGStab;
+use linear_solver::minres::{Fgmres, SolverPreconditioner};
+use linear_solver::monitor::HistoryMonitor;
+use linear_solver::operator::LinearOperator;
+use linear_solver::solver::IterativeSolver;
+use linear_solver::stopping::StoppingCriterion;
+use ndarray::Array1;
+
+fn main() {
+    let n = 100;
+    let mut a = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..n {
+        a.add_triplet(i, i, 4.0);
+        if i + 1 < n {
+            a.add_triplet(i, i + 1, -1.5);
+            a.add_triplet(i + 1, i, -0.5);
+        }
+    }
+    let a = a.to_csr();
+    let b = Array1::<f64>::from(vec![1.0; n]);
+    let x0 = Array1::<f64>::zeros(n);
+
+    // a few BiCGStab steps as the (varying) preconditioner
+    let inner = SolverPreconditioner::new(BiCGStab, &a, StoppingCriterion::new(3));
+    let solver = Fgmres::new(10).with_preconditioner(&inner);
+    let report = solver.solve(
+        &a,
+        b.view(),
+        x0.view(),
+        &StoppingCriterion::new(100).with_rel_tol(1e-10),
+    );
+    println!(
+        "{} iterations, {}, resid={:e}",
+        report.iterations, report.reason, report.resid_norm
+    );
+
+    // the monitor sees every step of a cycle
+    let criterion = StoppingCriterion::new(100).with_rel_tol(1e-10);
+    let mut monitor = HistoryMonitor::new();
+    let report = solver.solve_with_monitor(&a, b.view(), x0.view(), &criterion, &mut monitor);
+    let steps: Vec<usize> = monitor.history.iter().map(|&(i, _)| i).collect();
+    assert_eq!(steps, (1..=report.iterations).collect::<Vec<_>>());
+
+    // started at the exact solution with no residual test: FGMRES must not
+    // divide by the zero residual
+    let x = Array1::<f64>::from(vec![1.0; n]);
+    let b = a.apply(x.view());
+    let report = solver.solve(&a, b.view(), x.view(), &StoppingCriterion::new(5));
+    println!(
+        "exact start: {} iterations, {}, resid={:e}",
+        report.iterations, report.reason, report.resid_norm
+    );
+    assert!(report.x.iter().all(|v| v.is_finite()) && report.resid_norm == 0.0);
+    assert!(report.iterations == 0 && report.reason.is_converged());
+}
@@ -0,0 +1,89 @@
+extern crate linear_solver;
+extern crate ndarray;
+extern crate sprs;
+
+use linear_solver::minres::{GcroDr, Gmres, GmresDr};
+use linear_solver::operator::LinearOperator;
+use linear_solver::solver::IterativeSolver;
+use linear_solver::stopping::StoppingCriterion;
+use ndarray::Array1;
+
+/// upper bidiagonal with small eigenvalues 0.1, 0.2, 0.3 below the bulk
+/// 1..n, perturbed by `t` on the diagonal and the superdiagonal
+fn matrix(n: usize, t: f64) -> sprs::CsMat<f64> {
+    let mut a = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..n {
+        let d = if i < 3 {
+            0.1 * (i + 1) as f64
+        } else {
+            (i - 2) as f64
+        };
+        a.add_triplet(i, i, d * (1.0 + t * ((i as f64) * 0.37).sin()));
+        if i + 1 < n {
+            a.add_triplet(i, i + 1, 0.1 + t);
+        }
+    }
+    a.to_csr()
+}
+
+fn main() {
+    // a slowly changing sequence of systems with changing right hand sides
+    let n = 1000;
+    let (m, k) = (25, 8);
+    let x0 = Array1::<f64>::zeros(n);
+    let criterion = StoppingCriterion::new(20000).with_rel_tol(1e-10);
+    let gcrodr = GcroDr::new(m, k);
+    let mut totals = [0; 3];
+    for step in 0..8 {
+        let t = 0.01 * step as f64;
+        let a = matrix(n, t);
+        let b = Array1::from(
+            (0..n)
+                .map(|i| 1.0 + (0.1 * (i + step) as f64).cos())
+                .collect::<Vec<_>>(),
+        );
+        let gmres = Gmres::new(m).solve(&a, b.view(), x0.view(), &criterion);
+        let dr = GmresDr::new(m, k).solve(&a, b.view(), x0.view(), &criterion);
+        let rc = gcrodr.solve(&a, b.view(), x0.view(), &criterion);
+        println!(
+            "system {}: gmres({}) {}, gmres-dr({}, {}) {}, gcro-dr({}, {}) {} iterations ({}, resid={:e})",
+            step,
+            m,
+            gmres.iterations,
+            m,
+            k,
+            dr.iterations,
+            m,
+            k,
+            rc.iterations,
+            rc.reason,
+            rc.resid_norm
+        );
+        totals[0] += gmres.iterations;
+        totals[1] += dr.iterations;
+        totals[2] += rc.iterations;
+    }
+    println!(
+        "total: gmres {}, gmres-dr {}, gcro-dr {}",
+        totals[0], totals[1], totals[2]
+    );
+    let ritz: Vec<String> = gcrodr
+        .recycle_space()
+        .ritz
+        .iter()
+        .map(|t| format!("{:.4}", t.re))
+        .collect();
+    println!("recycled harmonic Ritz values: {}", ritz.join(" "));
+
+    // started at the exact solution with no residual test, reusing the space
+    let a = matrix(n, 0.0);
+    let x = Array1::<f64>::from(vec![1.0; n]);
+    let b = a.apply(x.view());
+    let rc = gcrodr.solve(&a, b.view(), x.view(), &StoppingCriterion::new(5));
+    println!(
+        "exact start: {} iterations, {}, resid={:e}",
+        rc.iterations, rc.reason, rc.resid_norm
+    );
+    assert!(rc.x.iter().all(|v| v.is_finite()) && rc.resid_norm == 0.0);
+    assert!(rc.iterations == 0 && rc.reason.is_converged());
+}
@@ -6,7 +6,8 @@ extern crate ndarray;
 extern crate sprs;
 
 use linear_solver::io::RawMM;
-use linear_solver::minres::gmres::GmresState;
+use linear_solver::minres::gmres::{GmresState, Preconditioning};
+use linear_solver::operator::FnOperator;
 use linear_solver::utils::sp_mul_a1;
 use ndarray::Array1;
 use ndarray::{array, ArrayView1};
@@ -20,16 +21,19 @@ fn main() {
     let b = sp_mul_a1(&a, x0.view());
     println!("{:?}", b);
 
-    let A = |x: ArrayView1<f64>| -> Array1<f64> {
+    let A = FnOperator::square(a.cols(), |x: ArrayView1<f64>| -> Array1<f64> {
         //a.dot(&x.to_owned())
         sp_mul_a1(&a, x)
-    };
+    });
     let mut x = Array1::<f64>::from(vec![10.0; a.cols()]);
-    let M = |x: ArrayView1<f64>| -> Array1<f64> { x.to_owned() };
-    let tol = 1e-20;
+    let M = FnOperator::square(a.cols(), |x: ArrayView1<f64>| -> Array1<f64> {
+        x.to_owned()
+    });
+    let tol = 1e-10;
 
     //let r=agmres(&A, &mut x, b.view(), &M, 925, 10, 1, 1, 0.4, &mut tol);
-    let mut ags = GmresState::<f64, f64>::new(&A, x.view(), b.view(), &M, 30, tol);
+    let mut ags =
+        GmresState::<f64, f64>::new(&A, x.view(), b.view(), Preconditioning::Left(&M), 30, tol);
 
     x.fill(100.);
     let mut cnt = 0;
@@ -38,7 +42,7 @@ fn main() {
         if cnt % 100 == 0 {
             println!("{}", ags.resid);
         }
-        ags.next(&A, Some(&M));
+        ags.next(&A, Preconditioning::Left(&M));
     }
 
     //println!("r={}", r);
@@ -0,0 +1,101 @@
+extern crate linear_solver;
+extern crate ndarray;
+extern crate num_complex;
+extern crate sprs;
+
+use linear_solver::minres::{Gmres, GmresDr, GmresDrState, Preconditioning};
+use linear_solver::monitor::{AbortMonitor, HistoryMonitor};
+use linear_solver::operator::LinearOperator;
+use linear_solver::solver::IterativeSolver;
+use linear_solver::stopping::{StopReason, StoppingCriterion};
+use ndarray::Array1;
+use num_complex::Complex;
+
+fn main() {
+    // upper bidiagonal with small eigenvalues 0.1, 0.2, 0.3 below the
+    // bulk 1..n; restarted GMRES has to rediscover them in every cycle
+    let n = 1000;
+    let diag = |i: usize| {
+        if i < 3 {
+            0.1 * (i + 1) as f64
+        } else {
+            (i - 2) as f64
+        }
+    };
+    let mut a = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..n {
+        a.add_triplet(i, i, diag(i));
+        if i + 1 < n {
+            a.add_triplet(i, i + 1, 0.1);
+        }
+    }
+    let a = a.to_csr();
+    let b = Array1::<f64>::from(vec![1.0; n]);
+    let x0 = Array1::<f64>::zeros(n);
+    let criterion = StoppingCriterion::new(20000).with_rel_tol(1e-10);
+
+    let (m, k) = (25, 8);
+    let gmres = Gmres::new(m).solve(&a, b.view(), x0.view(), &criterion);
+    println!(
+        "gmres({}): {} iterations, {}, resid={:e}",
+        m, gmres.iterations, gmres.reason, gmres.resid_norm
+    );
+    let dr = GmresDr::new(m, k).solve(&a, b.view(), x0.view(), &criterion);
+    println!(
+        "gmres-dr({}, {}): {} iterations, {}, resid={:e}",
+        m, k, dr.iterations, dr.reason, dr.resid_norm
+    );
+
+    // the monitor sees every new Arnoldi step, also after deflated restarts,
+    // and an abort ends the cycle at once
+    let mut monitor = HistoryMonitor::new();
+    let dr =
+        GmresDr::new(m, k).solve_with_monitor(&a, b.view(), x0.view(), &criterion, &mut monitor);
+    let steps: Vec<usize> = monitor.history.iter().map(|&(i, _)| i).collect();
+    assert_eq!(steps, (1..=dr.iterations).collect::<Vec<_>>());
+    let mut abort = AbortMonitor::new(|i: usize, _: f64| i == m + 7);
+    let dr = GmresDr::new(m, k).solve_with_monitor(&a, b.view(), x0.view(), &criterion, &mut abort);
+    println!(
+        "gmres-dr({}, {}) aborted by a monitor: {} iterations, {}",
+        m, k, dr.iterations, dr.reason
+    );
+    assert!(dr.iterations == m + 7 && dr.reason == StopReason::Aborted);
+
+    // after a few cycles the kept harmonic Ritz values approximate the
+    // smallest eigenvalues
+    let mut ags =
+        GmresDrState::<f64, f64>::new(&a, x0.view(), b.view(), Preconditioning::None, m, k, 0.0);
+    for _ in 0..10 {
+        ags.next(&a, Preconditioning::None);
+    }
+    let ritz: Vec<String> = ags.ritz.iter().map(|t| format!("{:.5}", t.re)).collect();
+    let exact: Vec<String> = (0..k).map(|i| format!("{:.5}", diag(i))).collect();
+    println!("harmonic Ritz values after 10 cycles: {}", ritz.join(" "));
+    println!("smallest eigenvalues:                 {}", exact.join(" "));
+
+    // the same problem in complex arithmetic, rotated by a phase
+    let phase = Complex::new(0.6, 0.8);
+    let mut ac = sprs::TriMat::<Complex<f64>>::new((n, n));
+    for (&v, (i, j)) in a.iter() {
+        ac.add_triplet(i, j, phase * v);
+    }
+    let ac = ac.to_csr();
+    let bc = Array1::from(vec![Complex::new(1.0, 0.0); n]);
+    let x0c = Array1::<Complex<f64>>::zeros(n);
+    let dr = GmresDr::new(m, k).solve(&ac, bc.view(), x0c.view(), &criterion);
+    println!(
+        "complex gmres-dr({}, {}): {} iterations, {}, resid={:e}",
+        m, k, dr.iterations, dr.reason, dr.resid_norm
+    );
+
+    // started at the exact solution with no residual test
+    let x = Array1::<f64>::from(vec![1.0; n]);
+    let b = a.apply(x.view());
+    let dr = GmresDr::new(m, k).solve(&a, b.view(), x.view(), &StoppingCriterion::new(5));
+    println!(
+        "exact start: {} iterations, {}, resid={:e}",
+        dr.iterations, dr.reason, dr.resid_norm
+    );
+    assert!(dr.x.iter().all(|v| v.is_finite()) && dr.resid_norm == 0.0);
+    assert!(dr.iterations == 0 && dr.reason.is_converged());
+}
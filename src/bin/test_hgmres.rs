@@ -0,0 +1,79 @@
+#![allow(non_snake_case)]
+extern crate linear_solver;
+extern crate ndarray;
+extern crate num_complex;
+extern crate sprs;
+
+use linear_solver::arnoldi::{ArnoldiSpace, Orthogonalization};
+use linear_solver::minres::Gmres;
+use linear_solver::solver::IterativeSolver;
+use linear_solver::stopping::StoppingCriterion;
+use linear_solver::utils::hermit;
+use ndarray::{Array1, Array2};
+use num_complex::Complex;
+
+/// upper bidiagonal with diagonal `1..=n`
+fn bidiagonal(n: usize) -> sprs::CsMat<f64> {
+    let mut a = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..n {
+        a.add_triplet(i, i, (i + 1) as f64);
+        if i + 1 < n {
+            a.add_triplet(i, i + 1, 0.1);
+        }
+    }
+    a.to_csr()
+}
+
+fn loss_of_orthogonality(arn: &ArnoldiSpace<f64, f64>) -> f64 {
+    let Q = arn.get_Q_star();
+    let E = hermit(Q.view()).dot(&Q) - Array2::<f64>::eye(Q.ncols());
+    E.iter().map(|x| x * x).sum::<f64>().sqrt()
+}
+
+fn main() {
+    let n = 100;
+    let a = bidiagonal(n);
+    let b = Array1::<f64>::from(vec![1.0; n]);
+    let x0 = Array1::<f64>::zeros(n);
+
+    for &orth in &[Orthogonalization::Mgs, Orthogonalization::Householder] {
+        let mut arn = ArnoldiSpace::<f64, f64>::new(b.view()).with_orthogonalization(orth);
+        for _ in 0..80 {
+            arn.iter(&a).unwrap();
+        }
+        println!(
+            "{:?}: ||I - Q^H Q|| after 80 steps = {:e}",
+            orth,
+            loss_of_orthogonality(&arn)
+        );
+    }
+
+    let criterion = StoppingCriterion::new(5000).with_rel_tol(1e-10);
+    for &orth in &[Orthogonalization::Mgs, Orthogonalization::Householder] {
+        let r =
+            Gmres::new(100)
+                .with_orthogonalization(orth)
+                .solve(&a, b.view(), x0.view(), &criterion);
+        println!(
+            "gmres(100), {:?}: {} iterations, {}, resid={:e}",
+            orth, r.iterations, r.reason, r.resid_norm
+        );
+    }
+
+    // the same with a complex shift
+    let ac = a.map(|&x| Complex::new(x, 0.0));
+    let mut shift = sprs::TriMat::<Complex<f64>>::new((n, n));
+    for i in 0..n {
+        shift.add_triplet(i, i, Complex::new(0.0, 1e-3));
+    }
+    let ac = &ac + &shift.to_csr();
+    let bc = b.map(|&x| Complex::new(x, 0.0));
+    let x0c = Array1::<Complex<f64>>::zeros(n);
+    let r = Gmres::new(100)
+        .with_orthogonalization(Orthogonalization::Householder)
+        .solve(&ac, bc.view(), x0c.view(), &criterion);
+    println!(
+        "complex gmres(100), Householder: {} iterations, {}, resid={:e}",
+        r.iterations, r.reason, r.resid_norm
+    );
+}
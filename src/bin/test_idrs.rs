@@ -0,0 +1,113 @@
+extern crate linear_solver;
+extern crate ndarray;
+extern crate num_complex;
+extern crate sprs;
+
+use linear_solver::bicgstab::BiCGStab;
+use linear_solver::bicgstabl::BiCGStabL;
+use linear_solver::idrs::Idrs;
+use linear_solver::precond::Ilu0;
+use linear_solver::solver::{IterativeSolver, SolveReport};
+use linear_solver::stopping::StoppingCriterion;
+use ndarray::Array1;
+use num_complex::Complex64;
+
+fn print_report<T>(name: &str, matvecs_per_iter: usize, report: &SolveReport<T, f64>) {
+    println!(
+        "{:>14}: {:5} products with A, {}, resid={:e}",
+        name,
+        report.iterations * matvecs_per_iter,
+        report.reason,
+        report.resid_norm
+    );
+}
+
+fn main() {
+    // central differences for -Δu + β·∇u on a 40x40 grid: the eigenvalues
+    // have large imaginary parts and plain BiCGStab stagnates
+    let m = 40;
+    let n = m * m;
+    let h = 1.0 / (m + 1) as f64;
+    let (bx, by) = (300.0, 150.0);
+    let mut a = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..m {
+        for j in 0..m {
+            let k = i * m + j;
+            a.add_triplet(k, k, 4.0);
+            if i > 0 {
+                a.add_triplet(k, k - m, -1.0 - by * h / 2.0);
+            }
+            if i + 1 < m {
+                a.add_triplet(k, k + m, -1.0 + by * h / 2.0);
+            }
+            if j > 0 {
+                a.add_triplet(k, k - 1, -1.0 - bx * h / 2.0);
+            }
+            if j + 1 < m {
+                a.add_triplet(k, k + 1, -1.0 + bx * h / 2.0);
+            }
+        }
+    }
+    let a = a.to_csr();
+    let b = Array1::<f64>::from(vec![1.0; n]);
+    let x0 = Array1::<f64>::zeros(n);
+    let criterion = StoppingCriterion::new(5000).with_rel_tol(1e-10);
+    let ilu = Ilu0::new(&a).unwrap();
+
+    print_report(
+        "bicgstab",
+        2,
+        &BiCGStab.solve(&a, b.view(), x0.view(), &criterion),
+    );
+    for &ell in &[1, 2, 4] {
+        let solver = BiCGStabL::new(ell);
+        let name = format!("bicgstab({})", ell);
+        print_report(
+            &name,
+            2 * ell,
+            &solver.solve(&a, b.view(), x0.view(), &criterion),
+        );
+        let report = solver
+            .with_preconditioner(&ilu)
+            .solve(&a, b.view(), x0.view(), &criterion);
+        print_report(&(name + "+ilu0"), 2 * ell, &report);
+    }
+    for &s in &[1, 2, 4, 8] {
+        let solver = Idrs::new(s);
+        let name = format!("idr({})", s);
+        print_report(
+            &name,
+            s + 1,
+            &solver.solve(&a, b.view(), x0.view(), &criterion),
+        );
+        let report = solver
+            .with_preconditioner(&ilu)
+            .solve(&a, b.view(), x0.view(), &criterion);
+        print_report(&(name + "+ilu0"), s + 1, &report);
+    }
+
+    // complex non-Hermitian: shifted Helmholtz
+    let k = 20.0;
+    let shift = Complex64::new(-(k * h).powi(2), 0.3 * (k * h).powi(2));
+    let mut c = sprs::TriMat::<Complex64>::new((n, n));
+    for (&v, (i, j)) in a.iter() {
+        c.add_triplet(
+            i,
+            j,
+            Complex64::new(v, 0.0) + if i == j { shift } else { 0.0.into() },
+        );
+    }
+    let c = c.to_csr();
+    let b = Array1::<Complex64>::from(vec![Complex64::new(1.0, 1.0); n]);
+    let x0 = Array1::<Complex64>::zeros(n);
+    print_report(
+        "complex bicg2",
+        4,
+        &BiCGStabL::new(2).solve(&c, b.view(), x0.view(), &criterion),
+    );
+    print_report(
+        "complex idr4",
+        5,
+        &Idrs::new(4).solve(&c, b.view(), x0.view(), &criterion),
+    );
+}
@@ -0,0 +1,116 @@
+extern crate linear_solver;
+extern crate ndarray;
+extern crate sprs;
+
+use linear_solver::minres::gmres::{Gmres, Preconditioning};
+use linear_solver::operator::LinearOperator;
+use linear_solver::precond::{Ilu0, Ilut, IlutParams, PrecondErr};
+use linear_solver::solver::IterativeSolver;
+use linear_solver::stopping::StoppingCriterion;
+use ndarray::Array1;
+
+fn main() {
+    // upwind convection-diffusion -Δu + β·∇u on a 30x30 grid, Péclet number 100
+    let m = 30;
+    let n = m * m;
+    let h = 1.0 / (m + 1) as f64;
+    let (bx, by) = (100.0, 50.0);
+    let mut a = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..m {
+        for j in 0..m {
+            let k = i * m + j;
+            a.add_triplet(k, k, 4.0 + (bx + by) * h);
+            if i > 0 {
+                a.add_triplet(k, k - m, -1.0 - by * h);
+            }
+            if i + 1 < m {
+                a.add_triplet(k, k + m, -1.0);
+            }
+            if j > 0 {
+                a.add_triplet(k, k - 1, -1.0 - bx * h);
+            }
+            if j + 1 < m {
+                a.add_triplet(k, k + 1, -1.0);
+            }
+        }
+    }
+    let a = a.to_csr();
+    let b = Array1::<f64>::from(vec![1.0; n]);
+    let x0 = Array1::<f64>::zeros(n);
+    let criterion = StoppingCriterion::new(2000).with_rel_tol(1e-10);
+
+    let ilu0 = Ilu0::new(&a).unwrap();
+    let precs: Vec<(String, Box<dyn LinearOperator<f64>>)> = vec![
+        ("ilu0".to_string(), Box::new(ilu0)),
+        report_ilut(&a, IlutParams::new(5, 1e-2)),
+        report_ilut(&a, IlutParams::new(10, 1e-4)),
+        report_ilut(&a, IlutParams::new(10, 1e-4).with_pivoting(0.1)),
+    ];
+
+    let report = Gmres::new(30).solve(&a, b.view(), x0.view(), &criterion);
+    println!("{:>30}: {} iterations", "none", report.iterations);
+    for (name, m) in &precs {
+        let report = Gmres::new(30)
+            .with_preconditioning(Preconditioning::Right(m.as_ref()))
+            .solve(&a, b.view(), x0.view(), &criterion);
+        println!(
+            "{:>30}: {} iterations, {}",
+            name, report.iterations, report.reason
+        );
+    }
+
+    // with no dropping ILUT is the exact LU factorization
+    let lu = Ilut::new(&a, &IlutParams::new(n, 0.0).with_pivoting(0.5)).unwrap();
+    let x = lu.apply(b.view());
+    let r = &b - &a.apply(x.view());
+    println!(
+        "exact LU: residual {:e}, fill ratio {:.2}",
+        r.iter().fold(0.0f64, |m, r| m.max(r.abs())),
+        lu.fill_ratio
+    );
+
+    // a missing diagonal entry is replaced and reported
+    let mut c = sprs::TriMat::<f64>::new((2, 2));
+    c.add_triplet(0, 1, 1.0);
+    c.add_triplet(1, 0, 1.0);
+    let c = c.to_csr();
+    println!(
+        "zero pivots: {:?}",
+        Ilut::<f64, f64>::new(&c, &IlutParams::new(2, 0.0))
+            .unwrap()
+            .zero_pivots
+    );
+    println!(
+        "with pivoting: {:?}",
+        Ilut::<f64, f64>::new(&c, &IlutParams::new(2, 0.0).with_pivoting(0.5))
+            .unwrap()
+            .zero_pivots
+    );
+
+    // a NaN entry is reported instead of breaking the magnitude sort
+    let mut c = sprs::TriMat::<f64>::new((3, 3));
+    for i in 0..3 {
+        c.add_triplet(i, i, 4.0);
+    }
+    c.add_triplet(1, 0, f64::NAN);
+    c.add_triplet(1, 2, 1.0);
+    let r = Ilut::<f64, f64>::new(&c.to_csr(), &IlutParams::new(1, 0.0).with_pivoting(0.5));
+    println!("NaN entry: {:?}", r.as_ref().err());
+    assert_eq!(r.err(), Some(PrecondErr::NonFinite(1)));
+}
+
+fn report_ilut(
+    a: &sprs::CsMat<f64>,
+    params: IlutParams<f64>,
+) -> (String, Box<dyn LinearOperator<f64>>) {
+    let ilut = Ilut::new(a, &params).unwrap();
+    let name = format!(
+        "ilut({}, {:e}, {})",
+        params.fill, params.drop_tol, params.perm_tol
+    );
+    println!(
+        "{:>30}: fill ratio {:.2}, zero pivots {:?}, {:?}",
+        name, ilut.fill_ratio, ilut.zero_pivots, ilut.info
+    );
+    (name, Box::new(ilut))
+}
@@ -0,0 +1,108 @@
+extern crate linear_solver;
+extern crate ndarray;
+extern crate sprs;
+
+use linear_solver::lsmr::{Lsmr, LsmrState};
+use linear_solver::lsqr::{Lsqr, LsqrState};
+use linear_solver::operator::LinearOperator;
+use linear_solver::qr_solve;
+use linear_solver::solver::IterativeSolver;
+use linear_solver::stopping::StoppingCriterion;
+use ndarray::{Array1, Array2};
+
+fn main() {
+    // an inconsistent, ill-conditioned overdetermined system
+    let (m, n) = (120, 60);
+    let mut a = sprs::TriMat::<f64>::new((m, n));
+    for i in 0..m {
+        for j in 0..n {
+            if (i + 2 * j) % 7 < 2 || i == j {
+                a.add_triplet(
+                    i,
+                    j,
+                    (1.0 + j as f64).powf(-1.5) * (1.0 + ((i * j) % 5) as f64),
+                );
+            }
+        }
+    }
+    let a = a.to_csr();
+    let b = Array1::from(
+        (0..m)
+            .map(|i| (i as f64 * 0.3).sin() + 0.5)
+            .collect::<Vec<_>>(),
+    );
+    let x0 = Array1::<f64>::zeros(n);
+
+    // ‖A^T r‖ of LSMR decreases monotonically, that of LSQR need not
+    let arnorm = |x: &Array1<f64>| {
+        let atr = a.apply_adjoint((&b - &a.apply(x.view())).view());
+        atr.dot(&atr).sqrt()
+    };
+    let mut lsqr = LsqrState::new(&a, &b, 0.0);
+    let mut lsmr = LsmrState::new(&a, &b, 0.0);
+    let (mut up_lsqr, mut up_lsmr) = (0, 0);
+    let (mut last_lsqr, mut last_lsmr) = (f64::INFINITY, f64::INFINITY);
+    for _ in 0..60 {
+        lsqr.next(&a);
+        lsmr.next(&a);
+        let (r1, r2) = (arnorm(&lsqr.x), arnorm(&lsmr.x));
+        up_lsqr += (r1 > last_lsqr) as usize;
+        up_lsmr += (r2 > last_lsmr) as usize;
+        last_lsqr = r1;
+        last_lsmr = r2;
+    }
+    println!(
+        "increases of ‖A^T r‖ in 60 steps: lsqr {}, lsmr {}",
+        up_lsqr, up_lsmr
+    );
+    println!(
+        "lsmr estimates: ‖r‖ {:e} (true {:e}), ‖A^T r‖ {:e} (true {:e}), ‖A‖ {:.3}, cond {:.3e}",
+        lsmr.rnorm,
+        (&b - &a.apply(lsmr.x.view()))
+            .dot(&(&b - &a.apply(lsmr.x.view())))
+            .sqrt(),
+        lsmr.arnorm,
+        last_lsmr,
+        lsmr.anorm,
+        lsmr.acond
+    );
+
+    let criterion = StoppingCriterion::new(1000).with_paige_saunders(1e-10, 1e-10, 1e8);
+    for (name, report) in [
+        (
+            "lsqr",
+            Lsqr::new().solve(&a, b.view(), x0.view(), &criterion),
+        ),
+        (
+            "lsmr",
+            Lsmr::new().solve(&a, b.view(), x0.view(), &criterion),
+        ),
+    ]
+    .iter()
+    {
+        println!(
+            "{}: {} iterations, {}, ‖A^T r‖={:e}",
+            name,
+            report.iterations,
+            report.reason,
+            arnorm(&report.x)
+        );
+    }
+
+    // damped: compare with the dense solution of (A^T A + λ^2 I) x = A^T b
+    let damp = 0.1;
+    let report = Lsmr::new()
+        .with_damping(damp)
+        .solve(&a, b.view(), x0.view(), &criterion);
+    let mut ad = Array2::<f64>::zeros((m, n));
+    for (&v, (i, j)) in a.iter() {
+        ad[(i, j)] = v;
+    }
+    let ata = ad.t().dot(&ad) + Array2::<f64>::eye(n) * (damp * damp);
+    let x = qr_solve::solve(ata.view(), ad.t().dot(&b).view());
+    let diff = (&report.x - &x).iter().fold(0.0f64, |m, d| m.max(d.abs()));
+    println!(
+        "damped lsmr: {} iterations, {}, max |x - x_dense| = {:e}",
+        report.iterations, report.reason, diff
+    );
+}
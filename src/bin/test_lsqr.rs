@@ -19,12 +19,10 @@ fn main() {
 
     let b = sp_mul_a1(&a, x0.view());
 
-    let mut aa = lsqr_init(&|x| sp_mul_a1(&a.transpose_view(), x), a.cols(), &b);
+    let mut aa = lsqr_init(&a, &b, 0.0);
 
     for _i in 0..15 {
-        if let Some(()) = aa.next(&|x| sp_mul_a1(&a, x), &|x| {
-            sp_mul_a1(&a.transpose_view(), x)
-        }) {
+        if let Some(()) = aa.next(&a) {
             println!("a={:?}", aa.x);
         } else {
             println!("a={:?}", aa.x);
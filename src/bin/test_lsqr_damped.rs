@@ -0,0 +1,109 @@
+extern crate linear_solver;
+extern crate ndarray;
+extern crate sprs;
+
+use linear_solver::lsqr::{Lsqr, LsqrState};
+use linear_solver::operator::LinearOperator;
+use linear_solver::qr_solve;
+use linear_solver::solver::IterativeSolver;
+use linear_solver::stopping::StoppingCriterion;
+use ndarray::{Array1, Array2};
+
+fn main() {
+    // deconvolution of a Gaussian blur: severely ill-conditioned, with
+    // slightly noisy data
+    let n = 100;
+    let width = 0.03;
+    let mut a = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..n {
+        for j in 0..n {
+            let d = (i as f64 - j as f64) / n as f64;
+            let v = (-d * d / (2.0 * width * width)).exp() / n as f64;
+            if v > 1e-12 {
+                a.add_triplet(i, j, v);
+            }
+        }
+    }
+    let a = a.to_csr();
+    let x_true = Array1::from(
+        (0..n)
+            .map(|i| {
+                let t = i as f64 / n as f64;
+                if (0.3..0.6).contains(&t) {
+                    1.0
+                } else {
+                    0.0
+                }
+            })
+            .collect::<Vec<_>>(),
+    );
+    let noise = Array1::from(
+        (0..n)
+            .map(|i| 1e-3 * ((i * 7919) % 13) as f64 / 13.0 - 5e-4)
+            .collect::<Vec<_>>(),
+    );
+    let b = &a.apply(x_true.view()) + &noise;
+    let x0 = Array1::<f64>::zeros(n);
+    let err = |x: &Array1<f64>| {
+        let e = x - &x_true;
+        e.dot(&e).sqrt()
+    };
+
+    // the running estimates against the quantities they estimate
+    let damp = 1e-2;
+    let mut s = LsqrState::new(&a, &b, damp);
+    for _ in 0..20 {
+        s.next(&a);
+    }
+    let r = s.calc_resid(&a, &b);
+    let atr = &a.apply_adjoint(r.view()) - &(&s.x * (damp * damp));
+    println!(
+        "after 20 steps: ‖r‖ {:e} (true {:e}), ‖A^T r - λ^2 x‖ {:e} (true {:e}), ‖x‖ {:e} (true {:e}), ‖A‖ {:.4}, cond {:.3e}",
+        s.rnorm,
+        (r.dot(&r) + damp * damp * s.x.dot(&s.x)).sqrt(),
+        s.arnorm,
+        atr.dot(&atr).sqrt(),
+        s.xnorm,
+        s.x.dot(&s.x).sqrt(),
+        s.anorm,
+        s.acond
+    );
+
+    // without damping the error blows up once the noise is fitted
+    let criterion = StoppingCriterion::new(500).with_paige_saunders(1e-12, 1e-12, 1e8);
+    for &damp in &[0.0, 1e-3, 1e-2, 1e-1] {
+        let report = Lsqr::new()
+            .with_damping(damp)
+            .solve(&a, b.view(), x0.view(), &criterion);
+        println!(
+            "λ = {:e}: {} iterations, istop {:?} ({}), ‖x - x_true‖ = {:.4}",
+            damp,
+            report.iterations,
+            report.reason.istop(),
+            report.reason,
+            err(&report.x)
+        );
+    }
+
+    // damped: compare with the dense solution of (A^T A + λ^2 I) x = A^T b
+    let damp = 1e-2;
+    let report = Lsqr::new()
+        .with_damping(damp)
+        .solve(&a, b.view(), x0.view(), &criterion);
+    let mut ad = Array2::<f64>::zeros((n, n));
+    for (&v, (i, j)) in a.iter() {
+        ad[(i, j)] = v;
+    }
+    let mut normal = ad.t().dot(&ad);
+    for i in 0..n {
+        normal[(i, i)] += damp * damp;
+    }
+    let x_dense = qr_solve::solve(normal.view(), ad.t().dot(&b).view());
+    let diff = (&report.x - &x_dense)
+        .iter()
+        .fold(0.0f64, |m, &v| m.max(v.abs()));
+    println!(
+        "damped lsqr: {} iterations, {}, max |x - x_dense| = {:e}",
+        report.iterations, report.reason, diff
+    );
+}
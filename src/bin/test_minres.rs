@@ -0,0 +1,70 @@
+extern crate linear_solver;
+extern crate ndarray;
+extern crate sprs;
+
+use linear_solver::minres::{Minres, MinresState};
+use linear_solver::solver::IterativeSolver;
+use linear_solver::stopping::{StopReason, StoppingCriterion};
+use ndarray::{Array1, ArrayView1};
+
+fn main() {
+    // symmetric indefinite
+    let a = sprs::CsMatI::<f64, usize>::new(
+        (3, 3),
+        vec![0, 2, 5, 7],
+        vec![0, 1, 0, 1, 2, 1, 2],
+        vec![4.0, 1.0, 1.0, -3.0, -1.0, -1.0, 2.0],
+    );
+    let b = Array1::<f64>::from(vec![1., 2., 3.]);
+    println!("{:?}", b);
+
+    let mut ms = MinresState::new(
+        &a,
+        ArrayView1::from(&[0., 0., 0.]),
+        b.view(),
+        None,
+        1e-12,
+        false,
+    );
+
+    while !ms.converged {
+        if ms.next(&a, None).is_none() {
+            break;
+        }
+        println!("{:?} {:e}", ms.x, ms.resid);
+    }
+    println!("{:?}", ms.calc_resid(&a, &b));
+
+    // singular and incompatible: MINRES-QLP gives the minimum-length
+    // least-squares solution [0.5, 1, 0]
+    let s = sprs::CsMatI::<f64, usize>::new((3, 3), vec![0, 1, 2, 2], vec![0, 1], vec![2.0, -1.0]);
+    let b = Array1::<f64>::from(vec![1., -1., 1.]);
+    let mut ms = MinresState::new(
+        &s,
+        ArrayView1::from(&[0., 0., 0.]),
+        b.view(),
+        None,
+        1e-12,
+        true,
+    );
+    for _i in 0..10 {
+        if ms.next(&s, None).is_none() {
+            break;
+        }
+    }
+    println!("{:?} {:e}", ms.x, ms.resid);
+
+    // the solver reports the end of the Krylov space as a least-squares
+    // solution after checking that A r vanishes
+    let r = Minres::qlp().solve(
+        &s,
+        b.view(),
+        ArrayView1::from(&[0., 0., 0.]),
+        &StoppingCriterion::new(10).with_rel_tol(1e-12),
+    );
+    println!(
+        "minres-qlp: {:?}, {} iterations, {}, resid={:e}",
+        r.x, r.iterations, r.reason, r.resid_norm
+    );
+    assert_eq!(r.reason, StopReason::LeastSquaresSolution);
+}
@@ -0,0 +1,66 @@
+extern crate linear_solver;
+extern crate ndarray;
+extern crate sprs;
+
+use linear_solver::cg::Cg;
+use linear_solver::operator::LinearOperator;
+use linear_solver::precond::{BlockJacobi, Ic0, Ilu0, Jacobi, Ssor};
+use linear_solver::solver::IterativeSolver;
+use linear_solver::stopping::StoppingCriterion;
+use ndarray::Array1;
+
+fn main() {
+    // 2D Laplacian on a 20x20 grid
+    let m = 20;
+    let n = m * m;
+    let mut a = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..m {
+        for j in 0..m {
+            let k = i * m + j;
+            a.add_triplet(k, k, 4.0);
+            if i > 0 {
+                a.add_triplet(k, k - m, -1.0);
+            }
+            if i + 1 < m {
+                a.add_triplet(k, k + m, -1.0);
+            }
+            if j > 0 {
+                a.add_triplet(k, k - 1, -1.0);
+            }
+            if j + 1 < m {
+                a.add_triplet(k, k + 1, -1.0);
+            }
+        }
+    }
+    let a = a.to_csr();
+    let b = Array1::<f64>::from(vec![1.0; n]);
+    let x0 = Array1::<f64>::zeros(n);
+    let criterion = StoppingCriterion::new(1000).with_rel_tol(1e-10);
+
+    let jacobi = Jacobi::new(&a).unwrap();
+    let block_jacobi = BlockJacobi::new(&a, m).unwrap();
+    let ssor = Ssor::new(&a, 1.5).unwrap();
+    let ilu0 = Ilu0::new(&a).unwrap();
+    let ic0 = Ic0::new(&a).unwrap();
+    let precs: Vec<(&str, &dyn LinearOperator<f64>, _)> = vec![
+        ("jacobi", &jacobi, jacobi.info),
+        ("block jacobi", &block_jacobi, block_jacobi.info),
+        ("ssor", &ssor, ssor.info),
+        ("ilu0", &ilu0, ilu0.info),
+        ("ic0", &ic0, ic0.info),
+    ];
+
+    let report = Cg::new().solve(&a, b.view(), x0.view(), &criterion);
+    println!("{:>12}: {} iterations", "none", report.iterations);
+    for (name, m, info) in precs {
+        let report = Cg::new()
+            .with_preconditioner(m)
+            .solve(&a, b.view(), x0.view(), &criterion);
+        println!("{:>12}: {} iterations, {:?}", name, report.iterations, info);
+    }
+
+    // IC(0) reports an indefinite matrix
+    let mut c = a.clone();
+    c.data_mut()[0] = -4.0;
+    println!("{:?}", Ic0::<f64, f64>::new(&c).err());
+}
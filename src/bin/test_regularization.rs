@@ -0,0 +1,84 @@
+extern crate linear_solver;
+extern crate ndarray;
+extern crate sprs;
+
+use linear_solver::operator::LinearOperator;
+use linear_solver::regularization::{
+    gcv_minimize, lambda_sweep, lcurve_corner, log_space, TraceEstimator,
+};
+use linear_solver::stopping::StoppingCriterion;
+use ndarray::Array1;
+
+fn main() {
+    // deconvolution of a Gaussian blur with noisy data
+    let n = 80;
+    let width = 0.03;
+    let mut a = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..n {
+        for j in 0..n {
+            let d = (i as f64 - j as f64) / n as f64;
+            let v = (-d * d / (2.0 * width * width)).exp() / n as f64;
+            if v > 1e-12 {
+                a.add_triplet(i, j, v);
+            }
+        }
+    }
+    let a = a.to_csr();
+    let x_true = Array1::from(
+        (0..n)
+            .map(|i| {
+                let t = i as f64 / n as f64;
+                (2.0 * std::f64::consts::PI * t).sin().max(0.0) + if t > 0.7 { 0.5 } else { 0.0 }
+            })
+            .collect::<Vec<_>>(),
+    );
+    let noise = Array1::from(
+        (0..n)
+            .map(|i| 2e-3 * (((i * 7919) % 31) as f64 / 31.0 - 0.5))
+            .collect::<Vec<_>>(),
+    );
+    let b = &a.apply(x_true.view()) + &noise;
+    let err = |x: &Array1<f64>| {
+        let e = x - &x_true;
+        e.dot(&e).sqrt() / x_true.dot(&x_true).sqrt()
+    };
+
+    let criterion = StoppingCriterion::new(1000).with_paige_saunders(1e-10, 1e-10, 1e10);
+    let lambdas = log_space(1e-5, 1.0, 26);
+
+    let sweep = lambda_sweep(&a, b.view(), &lambdas, &criterion);
+    let best = sweep
+        .iter()
+        .map(|p| err(&p.x))
+        .enumerate()
+        .fold(
+            (0, f64::INFINITY),
+            |m, (i, e)| if e < m.1 { (i, e) } else { m },
+        );
+    println!(
+        "best λ on the grid: {:.3e}, relative error {:.4}",
+        lambdas[best.0], best.1
+    );
+    let k = lcurve_corner(&sweep);
+    println!(
+        "L-curve corner: λ = {:.3e}, ‖r‖ = {:.3e}, ‖x‖ = {:.3}, relative error {:.4}",
+        sweep[k].lambda,
+        sweep[k].rnorm,
+        sweep[k].xnorm,
+        err(&sweep[k].x)
+    );
+
+    for &(name, trace) in &[
+        ("exact", TraceEstimator::Exact),
+        ("hutchinson(20)", TraceEstimator::Hutchinson(20)),
+    ] {
+        let g = gcv_minimize(&a, b.view(), &lambdas, &criterion, trace, 8);
+        println!(
+            "GCV {}: λ = {:.3e}, dof = {:.2}, relative error {:.4}",
+            name,
+            g.point.lambda,
+            g.dof,
+            err(&g.point.x)
+        );
+    }
+}
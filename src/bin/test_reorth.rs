@@ -0,0 +1,99 @@
+extern crate linear_solver;
+extern crate ndarray;
+extern crate num_complex;
+extern crate sprs;
+
+use linear_solver::arnoldi::{ArnoldiSpace, Orthogonalization};
+use linear_solver::minres::{Gmres, GmresState};
+use linear_solver::monitor::{Monitor, MonitorAction};
+use linear_solver::solver::IterativeSolver;
+use linear_solver::stopping::StoppingCriterion;
+use ndarray::Array1;
+use std::time::Instant;
+
+/// upper bidiagonal with diagonal `1..=n`; its Ritz values converge fast,
+/// which is when one MGS sweep loses orthogonality
+fn bidiagonal(n: usize) -> sprs::CsMat<f64> {
+    let mut a = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..n {
+        a.add_triplet(i, i, (i + 1) as f64);
+        if i + 1 < n {
+            a.add_triplet(i, i + 1, 0.1);
+        }
+    }
+    a.to_csr()
+}
+
+/// keeps the largest loss of orthogonality seen in any cycle
+struct LossMonitor(f64);
+
+impl Monitor<GmresState<f64, f64>, f64> for LossMonitor {
+    fn observe(&mut self, _: usize, _: f64, state: &GmresState<f64, f64>) -> MonitorAction {
+        self.0 = state.arn.loss.iter().cloned().fold(self.0, f64::max);
+        MonitorAction::Continue
+    }
+}
+
+fn main() {
+    let n = 100;
+    let a = bidiagonal(n);
+    let b = Array1::<f64>::from(vec![1.0; n]);
+    let x0 = Array1::<f64>::zeros(n);
+    let all = [
+        Orthogonalization::Mgs,
+        Orthogonalization::Cgs2,
+        Orthogonalization::Dgks,
+        Orthogonalization::Selective,
+        Orthogonalization::Householder,
+    ];
+
+    for &orth in &all {
+        let mut arn = ArnoldiSpace::<f64, f64>::new(b.view())
+            .with_orthogonalization(orth)
+            .with_loss_measurement(true);
+        let start = Instant::now();
+        for _ in 0..80 {
+            arn.iter(&a).unwrap();
+        }
+        let elapsed = start.elapsed();
+        let first = arn.loss.iter().position(|&l| l > 1e-8);
+        println!(
+            "{:?}: ||I - Q^H Q|| after 80 steps = {:e} (above 1e-8 from step {:?}), {:?}",
+            orth,
+            arn.loss.last().unwrap(),
+            first.map(|i| i + 1),
+            elapsed
+        );
+        assert!((arn.loss_of_orthogonality() - arn.loss.last().unwrap()).abs() < 1e-8);
+    }
+
+    let criterion = StoppingCriterion::new(5000).with_rel_tol(1e-12);
+    for &orth in &all {
+        let mut monitor = LossMonitor(0.0);
+        let r = Gmres::new(60)
+            .with_orthogonalization(orth)
+            .with_loss_measurement(true)
+            .solve_with_monitor(&a, b.view(), x0.view(), &criterion, &mut monitor);
+        println!(
+            "gmres(60), {:?}: {} iterations, {}, resid={:e}, max loss={:e}",
+            orth, r.iterations, r.reason, r.resid_norm, monitor.0
+        );
+    }
+
+    // implicitly restarted Arnoldi inherits the orthogonalization
+    let mut arn = ArnoldiSpace::<f64, f64>::new(b.view())
+        .with_orthogonalization(Orthogonalization::Dgks)
+        .with_loss_measurement(true)
+        .to_complex();
+    let ac = a.map(|&x| num_complex::Complex::new(x, 0.0));
+    for _ in 0..10 {
+        arn.iter(&ac).unwrap();
+    }
+    for _ in 0..20 {
+        arn.restart(&ac, 10, 1e-10).unwrap();
+    }
+    println!(
+        "iram with Dgks: ||I - Q^H Q|| = {:e}",
+        arn.loss_of_orthogonality()
+    );
+}
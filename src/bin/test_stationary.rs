@@ -0,0 +1,127 @@
+extern crate linear_solver;
+extern crate ndarray;
+extern crate sprs;
+
+use linear_solver::cg::{Cg, CgState};
+use linear_solver::chebyshev::Chebyshev;
+use linear_solver::precond::Jacobi;
+use linear_solver::solver::IterativeSolver;
+use linear_solver::stationary::{optimal_sor_omega, Stationary, Sweep};
+use linear_solver::stopping::StoppingCriterion;
+use ndarray::Array1;
+
+fn main() {
+    // 2D Laplacian on a 24x24 grid, lexicographic (consistent) ordering
+    let m = 24;
+    let n = m * m;
+    let mut a = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..m {
+        for j in 0..m {
+            let p = i * m + j;
+            a.add_triplet(p, p, 4.0);
+            for &(q, ok) in &[
+                (p.wrapping_sub(m), i > 0),
+                (p + m, i + 1 < m),
+                (p.wrapping_sub(1), j > 0),
+                (p + 1, j + 1 < m),
+            ] {
+                if ok {
+                    a.add_triplet(p, q, -1.0);
+                }
+            }
+        }
+    }
+    let a = a.to_csr();
+    let b = Array1::<f64>::from(vec![1.0; n]);
+    let x0 = Array1::<f64>::zeros(n);
+    let criterion = StoppingCriterion::new(5000).with_rel_tol(1e-6);
+
+    // Jacobi has ρ = cos(π h), so ω_opt = 2 / (1 + sin(π h))
+    let h = 1.0 / (m + 1) as f64;
+    let omega_exact = 2.0 / (1.0 + (std::f64::consts::PI * h).sin());
+    let omega = optimal_sor_omega::<f64, f64>(&a, 200).unwrap();
+    println!(
+        "optimal ω: estimated {:.4}, exact {:.4}",
+        omega, omega_exact
+    );
+
+    // λ_max(A) < 8 by Gershgorin
+    let methods: Vec<(&str, Stationary<f64, f64>)> = vec![
+        ("jacobi", Stationary::jacobi(&a, 1.0).unwrap()),
+        (
+            "damped jacobi 2/3",
+            Stationary::jacobi(&a, 2.0 / 3.0).unwrap(),
+        ),
+        ("richardson 1/8", Stationary::richardson(&a, 0.125).unwrap()),
+        (
+            "gauss-seidel forward",
+            Stationary::gauss_seidel(&a, Sweep::Forward).unwrap(),
+        ),
+        (
+            "gauss-seidel backward",
+            Stationary::gauss_seidel(&a, Sweep::Backward).unwrap(),
+        ),
+        (
+            "symmetric gauss-seidel",
+            Stationary::gauss_seidel(&a, Sweep::Symmetric).unwrap(),
+        ),
+        (
+            "sor(1.5)",
+            Stationary::sor(&a, 1.5, Sweep::Forward).unwrap(),
+        ),
+        (
+            "sor(ω estimated)",
+            Stationary::sor(&a, omega, Sweep::Forward).unwrap(),
+        ),
+        ("ssor(1.5)", Stationary::ssor(&a, 1.5).unwrap()),
+    ];
+    for (name, method) in &methods {
+        let report = method.solve(&a, b.view(), x0.view(), &criterion);
+        println!(
+            "{}: {} iterations, {}, resid={:e}",
+            name, report.iterations, report.reason, report.resid_norm
+        );
+    }
+
+    // Chebyshev with bounds from a few CG steps on the Jacobi preconditioned
+    // system; the smallest Ritz value approaches λ_min from above, so it is
+    // lowered a little
+    let jacobi = Jacobi::<f64, f64>::new(&a).unwrap();
+    let mut cg = CgState::new(&a, x0.view(), b.view(), Some(&jacobi), 0.0);
+    for _ in 0..15 {
+        cg.next(&a, Some(&jacobi));
+    }
+    let (lmin, lmax) = cg.eig_estimate().unwrap();
+    println!(
+        "CG estimate of the spectrum of D^-1 A: [{:e}, {:e}]",
+        lmin, lmax
+    );
+    for &(name, lo, hi) in &[
+        ("estimated", 0.9 * lmin, 1.05 * lmax),
+        ("exact", 1.0 - (std::f64::consts::PI * h).cos(), 2.0),
+    ] {
+        let report = Chebyshev::new(lo, hi).with_preconditioner(&jacobi).solve(
+            &a,
+            b.view(),
+            x0.view(),
+            &criterion,
+        );
+        println!(
+            "chebyshev ({} bounds): {} iterations, {}, resid={:e}",
+            name, report.iterations, report.reason, report.resid_norm
+        );
+    }
+
+    // two symmetric sweeps as a CG preconditioner
+    let sgs = Stationary::gauss_seidel(&a, Sweep::Symmetric)
+        .unwrap()
+        .with_sweeps(2);
+    let plain = Cg::new().solve(&a, b.view(), x0.view(), &criterion);
+    let report = Cg::new()
+        .with_preconditioner(&sgs)
+        .solve(&a, b.view(), x0.view(), &criterion);
+    println!(
+        "CG: {} iterations; with 2 SGS sweeps: {} iterations, {}",
+        plain.iterations, report.iterations, report.reason
+    );
+}
@@ -0,0 +1,97 @@
+extern crate linear_solver;
+extern crate ndarray;
+extern crate num_complex;
+extern crate sprs;
+
+use linear_solver::bicgstab::BiCGStab;
+use linear_solver::cgs::Cgs;
+use linear_solver::solver::{IterativeSolver, SolveReport};
+use linear_solver::stopping::StoppingCriterion;
+use linear_solver::tfqmr::Tfqmr;
+use linear_solver::utils::norm;
+use ndarray::Array1;
+use num_complex::Complex64;
+
+/// `tol` is the residual norm the criterion asks for
+fn print_report<T>(name: &str, report: &SolveReport<T, f64>, tol: f64) {
+    let (min, max) = report
+        .history
+        .windows(2)
+        .map(|w| w[1] / w[0])
+        .fold((f64::INFINITY, 0.0f64), |(a, b), r| (a.min(r), b.max(r)));
+    println!(
+        "{:>16}: {:4} iterations, {}, resid={:e}, step ratio of the residual in [{:.2}, {:.2}]",
+        name, report.iterations, report.reason, report.resid_norm, min, max
+    );
+    assert!(report.reason.is_converged() && report.resid_norm <= tol);
+}
+
+fn main() {
+    // upwind convection-diffusion on a 30x30 grid
+    let m = 30;
+    let n = m * m;
+    let h = 1.0 / (m + 1) as f64;
+    let (bx, by) = (20.0f64, -10.0f64);
+    let mut a = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..m {
+        for j in 0..m {
+            let k = i * m + j;
+            a.add_triplet(k, k, 4.0 + (bx + by.abs()) * h);
+            if i > 0 {
+                a.add_triplet(k, k - m, -1.0);
+            }
+            if i + 1 < m {
+                a.add_triplet(k, k + m, -1.0 + by * h);
+            }
+            if j > 0 {
+                a.add_triplet(k, k - 1, -1.0 - bx * h);
+            }
+            if j + 1 < m {
+                a.add_triplet(k, k + 1, -1.0);
+            }
+        }
+    }
+    let a = a.to_csr();
+    let b = Array1::<f64>::from(vec![1.0; n]);
+    let x0 = Array1::<f64>::zeros(n);
+    let criterion = StoppingCriterion::new(2000).with_rel_tol(1e-10);
+    let tol = 1e-10 * norm(b.view());
+    print_report(
+        "bicgstab",
+        &BiCGStab.solve(&a, b.view(), x0.view(), &criterion),
+        tol,
+    );
+    print_report("cgs", &Cgs.solve(&a, b.view(), x0.view(), &criterion), tol);
+    print_report(
+        "tfqmr",
+        &Tfqmr.solve(&a, b.view(), x0.view(), &criterion),
+        tol,
+    );
+
+    // complex non-Hermitian
+    let shift = Complex64::new(-0.05, 0.05);
+    let mut c = sprs::TriMat::<Complex64>::new((n, n));
+    for (&v, (i, j)) in a.iter() {
+        let s = if i == j { shift } else { 0.0.into() };
+        c.add_triplet(i, j, Complex64::new(v, 0.0) + s);
+    }
+    let c = c.to_csr();
+    let b = Array1::<Complex64>::from(vec![Complex64::new(1.0, -1.0); n]);
+    let x0 = Array1::<Complex64>::zeros(n);
+    let tol = 1e-10 * norm(b.view());
+    print_report(
+        "complex bicgstab",
+        &BiCGStab.solve(&c, b.view(), x0.view(), &criterion),
+        tol,
+    );
+    print_report(
+        "complex cgs",
+        &Cgs.solve(&c, b.view(), x0.view(), &criterion),
+        tol,
+    );
+    print_report(
+        "complex tfqmr",
+        &Tfqmr.solve(&c, b.view(), x0.view(), &criterion),
+        tol,
+    );
+}
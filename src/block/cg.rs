@@ -0,0 +1,300 @@
+#![allow(non_snake_case)]
+#![allow(clippy::many_single_char_names)]
+use ndarray::{Array2, ArrayView2, Axis};
+use num_traits::Float;
+use std::time::Instant;
+
+use super::{column_norms, rrqr, BlockIterativeSolver, BlockSolveReport};
+use crate::minres::gmresdr::qr_solve_dense;
+use crate::monitor::{Monitor, MonitorAction};
+use crate::operator::{check_shape, LinearOperator};
+use crate::qr::householder_reflection;
+use crate::stopping::{IterInfo, StopReason, StoppingCriterion};
+use crate::utils::{hermit, ComplexOrReal};
+
+/// State of the breakdown-free block conjugate gradient method (Ji and Li,
+/// 2017) for Hermitian positive definite `A` with a Hermitian positive
+/// definite preconditioner `M ≈ A^-1`.
+///
+/// The search block `P` is kept orthonormal by `rrqr`, which deflates
+/// directions that have become dependent — the case in which O'Leary's
+/// original block CG breaks down. Converged columns are frozen and no
+/// longer contribute to `P`.
+pub struct BlockCgState<T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    pub X: Array2<T>,
+    /// residual block, updated by the recurrence
+    pub R: Array2<T>,
+    /// search directions
+    pub P: Array2<T>,
+    /// residual threshold of each column
+    pub tol: Vec<U>,
+    /// relative tolerance of the rank-revealing QRs
+    pub deflation_tol: U,
+    pub resid: Vec<U>,
+    pub converged: Vec<bool>,
+    pub applications: usize,
+}
+
+fn apply_precond<T>(M: Option<&dyn LinearOperator<T>>, R: Array2<T>) -> Array2<T>
+where
+    T: Clone + num_traits::Zero,
+{
+    match M {
+        Some(M) => M.apply_block(R.view()),
+        None => R,
+    }
+}
+
+/// `A^-1 B` for a small dense `A`
+fn dense_solve<T, U>(A: &Array2<T>, B: &Array2<T>) -> Array2<T>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float,
+{
+    let (q, r) = householder_reflection(A.view());
+    qr_solve_dense(&q, &r, B.view())
+}
+
+impl<T, U> BlockCgState<T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    fn active(&self) -> Vec<usize> {
+        (0..self.X.ncols())
+            .filter(|&j| !self.converged[j])
+            .collect()
+    }
+
+    /// the orthonormalized (and deflated) search block spanned by `Z`
+    fn search_block(&self, Z: Array2<T>) -> Array2<T> {
+        let scale = column_norms(Z.view()).into_iter().fold(U::zero(), U::max);
+        rrqr(Z.view(), self.deflation_tol * scale).0
+    }
+
+    pub fn new(
+        A: &dyn LinearOperator<T>,
+        X: ArrayView2<T>,
+        B: ArrayView2<T>,
+        M: Option<&dyn LinearOperator<T>>,
+        deflation_tol: U,
+        tol: U,
+    ) -> BlockCgState<T, U> {
+        check_shape(A, B.nrows(), X.nrows());
+        assert_eq!(B.ncols(), X.ncols());
+        let p = B.ncols();
+        let R = &B - &A.apply_block(X);
+        let resid = column_norms(R.view());
+        let converged: Vec<bool> = resid.iter().map(|&r| r < tol).collect();
+        let mut state = BlockCgState {
+            X: X.to_owned(),
+            R,
+            P: Array2::zeros((B.nrows(), 0)),
+            tol: vec![tol; p],
+            deflation_tol,
+            resid,
+            converged,
+            applications: p,
+        };
+        state.restart(M);
+        state
+    }
+
+    /// Restarts the search directions from the residuals of the columns
+    /// that have not converged, e.g. after `tol` was changed.
+    pub fn restart(&mut self, M: Option<&dyn LinearOperator<T>>) {
+        let act = self.active();
+        let Z = apply_precond(M, self.R.select(Axis(1), &act));
+        self.P = self.search_block(Z);
+    }
+
+    /// Returns `None` (and keeps the old state) if no search direction is
+    /// left for columns that have not converged, or if the step produced
+    /// non-finite values, e.g. because `A` or `M` is not positive definite.
+    pub fn next(
+        &mut self,
+        A: &dyn LinearOperator<T>,
+        M: Option<&dyn LinearOperator<T>>,
+    ) -> Option<()> {
+        let act = self.active();
+        if act.is_empty() {
+            return Some(());
+        }
+        if self.P.ncols() == 0 {
+            return None;
+        }
+        let Q = A.apply_block(self.P.view());
+        let applications = self.applications + self.P.ncols();
+        let PQ = hermit(self.P.view()).dot(&Q);
+
+        let Ra = self.R.select(Axis(1), &act);
+        let alpha = dense_solve(&PQ, &hermit(self.P.view()).dot(&Ra));
+        let Xa = &self.X.select(Axis(1), &act) + &self.P.dot(&alpha);
+        let Ra = &Ra - &Q.dot(&alpha);
+        let resid = column_norms(Ra.view());
+        if !Xa.iter().chain(Ra.iter()).all(|x| x.abs().is_finite()) {
+            return None;
+        }
+
+        let mut X = self.X.clone();
+        let mut R = self.R.clone();
+        let mut converged = self.converged.clone();
+        let mut res = self.resid.clone();
+        for (a, &j) in act.iter().enumerate() {
+            X.column_mut(j).assign(&Xa.column(a));
+            R.column_mut(j).assign(&Ra.column(a));
+            res[j] = resid[a];
+            converged[j] = resid[a] < self.tol[j];
+        }
+        let still: Vec<usize> = (0..act.len()).filter(|&a| !converged[act[a]]).collect();
+        let Z = apply_precond(M, Ra.select(Axis(1), &still));
+        let beta = dense_solve(&PQ, &hermit(Q.view()).dot(&Z));
+        let P = self.search_block(&Z - &self.P.dot(&beta));
+        if !P.iter().all(|x| x.abs().is_finite()) {
+            return None;
+        }
+
+        self.X = X;
+        self.R = R;
+        self.P = P;
+        self.resid = res;
+        self.converged = converged;
+        self.applications = applications;
+        Some(())
+    }
+
+    pub fn calc_resid(&self, lhs: &dyn LinearOperator<T>, B: &Array2<T>) -> Array2<T> {
+        B - &lhs.apply_block(self.X.view())
+    }
+}
+
+/// Block CG as a `BlockIterativeSolver`; `iterations` counts block steps.
+/// The residual tests apply to each column's recursively updated residual.
+pub struct BlockCg<'a, T, U> {
+    pub M: Option<&'a dyn LinearOperator<T>>,
+    /// relative tolerance for deflating the search block, `sqrt(eps)` by
+    /// default
+    pub deflation_tol: U,
+}
+
+impl<'a, T, U> BlockCg<'a, T, U>
+where
+    U: Float,
+{
+    pub fn new() -> BlockCg<'a, T, U> {
+        BlockCg {
+            M: None,
+            deflation_tol: U::epsilon().sqrt(),
+        }
+    }
+
+    pub fn with_preconditioner(mut self, M: &'a dyn LinearOperator<T>) -> BlockCg<'a, T, U> {
+        self.M = Some(M);
+        self
+    }
+
+    pub fn with_deflation_tol(mut self, tol: U) -> BlockCg<'a, T, U> {
+        self.deflation_tol = tol;
+        self
+    }
+}
+
+impl<'a, T, U> Default for BlockCg<'a, T, U>
+where
+    U: Float,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, T, U> BlockIterativeSolver<T, U> for BlockCg<'a, T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    type State = BlockCgState<T, U>;
+
+    fn solve_with_monitor(
+        &self,
+        A: &dyn LinearOperator<T>,
+        B: ArrayView2<T>,
+        X0: ArrayView2<T>,
+        criterion: &StoppingCriterion<U>,
+        monitor: &mut dyn Monitor<BlockCgState<T, U>, U>,
+    ) -> BlockSolveReport<T, U> {
+        let start = Instant::now();
+        let p = B.ncols();
+        let b_norms = column_norms(B);
+        let mut state = BlockCgState::new(A, X0, B, self.M, self.deflation_tol, U::zero());
+        let r0_norms = state.resid.clone();
+        for j in 0..p {
+            state.tol[j] = criterion
+                .residual_threshold(r0_norms[j], b_norms[j])
+                .map_or(U::zero(), |(th, _)| th);
+        }
+        let mut reasons: Vec<Option<StopReason>> = vec![None; p];
+        let mut history = vec![r0_norms.iter().cloned().fold(U::zero(), U::max)];
+        let mut iterations = 0;
+        loop {
+            let elapsed = start.elapsed();
+            let mut stopped = false;
+            for j in 0..p {
+                if reasons[j].is_none() {
+                    let info =
+                        IterInfo::new(iterations, state.resid[j], r0_norms[j], b_norms[j], elapsed);
+                    reasons[j] = criterion.check(&info);
+                    stopped |= reasons[j].is_some() && !state.converged[j];
+                    state.converged[j] = reasons[j].is_some();
+                }
+            }
+            if reasons.iter().all(Option::is_some) {
+                break;
+            }
+            // columns that stopped without meeting `tol` leave the block
+            if stopped {
+                state.restart(self.M);
+            }
+            if state.next(A, self.M).is_none() {
+                for r in reasons.iter_mut().filter(|r| r.is_none()) {
+                    *r = Some(StopReason::Breakdown);
+                }
+                break;
+            }
+            iterations += 1;
+            let worst = (0..p)
+                .filter(|&j| reasons[j].is_none())
+                .map(|j| state.resid[j])
+                .fold(U::zero(), U::max);
+            history.push(worst);
+            if monitor.observe(iterations, worst, &state) == MonitorAction::Abort {
+                for r in reasons.iter_mut().filter(|r| r.is_none()) {
+                    *r = Some(StopReason::Aborted);
+                }
+                break;
+            }
+        }
+        let W = state.calc_resid(A, &B.to_owned());
+        let resid_norms = column_norms(W.view());
+        let reasons = reasons
+            .into_iter()
+            .zip(resid_norms.iter().zip(r0_norms.iter().zip(b_norms.iter())))
+            .map(|(reason, (&r, (&r0, &b)))| match reason.unwrap() {
+                StopReason::Breakdown => criterion.breakdown_reason(r, r0, b),
+                reason => reason,
+            })
+            .collect();
+        BlockSolveReport {
+            X: state.X,
+            iterations,
+            applications: state.applications,
+            resid_norms,
+            reasons,
+            history,
+        }
+    }
+}
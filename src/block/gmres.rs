@@ -0,0 +1,452 @@
+#![allow(non_snake_case)]
+#![allow(clippy::many_single_char_names)]
+use ndarray::{s, Array1, Array2, ArrayView2, Axis};
+use num_traits::Float;
+use std::time::Instant;
+
+use super::{column_norms, rrqr, BlockIterativeSolver, BlockSolveReport};
+use crate::minres::utils::{apply_plane_rotation, generate_plane_rotation};
+use crate::minres::Preconditioning;
+use crate::monitor::{Monitor, MonitorAction, SilentMonitor};
+use crate::operator::{check_shape, LinearOperator};
+use crate::stopping::{IterInfo, StopReason, StoppingCriterion};
+use crate::utils::{inner, norm, ComplexOrReal};
+
+fn apply_left_block<T>(M: Preconditioning<T>, V: Array2<T>) -> Array2<T>
+where
+    T: Clone + num_traits::Zero,
+{
+    match M.left() {
+        Some(M) => M.apply_block(V.view()),
+        None => V,
+    }
+}
+
+fn apply_right_block<T>(M: Preconditioning<T>, V: Array2<T>) -> Array2<T>
+where
+    T: Clone + num_traits::Zero,
+{
+    match M.right() {
+        Some(M) => M.apply_block(V.view()),
+        None => V,
+    }
+}
+
+/// `M1 A M2 V`, one batched application per factor
+fn apply_operator<T>(A: &dyn LinearOperator<T>, M: Preconditioning<T>, V: Array2<T>) -> Array2<T>
+where
+    T: Clone + num_traits::Zero,
+{
+    let AV = A.apply_block(apply_right_block(M, V).view());
+    apply_left_block(M, AV)
+}
+
+/// State of restarted block GMRES(m) for `A X = B`.
+///
+/// A cycle orthonormalizes the residuals of the columns that have not
+/// converged yet by `rrqr` and runs `m` steps of block Arnoldi from them,
+/// minimizing every residual over the whole block Krylov space. Each new
+/// block is again reduced by `rrqr`, so it shrinks when its directions
+/// become dependent; a block of size zero means the space is invariant and
+/// the cycle's solutions are exact.
+pub struct BlockGmresState<T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    /// block steps per cycle
+    pub m: usize,
+    /// residual threshold of each column
+    pub tol: Vec<U>,
+    /// relative tolerance of the rank-revealing QRs
+    pub deflation_tol: U,
+    pub X: Array2<T>,
+    pub B: Array2<T>,
+    /// the (left preconditioned) residual block
+    pub R: Array2<T>,
+    pub resid: Vec<U>,
+    pub converged: Vec<bool>,
+    /// the basis of the last cycle, block by block
+    pub V: Vec<Array1<T>>,
+    pub block_sizes: Vec<usize>,
+    /// block steps in the last cycle
+    pub steps: usize,
+    pub applications: usize,
+}
+
+/// Like `minres::gmresdr::triangularize`, for a right hand side with
+/// several columns.
+fn triangularize_block<T, U>(
+    h: &Array1<T>,
+    j: usize,
+    rots: &mut Vec<(usize, T, T)>,
+    S: &mut Array2<T>,
+) -> Array1<T>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    let mut r = Array1::zeros(S.nrows());
+    r.slice_mut(s![..h.len()]).assign(h);
+    for &(l, cs, sn) in rots.iter() {
+        let (dx, dy) = apply_plane_rotation(r[l], r[l + 1], cs, sn);
+        r[l] = dx;
+        r[l + 1] = dy;
+    }
+    for l in (j..h.len() - 1).rev() {
+        let (cs, sn) = generate_plane_rotation(r[l], r[l + 1]);
+        let (dx, dy) = apply_plane_rotation(r[l], r[l + 1], cs, sn);
+        r[l] = dx;
+        r[l + 1] = dy;
+        for mut col in S.axis_iter_mut(Axis(1)) {
+            let (dx, dy) = apply_plane_rotation(col[l], col[l + 1], cs, sn);
+            col[l] = dx;
+            col[l + 1] = dy;
+        }
+        rots.push((l, cs, sn));
+    }
+    r
+}
+
+pub fn block_gmres1<T, U>(
+    ags: &mut BlockGmresState<T, U>,
+    A: &dyn LinearOperator<T>,
+    M: Preconditioning<T>,
+) where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    block_gmres1_with_monitor(ags, A, M, 0, &mut SilentMonitor);
+}
+
+/// `block_gmres1`, calling `monitor` after every block step with the
+/// largest Givens residual estimate of the active columns; the steps are
+/// numbered from `iter + 1`. An `Abort` ends the cycle early, with `X`
+/// updated as usual, and is returned.
+pub fn block_gmres1_with_monitor<T, U>(
+    ags: &mut BlockGmresState<T, U>,
+    A: &dyn LinearOperator<T>,
+    M: Preconditioning<T>,
+    iter: usize,
+    monitor: &mut dyn Monitor<BlockGmresState<T, U>, U>,
+) -> MonitorAction
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    let n = ags.X.nrows();
+    let act: Vec<usize> = (0..ags.X.ncols()).filter(|&j| !ags.converged[j]).collect();
+    ags.steps = 0;
+    if act.is_empty() {
+        return MonitorAction::Continue;
+    }
+    let R0 = ags.R.select(Axis(1), &act);
+    let scale = column_norms(R0.view()).into_iter().fold(U::zero(), U::max);
+    let (V1, S0) = rrqr(R0.view(), ags.deflation_tol * scale);
+    let r0 = V1.ncols();
+    ags.V = V1.axis_iter(Axis(1)).map(|v| v.to_owned()).collect();
+    ags.block_sizes = vec![r0];
+    if r0 == 0 {
+        // the residuals vanish, the active columns are solved exactly
+        for &j in &act {
+            ags.converged[j] = true;
+        }
+        return MonitorAction::Continue;
+    }
+
+    let mut S = Array2::zeros(((ags.m + 1) * r0, act.len()));
+    S.slice_mut(s![..r0, ..]).assign(&S0);
+    let mut Rcols: Vec<Array1<T>> = Vec::new();
+    let mut rots = Vec::new();
+    // index of the first vector of the current block
+    let mut start = 0;
+    let mut action = MonitorAction::Continue;
+    for _ in 0..ags.m {
+        let rj = ags.block_sizes[ags.block_sizes.len() - 1];
+        let mut Vj = Array2::zeros((n, rj));
+        for (c, v) in ags.V[start..start + rj].iter().enumerate() {
+            Vj.column_mut(c).assign(v);
+        }
+        let mut W = apply_operator(A, M, Vj);
+        ags.applications += rj;
+        let wscale = column_norms(W.view()).into_iter().fold(U::zero(), U::max);
+
+        let total = ags.V.len();
+        let mut H = Array2::zeros((total, rj));
+        for _ in 0..2 {
+            for (i, v) in ags.V.iter().enumerate() {
+                for c in 0..rj {
+                    let h = inner(v.view(), W.column(c));
+                    let w = &W.column(c) - &(v * h);
+                    W.column_mut(c).assign(&w);
+                    H[(i, c)] = H[(i, c)] + h;
+                }
+            }
+        }
+        let (Vn, Hn) = rrqr(W.view(), ags.deflation_tol * wscale);
+        let rn = Vn.ncols();
+        for c in 0..rj {
+            let mut h = Array1::zeros(total + rn);
+            h.slice_mut(s![..total]).assign(&H.column(c));
+            h.slice_mut(s![total..]).assign(&Hn.column(c));
+            Rcols.push(triangularize_block(&h, start + c, &mut rots, &mut S));
+        }
+        ags.V.extend(Vn.axis_iter(Axis(1)).map(|v| v.to_owned()));
+        ags.steps += 1;
+        start += rj;
+
+        for (a, &j) in act.iter().enumerate() {
+            ags.resid[j] = norm(S.slice(s![start.., a]));
+        }
+        let worst = act.iter().map(|&j| ags.resid[j]).fold(U::zero(), U::max);
+        if monitor.observe(iter + ags.steps, worst, ags) == MonitorAction::Abort {
+            action = MonitorAction::Abort;
+            break;
+        }
+        if rn == 0 || act.iter().all(|&j| ags.resid[j] < ags.tol[j]) {
+            break;
+        }
+        ags.block_sizes.push(rn);
+    }
+
+    let k = start;
+    let mut Y = S.slice_move(s![..k, ..]);
+    for l in (0..k).rev() {
+        for mut y in Y.axis_iter_mut(Axis(1)) {
+            y[l] = y[l] / Rcols[l][l];
+            for i in 0..l {
+                y[i] = y[i] - Rcols[l][i] * y[l];
+            }
+        }
+    }
+    let mut Vk = Array2::zeros((n, k));
+    for (l, v) in ags.V.iter().take(k).enumerate() {
+        Vk.column_mut(l).assign(v);
+    }
+    let dX = apply_right_block(M, Vk.dot(&Y));
+    for (a, &j) in act.iter().enumerate() {
+        let x = &ags.X.column(j) + &dX.column(a);
+        ags.X.column_mut(j).assign(&x);
+    }
+
+    let Xa = ags.X.select(Axis(1), &act);
+    let W = &ags.B.select(Axis(1), &act) - &A.apply_block(Xa.view());
+    ags.applications += act.len();
+    let true_resid = column_norms(W.view());
+    let Ra = apply_left_block(M, W);
+    let resid = if M.true_residual() {
+        true_resid
+    } else {
+        column_norms(Ra.view())
+    };
+    for (a, &j) in act.iter().enumerate() {
+        ags.R.column_mut(j).assign(&Ra.column(a));
+        ags.resid[j] = resid[a];
+        ags.converged[j] = resid[a] < ags.tol[j];
+    }
+    action
+}
+
+impl<T, U> BlockGmresState<T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    pub fn create(
+        problem_size: usize,
+        nrhs: usize,
+        m: usize,
+        deflation_tol: U,
+        tol: U,
+    ) -> BlockGmresState<T, U> {
+        BlockGmresState {
+            m,
+            tol: vec![tol; nrhs],
+            deflation_tol,
+            X: Array2::zeros((problem_size, nrhs)),
+            B: Array2::zeros((problem_size, nrhs)),
+            R: Array2::zeros((problem_size, nrhs)),
+            resid: vec![U::zero(); nrhs],
+            converged: vec![false; nrhs],
+            V: Vec::new(),
+            block_sizes: Vec::new(),
+            steps: 0,
+            applications: 0,
+        }
+    }
+
+    pub fn init(
+        &mut self,
+        A: &dyn LinearOperator<T>,
+        X: ArrayView2<T>,
+        B: ArrayView2<T>,
+        M: Preconditioning<T>,
+    ) {
+        check_shape(A, B.nrows(), X.nrows());
+        assert_eq!(B.ncols(), X.ncols());
+        self.B = B.to_owned();
+        self.X = X.to_owned();
+        let W = &B - &A.apply_block(X);
+        self.applications = X.ncols();
+        let true_resid = column_norms(W.view());
+        self.R = apply_left_block(M, W);
+        self.resid = if M.true_residual() {
+            true_resid
+        } else {
+            column_norms(self.R.view())
+        };
+        self.converged = vec![false; X.ncols()];
+        self.steps = 0;
+    }
+
+    pub fn new(
+        A: &dyn LinearOperator<T>,
+        X: ArrayView2<T>,
+        B: ArrayView2<T>,
+        M: Preconditioning<T>,
+        m: usize,
+        deflation_tol: U,
+        tol: U,
+    ) -> Self {
+        let mut result = Self::create(B.nrows(), B.ncols(), m, deflation_tol, tol);
+        result.init(A, X, B, M);
+        result
+    }
+
+    pub fn next(&mut self, A: &dyn LinearOperator<T>, M: Preconditioning<T>) {
+        block_gmres1(self, A, M);
+    }
+
+    /// `next` with a per-step `monitor`, see `block_gmres1_with_monitor`
+    pub fn next_with_monitor(
+        &mut self,
+        A: &dyn LinearOperator<T>,
+        M: Preconditioning<T>,
+        iter: usize,
+        monitor: &mut dyn Monitor<Self, U>,
+    ) -> MonitorAction {
+        block_gmres1_with_monitor(self, A, M, iter, monitor)
+    }
+
+    pub fn calc_resid(&self, lhs: &dyn LinearOperator<T>, B: &Array2<T>) -> Array2<T> {
+        B - &lhs.apply_block(self.X.view())
+    }
+}
+
+/// Restarted block GMRES(m) as a `BlockIterativeSolver`; `m` counts block
+/// steps per cycle, and `iterations` block steps in total.
+///
+/// Preconditioning and the residual tests are as for `Gmres`, column by
+/// column. Directions whose norm relative to their block falls below
+/// `deflation_tol` (default `sqrt(eps)`) are deflated.
+pub struct BlockGmres<'a, T, U> {
+    pub m: usize,
+    pub M: Preconditioning<'a, T>,
+    pub deflation_tol: U,
+}
+
+impl<'a, T, U> BlockGmres<'a, T, U>
+where
+    U: Float,
+{
+    pub fn new(m: usize) -> BlockGmres<'a, T, U> {
+        BlockGmres {
+            m,
+            M: Preconditioning::None,
+            deflation_tol: U::epsilon().sqrt(),
+        }
+    }
+
+    /// left preconditioning by `M`
+    pub fn with_preconditioner(mut self, M: &'a dyn LinearOperator<T>) -> BlockGmres<'a, T, U> {
+        self.M = Preconditioning::Left(M);
+        self
+    }
+
+    pub fn with_preconditioning(mut self, M: Preconditioning<'a, T>) -> BlockGmres<'a, T, U> {
+        self.M = M;
+        self
+    }
+
+    pub fn with_deflation_tol(mut self, tol: U) -> BlockGmres<'a, T, U> {
+        self.deflation_tol = tol;
+        self
+    }
+}
+
+impl<'a, T, U> BlockIterativeSolver<T, U> for BlockGmres<'a, T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    type State = BlockGmresState<T, U>;
+
+    fn solve_with_monitor(
+        &self,
+        A: &dyn LinearOperator<T>,
+        B: ArrayView2<T>,
+        X0: ArrayView2<T>,
+        criterion: &StoppingCriterion<U>,
+        monitor: &mut dyn Monitor<BlockGmresState<T, U>, U>,
+    ) -> BlockSolveReport<T, U> {
+        let M = self.M;
+        let start = Instant::now();
+        let p = B.ncols();
+        let b_norms = if M.true_residual() {
+            column_norms(B)
+        } else {
+            column_norms(apply_left_block(M, B.to_owned()).view())
+        };
+        let mut ags = BlockGmresState::new(A, X0, B, M, self.m, self.deflation_tol, U::zero());
+        let r0_norms = ags.resid.clone();
+        for j in 0..p {
+            ags.tol[j] = criterion
+                .residual_threshold(r0_norms[j], b_norms[j])
+                .map_or(U::zero(), |(th, _)| th);
+        }
+        let mut reasons: Vec<Option<StopReason>> = vec![None; p];
+        let mut history = vec![r0_norms.iter().cloned().fold(U::zero(), U::max)];
+        let mut iterations = 0;
+        loop {
+            let elapsed = start.elapsed();
+            for j in 0..p {
+                if reasons[j].is_none() {
+                    let info =
+                        IterInfo::new(iterations, ags.resid[j], r0_norms[j], b_norms[j], elapsed);
+                    reasons[j] = criterion.check(&info);
+                    if reasons[j].is_none() && ags.resid[j] == U::zero() {
+                        // an exact column has nothing to iterate on
+                        reasons[j] = Some(criterion.exact_reason(r0_norms[j], b_norms[j]));
+                    }
+                    // a column that has stopped leaves the block
+                    ags.converged[j] |= reasons[j].is_some();
+                }
+            }
+            if reasons.iter().all(Option::is_some) {
+                break;
+            }
+            let action = ags.next_with_monitor(A, M, iterations, monitor);
+            iterations += ags.steps;
+            let worst = (0..p)
+                .filter(|&j| reasons[j].is_none())
+                .map(|j| ags.resid[j])
+                .fold(U::zero(), U::max);
+            history.push(worst);
+            if action == MonitorAction::Abort {
+                for r in reasons.iter_mut().filter(|r| r.is_none()) {
+                    *r = Some(StopReason::Aborted);
+                }
+                break;
+            }
+        }
+        let resid_norms = column_norms(ags.calc_resid(A, &ags.B).view());
+        BlockSolveReport {
+            X: ags.X,
+            iterations,
+            applications: ags.applications,
+            resid_norms,
+            reasons: reasons.into_iter().map(Option::unwrap).collect(),
+            history,
+        }
+    }
+}
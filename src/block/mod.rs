@@ -0,0 +1,145 @@
+//! Block Krylov solvers for `A X = B` with several right hand sides sharing
+//! one operator.
+//!
+//! All columns are advanced together, so each step applies the operator to
+//! a whole block through `LinearOperator::apply_block` (`sp_mul_a2` for
+//! sparse matrices) instead of one vector at a time, and the Krylov space
+//! of each column profits from the directions found for the others. Blocks
+//! are orthonormalized by a rank-revealing QR that drops (deflates)
+//! directions which are numerically dependent, and columns leave the block
+//! once they have converged.
+#![allow(non_snake_case)]
+
+pub mod cg;
+pub mod gmres;
+
+pub use cg::{BlockCg, BlockCgState};
+pub use gmres::{block_gmres1, BlockGmres, BlockGmresState};
+
+use crate::monitor::{Monitor, SilentMonitor};
+use crate::operator::LinearOperator;
+use crate::stopping::{StopReason, StoppingCriterion};
+use crate::utils::{inner, norm, ComplexOrReal};
+use ndarray::{Array1, Array2, ArrayView2, Axis};
+use num_traits::Float;
+
+/// Outcome of `BlockIterativeSolver::solve`, with one entry per column in
+/// `resid_norms` and `reasons`.
+#[derive(Debug, Clone)]
+pub struct BlockSolveReport<T, U> {
+    pub X: Array2<T>,
+    /// block iterations (block Arnoldi or Lanczos steps)
+    pub iterations: usize,
+    /// number of single-vector operator applications, summed over the blocks
+    pub applications: usize,
+    /// norms of the true residuals `b_j - A x_j` at the returned solution
+    pub resid_norms: Vec<U>,
+    /// the test that ended the iteration for each column
+    pub reasons: Vec<StopReason>,
+    /// largest residual norm estimate among the columns after each iteration
+    /// (after each restart cycle for block GMRES)
+    pub history: Vec<U>,
+}
+
+impl<T, U> BlockSolveReport<T, U> {
+    /// true if every column converged
+    pub fn converged(&self) -> bool {
+        self.reasons.iter().all(|r| r.is_converged())
+    }
+}
+
+/// Common entry point of the block solvers, the counterpart of
+/// `IterativeSolver`. The stopping criterion is applied to each column
+/// separately.
+pub trait BlockIterativeSolver<T, U> {
+    /// the state handed to monitors after each iteration
+    type State;
+
+    fn solve_with_monitor(
+        &self,
+        A: &dyn LinearOperator<T>,
+        B: ArrayView2<T>,
+        X0: ArrayView2<T>,
+        criterion: &StoppingCriterion<U>,
+        monitor: &mut dyn Monitor<Self::State, U>,
+    ) -> BlockSolveReport<T, U>;
+
+    fn solve(
+        &self,
+        A: &dyn LinearOperator<T>,
+        B: ArrayView2<T>,
+        X0: ArrayView2<T>,
+        criterion: &StoppingCriterion<U>,
+    ) -> BlockSolveReport<T, U> {
+        self.solve_with_monitor(A, B, X0, criterion, &mut SilentMonitor)
+    }
+}
+
+/// Rank-revealing QR by modified Gram–Schmidt with column pivoting (and one
+/// reorthogonalization): `V ≈ Q R` with orthonormal `Q` of `n x r` and `R`
+/// of `r x p`. Directions whose remaining norm is at most `tol` are
+/// dropped, so `r` is the numerical rank of `V` and the columns of `V`
+/// differ from those of `Q R` by at most `tol` in norm.
+pub fn rrqr<T, U>(V: ArrayView2<T>, tol: U) -> (Array2<T>, Array2<T>)
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float,
+{
+    let (n, p) = V.dim();
+    let mut W = V.to_owned();
+    let mut Q: Vec<Array1<T>> = Vec::with_capacity(p);
+    let mut R: Vec<Array1<T>> = Vec::with_capacity(p);
+    let mut chosen = vec![false; p];
+    loop {
+        let pivot = (0..p)
+            .filter(|&c| !chosen[c])
+            .map(|c| (c, norm(W.column(c))))
+            .fold(None, |acc: Option<(usize, U)>, (c, nc)| match acc {
+                Some((_, na)) if na >= nc => acc,
+                _ => Some((c, nc)),
+            });
+        let c = match pivot {
+            Some((c, nc)) if nc > tol => c,
+            _ => break,
+        };
+        chosen[c] = true;
+        let mut w = W.column(c).to_owned();
+        let mut row = Array1::zeros(p);
+        for (qi, ri) in Q.iter().zip(R.iter_mut()) {
+            let h = inner(qi.view(), w.view());
+            w = &w - &(qi * h);
+            ri[c] = ri[c] + h;
+        }
+        let nw = norm(w.view());
+        if nw <= tol {
+            continue;
+        }
+        let q = w / T::from(nw);
+        row[c] = T::from(nw);
+        for l in (0..p).filter(|&l| !chosen[l]) {
+            let h = inner(q.view(), W.column(l));
+            let wl = &W.column(l) - &(&q * h);
+            W.column_mut(l).assign(&wl);
+            row[l] = h;
+        }
+        Q.push(q);
+        R.push(row);
+    }
+    let r = Q.len();
+    let mut Qm = Array2::zeros((n, r));
+    let mut Rm = Array2::zeros((r, p));
+    for (i, (q, row)) in Q.iter().zip(R.iter()).enumerate() {
+        Qm.column_mut(i).assign(q);
+        Rm.row_mut(i).assign(row);
+    }
+    (Qm, Rm)
+}
+
+/// norms of the columns of `X`
+pub fn column_norms<T, U>(X: ArrayView2<T>) -> Vec<U>
+where
+    T: ComplexOrReal<U>,
+    U: Float,
+{
+    X.axis_iter(Axis(1)).map(|x| norm(x)).collect()
+}
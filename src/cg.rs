@@ -0,0 +1,263 @@
+#![allow(non_snake_case)]
+#![allow(clippy::many_single_char_names)]
+
+use crate::eigen::tridiag::tridiag_eigval;
+use crate::monitor::{Monitor, MonitorAction};
+use crate::operator::{check_shape, LinearOperator};
+use crate::solver::{IterativeSolver, SolveReport};
+use crate::stopping::{IterInfo, StopReason, StoppingCriterion};
+use crate::utils::{inner, norm, ComplexOrReal};
+use ndarray::{Array1, ArrayView1};
+use num_complex::Complex;
+use num_traits::Float;
+use std::time::Instant;
+
+/// State of the (preconditioned) conjugate gradient method for Hermitian
+/// positive definite `A`. The preconditioner `M` approximates `A^-1` and must
+/// itself be Hermitian positive definite.
+pub struct CgState<T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    pub x: Array1<T>,
+    pub res: Array1<T>,
+    /// preconditioned residual z = M r
+    pub z: Array1<T>,
+    pub p: Array1<T>,
+    /// r^H z
+    pub rz: U,
+    pub tol: U,
+    pub converged: bool,
+    /// step lengths, kept for the Lanczos eigenvalue estimate
+    pub alphas: Vec<U>,
+    pub betas: Vec<U>,
+}
+
+/// One CG step from `s_last`. Returns the new state with empty `alphas` and
+/// `betas`, and the step's `(alpha, beta)`, which `CgState::next` appends.
+pub(crate) fn cg_iter<T, U>(
+    lhs: &dyn LinearOperator<T>,
+    M: Option<&dyn LinearOperator<T>>,
+    s_last: &CgState<T, U>,
+) -> (CgState<T, U>, U, U)
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    let ap = lhs.apply(s_last.p.view());
+    let pap = Into::<Complex<U>>::into(inner(s_last.p.view(), ap.view())).re;
+    let alpha = s_last.rz / pap;
+    let x = &s_last.x + &(&s_last.p * T::from(alpha));
+    let res = &s_last.res - &(&ap * T::from(alpha));
+    let z = match M {
+        Some(M) => M.apply(res.view()),
+        None => res.clone(),
+    };
+    let rz = Into::<Complex<U>>::into(inner(res.view(), z.view())).re;
+    let beta = rz / s_last.rz;
+    let p = &z + &(&s_last.p * T::from(beta));
+    let converged = norm(res.view()) < s_last.tol;
+    let state = CgState {
+        x,
+        res,
+        z,
+        p,
+        rz,
+        tol: s_last.tol,
+        converged,
+        alphas: Vec::new(),
+        betas: Vec::new(),
+    };
+    (state, alpha, beta)
+}
+
+impl<T, U> CgState<T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    pub fn new(
+        lhs: &dyn LinearOperator<T>,
+        x: ArrayView1<T>,
+        b: ArrayView1<T>,
+        M: Option<&dyn LinearOperator<T>>,
+        tol: U,
+    ) -> CgState<T, U> {
+        check_shape(lhs, b.len(), x.len());
+        let res = &b - &lhs.apply(x);
+        let z = match M {
+            Some(M) => M.apply(res.view()),
+            None => res.clone(),
+        };
+        let rz = Into::<Complex<U>>::into(inner(res.view(), z.view())).re;
+        let p = z.clone();
+        let converged = norm(res.view()) < tol;
+        CgState {
+            x: x.to_owned(),
+            res,
+            z,
+            p,
+            rz,
+            tol,
+            converged,
+            alphas: Vec::new(),
+            betas: Vec::new(),
+        }
+    }
+
+    /// Returns `None` (and keeps the old state) if the step breaks down, which
+    /// happens when `A` or `M` is not positive definite.
+    pub fn next(
+        &mut self,
+        lhs: &dyn LinearOperator<T>,
+        M: Option<&dyn LinearOperator<T>>,
+    ) -> Option<()> {
+        let (mut ns, alpha, beta) = cg_iter(lhs, M, self);
+        if ns.valid() && alpha > U::zero() {
+            ns.alphas = std::mem::take(&mut self.alphas);
+            ns.betas = std::mem::take(&mut self.betas);
+            ns.alphas.push(alpha);
+            ns.betas.push(beta);
+            *self = ns;
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    pub fn calc_resid(&self, lhs: &dyn LinearOperator<T>, b: &Array1<T>) -> Array1<T> {
+        b - &lhs.apply(self.x.view())
+    }
+
+    pub fn converged(&self, lhs: &dyn LinearOperator<T>, b: &Array1<T>, th: U) -> bool {
+        norm(self.calc_resid(lhs, b).view()) < th
+    }
+
+    pub fn valid(&self) -> bool {
+        self.x.iter().all(|x| x.abs().is_finite())
+            && self.res.iter().all(|x| x.abs().is_finite())
+            && self.p.iter().all(|x| x.abs().is_finite())
+            && self.rz.is_finite()
+    }
+
+    /// Diagonal and off-diagonal of the Lanczos tridiagonal matrix implied by
+    /// the CG coefficients. Its eigenvalues (Ritz values) approximate those
+    /// of `M A`.
+    pub fn lanczos_tridiag(&self) -> (Vec<U>, Vec<U>) {
+        let k = self.alphas.len();
+        let mut diag = Vec::with_capacity(k);
+        let mut offdiag = Vec::with_capacity(k.saturating_sub(1));
+        for j in 0..k {
+            let d = if j == 0 {
+                U::one() / self.alphas[0]
+            } else {
+                U::one() / self.alphas[j] + self.betas[j - 1] / self.alphas[j - 1]
+            };
+            diag.push(d);
+            if j > 0 {
+                offdiag.push(self.betas[j - 1].sqrt() / self.alphas[j - 1]);
+            }
+        }
+        (diag, offdiag)
+    }
+
+    /// Estimates `(lambda_min, lambda_max)` of `M A` from the Ritz values
+    /// accumulated so far; `None` before the first iteration.
+    pub fn eig_estimate(&self) -> Option<(U, U)> {
+        if self.alphas.is_empty() {
+            return None;
+        }
+        let (diag, offdiag) = self.lanczos_tridiag();
+        let tol = U::epsilon().sqrt();
+        let lmin = tridiag_eigval(&diag, &offdiag, 0, tol);
+        let lmax = tridiag_eigval(&diag, &offdiag, diag.len() - 1, tol);
+        Some((lmin, lmax))
+    }
+
+    /// lambda_max / lambda_min of `M A`, a lower bound of its condition number
+    pub fn cond_estimate(&self) -> Option<U> {
+        self.eig_estimate().map(|(lmin, lmax)| lmax / lmin)
+    }
+}
+
+/// (Preconditioned) CG as an `IterativeSolver`. Residual tests use the
+/// unpreconditioned recursive residual.
+pub struct Cg<'a, T> {
+    pub M: Option<&'a dyn LinearOperator<T>>,
+}
+
+impl<'a, T> Cg<'a, T> {
+    pub fn new() -> Cg<'a, T> {
+        Cg { M: None }
+    }
+
+    pub fn with_preconditioner(mut self, M: &'a dyn LinearOperator<T>) -> Cg<'a, T> {
+        self.M = Some(M);
+        self
+    }
+}
+
+impl<'a, T> Default for Cg<'a, T> {
+    fn default() -> Cg<'a, T> {
+        Cg::new()
+    }
+}
+
+impl<'a, T, U> IterativeSolver<T, U> for Cg<'a, T>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    type State = CgState<T, U>;
+
+    fn solve_with_monitor(
+        &self,
+        A: &dyn LinearOperator<T>,
+        b: ArrayView1<T>,
+        x0: ArrayView1<T>,
+        criterion: &StoppingCriterion<U>,
+        monitor: &mut dyn Monitor<CgState<T, U>, U>,
+    ) -> SolveReport<T, U> {
+        let start = Instant::now();
+        let b = b.to_owned();
+        let b_norm = norm(b.view());
+        let mut state = CgState::new(A, x0, b.view(), self.M, U::zero());
+        let r0_norm = norm(state.res.view());
+        let mut history = vec![r0_norm];
+        let mut iterations = 0;
+        let reason = loop {
+            let info = IterInfo::new(
+                iterations,
+                history[iterations],
+                r0_norm,
+                b_norm,
+                start.elapsed(),
+            );
+            if let Some(reason) = criterion.check(&info) {
+                break reason;
+            }
+            if state.next(A, self.M).is_none() {
+                break StopReason::Breakdown;
+            }
+            iterations += 1;
+            let r = norm(state.res.view());
+            history.push(r);
+            if monitor.observe(iterations, r, &state) == MonitorAction::Abort {
+                break StopReason::Aborted;
+            }
+        };
+        let resid_norm = norm(state.calc_resid(A, &b).view());
+        let reason = match reason {
+            StopReason::Breakdown => criterion.breakdown_reason(resid_norm, r0_norm, b_norm),
+            _ => reason,
+        };
+        SolveReport {
+            x: state.x,
+            iterations,
+            resid_norm,
+            reason,
+            history,
+        }
+    }
+}
@@ -0,0 +1,229 @@
+#![allow(non_snake_case)]
+#![allow(clippy::many_single_char_names)]
+
+use crate::monitor::{Monitor, MonitorAction};
+use crate::operator::{check_adjoint, check_shape, LinearOperator};
+use crate::solver::{IterativeSolver, SolveReport};
+use crate::stopping::{IterInfo, LsqEstimates, StopReason, StoppingCriterion};
+use crate::utils::{norm, ComplexOrReal};
+use ndarray::{Array1, ArrayView1};
+use num_traits::Float;
+use std::time::Instant;
+
+/// State of CGLS, i.e. CG on the normal equations
+/// `(A^H A + λ^2 I) x = A^H b` of `min ‖A x - b‖^2 + λ^2 ‖x‖^2`, arranged so
+/// that `A^H A` is never formed. Mathematically equivalent to LSQR, but less
+/// robust when `A` is ill-conditioned; a handy reference for LSQR results.
+pub struct CglsState<T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    /// the damping `λ`
+    pub damp: U,
+    pub x: Array1<T>,
+    /// `b - A x`
+    pub res: Array1<T>,
+    /// the normal equation residual `A^H r - λ^2 x`
+    pub s: Array1<T>,
+    pub p: Array1<T>,
+    /// `‖s‖^2`
+    pub gamma: U,
+    /// lower bound on ‖(A; λ I)‖_2 from the Rayleigh quotients of the steps
+    pub anorm: U,
+    pub tol: U,
+    pub converged: bool,
+}
+
+pub fn cgls_iter<T, U>(
+    lhs: &dyn LinearOperator<T>,
+    s_last: &CglsState<T, U>,
+) -> Option<CglsState<T, U>>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    let delta = s_last.damp * s_last.damp;
+    let q = lhs.apply(s_last.p.view());
+    let pp = norm::<T, U>(s_last.p.view()).powi(2);
+    let qq = norm::<T, U>(q.view()).powi(2) + delta * pp;
+    if qq == U::zero() {
+        return None;
+    }
+    let alpha = s_last.gamma / qq;
+    let x = &s_last.x + &(&s_last.p * T::from(alpha));
+    let res = &s_last.res - &(&q * T::from(alpha));
+    let s = &lhs.apply_adjoint(res.view()) - &(&x * T::from(delta));
+    let gamma = norm::<T, U>(s.view()).powi(2);
+    let beta = gamma / s_last.gamma;
+    let p = &s + &(&s_last.p * T::from(beta));
+    let converged = gamma.sqrt() < s_last.tol;
+
+    Some(CglsState {
+        damp: s_last.damp,
+        x,
+        res,
+        s,
+        p,
+        gamma,
+        anorm: s_last.anorm.max((qq / pp).sqrt()),
+        tol: s_last.tol,
+        converged,
+    })
+}
+
+impl<T, U> CglsState<T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    /// `tol` bounds the normal equation residual `‖A^H r - λ^2 x‖`.
+    pub fn new(
+        lhs: &dyn LinearOperator<T>,
+        x: ArrayView1<T>,
+        b: ArrayView1<T>,
+        damp: U,
+        tol: U,
+    ) -> CglsState<T, U> {
+        check_shape(lhs, b.len(), x.len());
+        check_adjoint(lhs, "CGLS");
+        let res = &b - &lhs.apply(x);
+        let s = &lhs.apply_adjoint(res.view()) - &(&x * T::from(damp * damp));
+        let gamma = norm::<T, U>(s.view()).powi(2);
+        CglsState {
+            damp,
+            x: x.to_owned(),
+            res,
+            p: s.clone(),
+            s,
+            gamma,
+            anorm: U::zero(),
+            tol,
+            converged: gamma.sqrt() < tol,
+        }
+    }
+
+    /// Returns `None` (and keeps the old state) if `A p = 0` for an undamped
+    /// problem, i.e. the normal equations are already solved.
+    pub fn next(&mut self, lhs: &dyn LinearOperator<T>) -> Option<()> {
+        let ns = cgls_iter(lhs, self)?;
+        if ns.valid() {
+            *self = ns;
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    /// `sqrt(‖b - A x‖^2 + λ^2 ‖x‖^2)`, which is ‖b - A x‖ for `λ = 0`
+    pub fn damped_resid_norm(&self) -> U {
+        let r: U = norm(self.res.view());
+        let x: U = norm(self.x.view());
+        (r * r + self.damp * self.damp * x * x).sqrt()
+    }
+
+    pub fn calc_resid(&self, lhs: &dyn LinearOperator<T>, b: &Array1<T>) -> Array1<T> {
+        b - &lhs.apply(self.x.view())
+    }
+
+    pub fn converged(&self, lhs: &dyn LinearOperator<T>, b: &Array1<T>, th: U) -> bool {
+        norm(self.calc_resid(lhs, b).view()) < th
+    }
+
+    pub fn valid(&self) -> bool {
+        self.x.iter().all(|x| x.abs().is_finite())
+            && self.res.iter().all(|x| x.abs().is_finite())
+            && self.p.iter().all(|x| x.abs().is_finite())
+            && self.gamma.is_finite()
+    }
+}
+
+/// CGLS as an `IterativeSolver`. Like for `Lsqr`, the residual tests use the
+/// norm of the damped residual `(b - A x, -λ x)`; the Paige–Saunders tests
+/// get ‖A^H r - λ^2 x‖ and the lower bound `anorm` of `CglsState` (no
+/// condition estimate).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cgls<U> {
+    pub damp: U,
+}
+
+impl<U> Cgls<U>
+where
+    U: Float,
+{
+    pub fn new() -> Cgls<U> {
+        Cgls { damp: U::zero() }
+    }
+
+    /// solve the damped problem `min ‖A x - b‖^2 + λ^2 ‖x‖^2`
+    pub fn with_damping(mut self, damp: U) -> Cgls<U> {
+        self.damp = damp;
+        self
+    }
+}
+
+impl<T, U> IterativeSolver<T, U> for Cgls<U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    type State = CglsState<T, U>;
+
+    fn solve_with_monitor(
+        &self,
+        A: &dyn LinearOperator<T>,
+        b: ArrayView1<T>,
+        x0: ArrayView1<T>,
+        criterion: &StoppingCriterion<U>,
+        monitor: &mut dyn Monitor<CglsState<T, U>, U>,
+    ) -> SolveReport<T, U> {
+        let start = Instant::now();
+        let b = b.to_owned();
+        let b_norm = norm(b.view());
+        let mut state = CglsState::new(A, x0, b.view(), self.damp, U::zero());
+        let r0_norm = state.damped_resid_norm();
+        let mut history = vec![r0_norm];
+        let mut iterations = 0;
+        let reason = loop {
+            let mut info = IterInfo::new(
+                iterations,
+                history[iterations],
+                r0_norm,
+                b_norm,
+                start.elapsed(),
+            );
+            if iterations > 0 {
+                info.lsq = Some(LsqEstimates {
+                    arnorm: state.gamma.sqrt(),
+                    anorm: state.anorm,
+                    acond: U::zero(),
+                    xnorm: norm(state.x.view()),
+                });
+            }
+            if let Some(reason) = criterion.check(&info) {
+                break reason;
+            }
+            if state.next(A).is_none() {
+                break StopReason::Breakdown;
+            }
+            iterations += 1;
+            let r = state.damped_resid_norm();
+            history.push(r);
+            if monitor.observe(iterations, r, &state) == MonitorAction::Abort {
+                break StopReason::Aborted;
+            }
+        };
+        let resid_norm = norm(state.calc_resid(A, &b).view());
+        let reason = match reason {
+            StopReason::Breakdown => criterion.breakdown_reason(resid_norm, r0_norm, b_norm),
+            _ => reason,
+        };
+        SolveReport {
+            x: state.x,
+            iterations,
+            resid_norm,
+            reason,
+            history,
+        }
+    }
+}
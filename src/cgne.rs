@@ -0,0 +1,175 @@
+#![allow(non_snake_case)]
+#![allow(clippy::many_single_char_names)]
+
+use crate::monitor::{Monitor, MonitorAction};
+use crate::operator::{check_adjoint, check_shape, LinearOperator};
+use crate::solver::{IterativeSolver, SolveReport};
+use crate::stopping::{IterInfo, StopReason, StoppingCriterion};
+use crate::utils::{norm, ComplexOrReal};
+use ndarray::{Array1, ArrayView1};
+use num_traits::Float;
+use std::time::Instant;
+
+/// State of CGNE (Craig's method), i.e. CG on `A A^H y = b` with
+/// `x = A^H y`, without forming `A A^H`. It minimizes the error `‖x - x*‖`
+/// over the Krylov space, so for a consistent underdetermined system and
+/// `x0 = 0` it converges to the minimum-norm solution.
+pub struct CgneState<T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    pub x: Array1<T>,
+    pub res: Array1<T>,
+    /// search direction in the domain of `A`
+    pub p: Array1<T>,
+    /// `‖r‖^2`
+    pub gamma: U,
+    pub tol: U,
+    pub converged: bool,
+}
+
+pub fn cgne_iter<T, U>(
+    lhs: &dyn LinearOperator<T>,
+    s_last: &CgneState<T, U>,
+) -> Option<CgneState<T, U>>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    let pp = norm::<T, U>(s_last.p.view()).powi(2);
+    if pp == U::zero() {
+        return None;
+    }
+    let alpha = s_last.gamma / pp;
+    let x = &s_last.x + &(&s_last.p * T::from(alpha));
+    let res = &s_last.res - &(&lhs.apply(s_last.p.view()) * T::from(alpha));
+    let gamma = norm::<T, U>(res.view()).powi(2);
+    let beta = gamma / s_last.gamma;
+    let p = &lhs.apply_adjoint(res.view()) + &(&s_last.p * T::from(beta));
+    let converged = gamma.sqrt() < s_last.tol;
+
+    Some(CgneState {
+        x,
+        res,
+        p,
+        gamma,
+        tol: s_last.tol,
+        converged,
+    })
+}
+
+impl<T, U> CgneState<T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    pub fn new(
+        lhs: &dyn LinearOperator<T>,
+        x: ArrayView1<T>,
+        b: ArrayView1<T>,
+        tol: U,
+    ) -> CgneState<T, U> {
+        check_shape(lhs, b.len(), x.len());
+        check_adjoint(lhs, "CGNE");
+        let res = &b - &lhs.apply(x);
+        let gamma = norm::<T, U>(res.view()).powi(2);
+        CgneState {
+            x: x.to_owned(),
+            p: lhs.apply_adjoint(res.view()),
+            res,
+            gamma,
+            tol,
+            converged: gamma.sqrt() < tol,
+        }
+    }
+
+    /// Returns `None` (and keeps the old state) if `A^H r = 0` with `r != 0`,
+    /// i.e. the system is inconsistent.
+    pub fn next(&mut self, lhs: &dyn LinearOperator<T>) -> Option<()> {
+        let ns = cgne_iter(lhs, self)?;
+        if ns.valid() {
+            *self = ns;
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    pub fn calc_resid(&self, lhs: &dyn LinearOperator<T>, b: &Array1<T>) -> Array1<T> {
+        b - &lhs.apply(self.x.view())
+    }
+
+    pub fn converged(&self, lhs: &dyn LinearOperator<T>, b: &Array1<T>, th: U) -> bool {
+        norm(self.calc_resid(lhs, b).view()) < th
+    }
+
+    pub fn valid(&self) -> bool {
+        self.x.iter().all(|x| x.abs().is_finite())
+            && self.res.iter().all(|x| x.abs().is_finite())
+            && self.p.iter().all(|x| x.abs().is_finite())
+            && self.gamma.is_finite()
+    }
+}
+
+/// CGNE as an `IterativeSolver`, for consistent systems.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cgne;
+
+impl<T, U> IterativeSolver<T, U> for Cgne
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    type State = CgneState<T, U>;
+
+    fn solve_with_monitor(
+        &self,
+        A: &dyn LinearOperator<T>,
+        b: ArrayView1<T>,
+        x0: ArrayView1<T>,
+        criterion: &StoppingCriterion<U>,
+        monitor: &mut dyn Monitor<CgneState<T, U>, U>,
+    ) -> SolveReport<T, U> {
+        let start = Instant::now();
+        let b = b.to_owned();
+        let b_norm = norm(b.view());
+        let mut state = CgneState::new(A, x0, b.view(), U::zero());
+        let r0_norm = norm(state.res.view());
+        let mut history = vec![r0_norm];
+        let mut iterations = 0;
+        let reason = loop {
+            let info = IterInfo::new(
+                iterations,
+                history[iterations],
+                r0_norm,
+                b_norm,
+                start.elapsed(),
+            );
+            if let Some(reason) = criterion.check(&info) {
+                break reason;
+            }
+            if state.next(A).is_none() {
+                break StopReason::Breakdown;
+            }
+            iterations += 1;
+            let r = norm(state.res.view());
+            history.push(r);
+            if monitor.observe(iterations, r, &state) == MonitorAction::Abort {
+                break StopReason::Aborted;
+            }
+        };
+        let resid_norm = norm(state.calc_resid(A, &b).view());
+        let reason = match reason {
+            StopReason::Breakdown => criterion.breakdown_reason(resid_norm, r0_norm, b_norm),
+            _ => reason,
+        };
+        SolveReport {
+            x: state.x,
+            iterations,
+            resid_norm,
+            reason,
+            history,
+        }
+    }
+}
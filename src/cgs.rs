@@ -0,0 +1,198 @@
+#![allow(non_snake_case)]
+#![allow(clippy::many_single_char_names)]
+
+use crate::monitor::{Monitor, MonitorAction};
+use crate::operator::{check_shape, LinearOperator};
+use crate::solver::{IterativeSolver, SolveReport};
+use crate::stopping::{IterInfo, StopReason, StoppingCriterion};
+use crate::utils::{inner, norm, ComplexOrReal};
+use ndarray::{Array1, ArrayView1};
+use num_traits::Float;
+use std::time::Instant;
+
+/// State of Sonneveld's conjugate gradient squared method. Each step applies
+/// the BiCG polynomial twice, which needs two products with `A` but none with
+/// `A^H`; convergence is often fast but irregular.
+pub struct CgsState<T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    pub x: Array1<T>,
+    pub res: Array1<T>,
+    /// the shadow residual `r~`
+    pub res_shadow: Array1<T>,
+    pub u: Array1<T>,
+    pub p: Array1<T>,
+    /// `<r~, r>`
+    pub rho: T,
+    pub tol: U,
+    pub converged: bool,
+}
+
+pub fn cgs_iter<T, U>(
+    lhs: &dyn LinearOperator<T>,
+    s_last: &CgsState<T, U>,
+) -> Option<CgsState<T, U>>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    let v = lhs.apply(s_last.p.view());
+    let sigma = inner(s_last.res_shadow.view(), v.view());
+    if sigma == T::zero() || s_last.rho == T::zero() {
+        return None;
+    }
+    let alpha = s_last.rho / sigma;
+    let q = &s_last.u - &(&v * alpha);
+    let w = &s_last.u + &q;
+    let x = &s_last.x + &(&w * alpha);
+    let res = &s_last.res - &(&lhs.apply(w.view()) * alpha);
+    let rho = inner(s_last.res_shadow.view(), res.view());
+    let beta = rho / s_last.rho;
+    let u = &res + &(&q * beta);
+    let p = &u + &(&(&q + &(&s_last.p * beta)) * beta);
+    let converged = norm(res.view()) < s_last.tol;
+
+    Some(CgsState {
+        x,
+        res,
+        res_shadow: s_last.res_shadow.clone(),
+        u,
+        p,
+        rho,
+        tol: s_last.tol,
+        converged,
+    })
+}
+
+impl<T, U> CgsState<T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    pub fn new(
+        lhs: &dyn LinearOperator<T>,
+        x: ArrayView1<T>,
+        b: ArrayView1<T>,
+        tol: U,
+    ) -> CgsState<T, U> {
+        check_shape(lhs, b.len(), x.len());
+        let res = &b - &lhs.apply(x);
+        let rho = inner(res.view(), res.view());
+        let converged = norm(res.view()) < tol;
+        CgsState {
+            x: x.to_owned(),
+            res_shadow: res.clone(),
+            u: res.clone(),
+            p: res.clone(),
+            res,
+            rho,
+            tol,
+            converged,
+        }
+    }
+
+    /// Returns `None` (and keeps the old state) on a breakdown, i.e.
+    /// `<r~, r> = 0` or `<r~, A p> = 0`.
+    pub fn next(&mut self, lhs: &dyn LinearOperator<T>) -> Option<()> {
+        let ns = cgs_iter(lhs, self)?;
+        if ns.valid() {
+            *self = ns;
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    pub fn calc_resid(&self, lhs: &dyn LinearOperator<T>, b: &Array1<T>) -> Array1<T> {
+        b - &lhs.apply(self.x.view())
+    }
+
+    pub fn converged(&self, lhs: &dyn LinearOperator<T>, b: &Array1<T>, th: U) -> bool {
+        norm(self.calc_resid(lhs, b).view()) < th
+    }
+
+    pub fn valid(&self) -> bool {
+        self.x.iter().all(|x| x.abs().is_finite())
+            && self.res.iter().all(|x| x.abs().is_finite())
+            && self.p.iter().all(|x| x.abs().is_finite())
+            && self.u.iter().all(|x| x.abs().is_finite())
+    }
+}
+
+/// CGS as an `IterativeSolver`.
+///
+/// The residual tests see the recursively updated residual, which can drift
+/// away from `b - A x` when the residuals grow strongly in between. A
+/// passed test is therefore confirmed with the true residual; if that fails,
+/// CGS restarts from the current iterate.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cgs;
+
+impl<T, U> IterativeSolver<T, U> for Cgs
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    type State = CgsState<T, U>;
+
+    fn solve_with_monitor(
+        &self,
+        A: &dyn LinearOperator<T>,
+        b: ArrayView1<T>,
+        x0: ArrayView1<T>,
+        criterion: &StoppingCriterion<U>,
+        monitor: &mut dyn Monitor<CgsState<T, U>, U>,
+    ) -> SolveReport<T, U> {
+        let start = Instant::now();
+        let b = b.to_owned();
+        let b_norm = norm(b.view());
+        let mut state = CgsState::new(A, x0, b.view(), U::zero());
+        let r0_norm = norm(state.res.view());
+        let mut history = vec![r0_norm];
+        let mut iterations = 0;
+        let reason = loop {
+            let info = IterInfo::new(
+                iterations,
+                history[iterations],
+                r0_norm,
+                b_norm,
+                start.elapsed(),
+            );
+            if let Some(reason) = criterion.check(&info) {
+                if !reason.is_converged() {
+                    break reason;
+                }
+                let r = norm(state.calc_resid(A, &b).view());
+                let info = IterInfo::new(iterations, r, r0_norm, b_norm, start.elapsed());
+                if let Some(reason) = criterion.check(&info) {
+                    break reason;
+                }
+                state = CgsState::new(A, state.x.view(), b.view(), U::zero());
+                history[iterations] = norm(state.res.view());
+            }
+            if state.next(A).is_none() {
+                break StopReason::Breakdown;
+            }
+            iterations += 1;
+            let r = norm(state.res.view());
+            history.push(r);
+            if monitor.observe(iterations, r, &state) == MonitorAction::Abort {
+                break StopReason::Aborted;
+            }
+        };
+        let resid_norm = norm(state.calc_resid(A, &b).view());
+        let reason = match reason {
+            StopReason::Breakdown => criterion.breakdown_reason(resid_norm, r0_norm, b_norm),
+            _ => reason,
+        };
+        SolveReport {
+            x: state.x,
+            iterations,
+            resid_norm,
+            reason,
+            history,
+        }
+    }
+}
@@ -0,0 +1,227 @@
+#![allow(non_snake_case)]
+#![allow(clippy::many_single_char_names)]
+
+use crate::monitor::{Monitor, MonitorAction};
+use crate::operator::{check_shape, LinearOperator};
+use crate::solver::{IterativeSolver, SolveReport};
+use crate::stopping::{IterInfo, StopReason, StoppingCriterion};
+use crate::utils::{norm, ComplexOrReal};
+use ndarray::{Array1, ArrayView1};
+use num_traits::Float;
+use std::time::Instant;
+
+/// State of the Chebyshev semi-iteration for `A` whose (preconditioned)
+/// spectrum lies in `[lmin, lmax]` with `0 < lmin`, e.g. a Hermitian positive
+/// definite `M A`. The residual polynomial is the scaled Chebyshev
+/// polynomial on that interval, so the error contracts by about
+/// `(sqrt(κ) - 1) / (sqrt(κ) + 1)` per step, `κ = lmax / lmin`. No inner
+/// products are needed, which makes it a good smoother; the price is that
+/// the bounds have to be known, e.g. from `CgState::eig_estimate`.
+pub struct ChebyshevState<T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    pub x: Array1<T>,
+    pub res: Array1<T>,
+    /// the next update of `x`
+    pub d: Array1<T>,
+    /// center `(lmax + lmin) / 2` of the interval
+    pub theta: U,
+    /// half width `(lmax - lmin) / 2` of the interval
+    pub delta: U,
+    pub rho: U,
+    pub tol: U,
+    pub converged: bool,
+}
+
+fn apply_precond<T>(M: Option<&dyn LinearOperator<T>>, x: &Array1<T>) -> Array1<T>
+where
+    T: Clone,
+{
+    match M {
+        Some(M) => M.apply(x.view()),
+        None => x.clone(),
+    }
+}
+
+pub fn chebyshev_iter<T, U>(
+    lhs: &dyn LinearOperator<T>,
+    M: Option<&dyn LinearOperator<T>>,
+    s_last: &ChebyshevState<T, U>,
+) -> ChebyshevState<T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    let two = U::one() + U::one();
+    let x = &s_last.x + &s_last.d;
+    let res = &s_last.res - &lhs.apply(s_last.d.view());
+    let sigma = s_last.theta / s_last.delta;
+    let rho = U::one() / (two * sigma - s_last.rho);
+    let z = apply_precond(M, &res);
+    let d = &(&s_last.d * T::from(rho * s_last.rho)) + &(&z * T::from(two * rho / s_last.delta));
+    let converged = norm(res.view()) < s_last.tol;
+
+    ChebyshevState {
+        x,
+        res,
+        d,
+        theta: s_last.theta,
+        delta: s_last.delta,
+        rho,
+        tol: s_last.tol,
+        converged,
+    }
+}
+
+impl<T, U> ChebyshevState<T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        lhs: &dyn LinearOperator<T>,
+        x: ArrayView1<T>,
+        b: ArrayView1<T>,
+        M: Option<&dyn LinearOperator<T>>,
+        lmin: U,
+        lmax: U,
+        tol: U,
+    ) -> ChebyshevState<T, U> {
+        assert!(
+            U::zero() < lmin && lmin < lmax,
+            "Chebyshev needs eigenvalue bounds 0 < lmin < lmax"
+        );
+        check_shape(lhs, b.len(), x.len());
+        let two = U::one() + U::one();
+        let theta = (lmax + lmin) / two;
+        let delta = (lmax - lmin) / two;
+        let res = &b - &lhs.apply(x);
+        let d = apply_precond(M, &res) * T::from(U::one() / theta);
+        let converged = norm(res.view()) < tol;
+        ChebyshevState {
+            x: x.to_owned(),
+            res,
+            d,
+            theta,
+            delta,
+            rho: delta / theta,
+            tol,
+            converged,
+        }
+    }
+
+    /// Returns `None` (and keeps the old state) if the iterate is no longer
+    /// finite, which happens when the bounds are far off.
+    pub fn next(
+        &mut self,
+        lhs: &dyn LinearOperator<T>,
+        M: Option<&dyn LinearOperator<T>>,
+    ) -> Option<()> {
+        let ns = chebyshev_iter(lhs, M, self);
+        if ns.valid() {
+            *self = ns;
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    pub fn calc_resid(&self, lhs: &dyn LinearOperator<T>, b: &Array1<T>) -> Array1<T> {
+        b - &lhs.apply(self.x.view())
+    }
+
+    pub fn converged(&self, lhs: &dyn LinearOperator<T>, b: &Array1<T>, th: U) -> bool {
+        norm(self.calc_resid(lhs, b).view()) < th
+    }
+
+    pub fn valid(&self) -> bool {
+        self.x.iter().all(|x| x.abs().is_finite())
+            && self.res.iter().all(|x| x.abs().is_finite())
+            && self.d.iter().all(|x| x.abs().is_finite())
+    }
+}
+
+/// Chebyshev iteration as an `IterativeSolver`, for the spectrum of `M A`
+/// in `[lmin, lmax]`.
+pub struct Chebyshev<'a, T, U> {
+    pub lmin: U,
+    pub lmax: U,
+    pub M: Option<&'a dyn LinearOperator<T>>,
+}
+
+impl<'a, T, U> Chebyshev<'a, T, U> {
+    pub fn new(lmin: U, lmax: U) -> Chebyshev<'a, T, U> {
+        Chebyshev {
+            lmin,
+            lmax,
+            M: None,
+        }
+    }
+
+    pub fn with_preconditioner(mut self, M: &'a dyn LinearOperator<T>) -> Chebyshev<'a, T, U> {
+        self.M = Some(M);
+        self
+    }
+}
+
+impl<'a, T, U> IterativeSolver<T, U> for Chebyshev<'a, T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    type State = ChebyshevState<T, U>;
+
+    fn solve_with_monitor(
+        &self,
+        A: &dyn LinearOperator<T>,
+        b: ArrayView1<T>,
+        x0: ArrayView1<T>,
+        criterion: &StoppingCriterion<U>,
+        monitor: &mut dyn Monitor<ChebyshevState<T, U>, U>,
+    ) -> SolveReport<T, U> {
+        let start = Instant::now();
+        let b = b.to_owned();
+        let b_norm = norm(b.view());
+        let mut state =
+            ChebyshevState::new(A, x0, b.view(), self.M, self.lmin, self.lmax, U::zero());
+        let r0_norm = norm(state.res.view());
+        let mut history = vec![r0_norm];
+        let mut iterations = 0;
+        let reason = loop {
+            let info = IterInfo::new(
+                iterations,
+                history[iterations],
+                r0_norm,
+                b_norm,
+                start.elapsed(),
+            );
+            if let Some(reason) = criterion.check(&info) {
+                break reason;
+            }
+            if state.next(A, self.M).is_none() {
+                break StopReason::Breakdown;
+            }
+            iterations += 1;
+            let r = norm(state.res.view());
+            history.push(r);
+            if monitor.observe(iterations, r, &state) == MonitorAction::Abort {
+                break StopReason::Aborted;
+            }
+        };
+        let resid_norm = norm(state.calc_resid(A, &b).view());
+        let reason = match reason {
+            StopReason::Breakdown => criterion.breakdown_reason(resid_norm, r0_norm, b_norm),
+            _ => reason,
+        };
+        SolveReport {
+            x: state.x,
+            iterations,
+            resid_norm,
+            reason,
+            history,
+        }
+    }
+}
@@ -0,0 +1,185 @@
+#![allow(non_snake_case)]
+#![allow(clippy::many_single_char_names)]
+
+use crate::lsqr::{bidiag_step, eculid_norm};
+use crate::monitor::{Monitor, MonitorAction};
+use crate::operator::{check_adjoint, LinearOperator};
+use crate::solver::{IterativeSolver, SolveReport};
+use crate::stopping::{IterInfo, StopReason, StoppingCriterion};
+use ndarray::ScalarOperand;
+use ndarray::{Array1, ArrayView1};
+use num_traits::Float;
+use std::time::Instant;
+
+/// State of CRAIG (Paige and Saunders' formulation of Craig's method) for
+/// the minimum-norm solution of a consistent system `A x = b`. It runs on
+/// the Golub–Kahan bidiagonalization of LSQR: with the lower bidiagonal
+/// `L_k` of the `alpha`s and `beta`s, `x_k = V_k z_k` where
+/// `L_k z_k = ‖b‖ e_1`. Mathematically equal to CGNE from `x0 = 0`, but
+/// numerically more reliable.
+#[derive(Clone)]
+pub struct CraigState<T>
+where
+    T: Float + Copy + Default + ScalarOperand,
+{
+    pub x: Array1<T>,
+    pub alpha: T,
+    pub u: Array1<T>,
+    pub v: Array1<T>,
+    /// the next entry of `z`, the coefficient of `v` in the next step
+    pub zeta: T,
+    /// ‖b - A x‖, which equals `|zeta_k| beta_{k+1}`
+    pub rnorm: T,
+}
+
+pub fn craig_iter<T>(A: &dyn LinearOperator<T>, s_last: &CraigState<T>) -> CraigState<T>
+where
+    T: Float + Copy + Default + ScalarOperand + std::fmt::Debug,
+{
+    let x = &s_last.x + &(&s_last.v * s_last.zeta);
+    let (beta, u, alpha, v) = bidiag_step(A, &s_last.u, &s_last.v, s_last.alpha);
+    let rnorm = s_last.zeta.abs() * beta;
+    let zeta = if beta == T::zero() {
+        T::zero()
+    } else {
+        -beta * s_last.zeta / alpha
+    };
+    CraigState {
+        x,
+        alpha,
+        u,
+        v,
+        zeta,
+        rnorm,
+    }
+}
+
+pub fn craig_init<T>(A: &dyn LinearOperator<T>, b: &Array1<T>) -> CraigState<T>
+where
+    T: Float + Copy + Default + ScalarOperand,
+{
+    assert_eq!(A.nrows(), b.len());
+    check_adjoint(A, "CRAIG");
+    let beta = eculid_norm(b);
+    let u = if beta > T::zero() {
+        b / beta
+    } else {
+        b.clone()
+    };
+    let mut v = A.apply_adjoint(u.view());
+    let alpha = eculid_norm(&v);
+    if alpha > T::zero() {
+        v = v / alpha;
+    }
+    CraigState {
+        x: Array1::zeros(A.ncols()),
+        alpha,
+        u,
+        v,
+        zeta: if beta > T::zero() {
+            beta / alpha
+        } else {
+            T::zero()
+        },
+        rnorm: beta,
+    }
+}
+
+impl<T> CraigState<T>
+where
+    T: Float + Copy + Default + ScalarOperand + std::fmt::Debug,
+{
+    pub fn new(A: &dyn LinearOperator<T>, b: &Array1<T>) -> CraigState<T> {
+        craig_init(A, b)
+    }
+
+    /// Returns `None` (and keeps the old state) if some `alpha` vanishes
+    /// before `b - A x` does, i.e. the system is inconsistent.
+    pub fn next(&mut self, A: &dyn LinearOperator<T>) -> Option<()> {
+        let ns = craig_iter(A, self);
+        if ns.valid() {
+            *self = ns;
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    pub fn calc_resid(&self, A: &dyn LinearOperator<T>, b: &Array1<T>) -> Array1<T> {
+        b - &A.apply(self.x.view())
+    }
+
+    pub fn valid(&self) -> bool {
+        self.x.iter().all(|x| x.is_finite())
+            && self.v.iter().all(|x| x.is_finite())
+            && self.zeta.is_finite()
+    }
+}
+
+/// CRAIG as an `IterativeSolver`; `x0` is handled by solving for the
+/// correction `A dx = b - A x0`, so the result is the solution closest to
+/// `x0`. The residual tests use the recurrence for ‖r‖.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Craig;
+
+impl<T> IterativeSolver<T, T> for Craig
+where
+    T: Float + Copy + Default + ScalarOperand + std::fmt::Debug,
+{
+    type State = CraigState<T>;
+
+    fn solve_with_monitor(
+        &self,
+        A: &dyn LinearOperator<T>,
+        b: ArrayView1<T>,
+        x0: ArrayView1<T>,
+        criterion: &StoppingCriterion<T>,
+        monitor: &mut dyn Monitor<CraigState<T>, T>,
+    ) -> SolveReport<T, T> {
+        let start = Instant::now();
+        let b = b.to_owned();
+        let b_norm = eculid_norm(&b);
+        let r0 = &b - &A.apply(x0);
+        let r0_norm = eculid_norm(&r0);
+        let mut history = vec![r0_norm];
+        let mut iterations = 0;
+        let mut state = None;
+        let reason = loop {
+            let info = IterInfo::new(
+                iterations,
+                history[iterations],
+                r0_norm,
+                b_norm,
+                start.elapsed(),
+            );
+            if let Some(reason) = criterion.check(&info) {
+                break reason;
+            }
+            let s = state.get_or_insert_with(|| CraigState::new(A, &r0));
+            if s.next(A).is_none() {
+                break StopReason::Breakdown;
+            }
+            iterations += 1;
+            history.push(s.rnorm);
+            if monitor.observe(iterations, s.rnorm, s) == MonitorAction::Abort {
+                break StopReason::Aborted;
+            }
+        };
+        let x = match state {
+            Some(s) => &x0 + &s.x,
+            None => x0.to_owned(),
+        };
+        let resid_norm = eculid_norm(&(&b - &A.apply(x.view())));
+        let reason = match reason {
+            StopReason::Breakdown => criterion.breakdown_reason(resid_norm, r0_norm, b_norm),
+            _ => reason,
+        };
+        SolveReport {
+            x,
+            iterations,
+            resid_norm,
+            reason,
+            history,
+        }
+    }
+}
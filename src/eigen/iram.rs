@@ -1,11 +1,12 @@
 #![allow(non_snake_case)]
 use crate::arnoldi::{ArnoldiErr, ArnoldiSpace};
 use crate::eigen::qr::qr_naive_eigvals;
+use crate::operator::LinearOperator;
 use crate::utils::ComplexOrReal;
 
 use crate::qr::householder_reflection;
 use crate::utils::hermit;
-use ndarray::{s, Array1, Array2, ArrayView1, ScalarOperand};
+use ndarray::{s, Array2, ScalarOperand};
 use num_complex::Complex;
 use num_traits::Float;
 use std::marker::PhantomData;
@@ -26,10 +27,22 @@ where
             .iter()
             .map(|arr| arr.map(|&x| Complex::from(x)))
             .collect();
+        let W = self
+            .W
+            .iter()
+            .map(|arr| arr.map(|&x| Complex::from(x)))
+            .collect();
+        let sigma = self.sigma.iter().map(|&x| Complex::from(x)).collect();
 
         ArnoldiSpace {
             H,
             Q,
+            orth: self.orth,
+            W,
+            sigma,
+            measure_loss: self.measure_loss,
+            loss: self.loss.clone(),
+            breakdown_tol: self.breakdown_tol,
             phantom: PhantomData,
         }
     }
@@ -40,16 +53,31 @@ where
     U: Float + std::fmt::Debug + Default,
     Complex<U>: ScalarOperand,
 {
+    /// Extends the space by `p` vectors and compresses it back with `p`
+    /// implicit shifts. If the Arnoldi process breaks down instead, the space
+    /// is left at the invariant subspace it found (see `is_invariant` and
+    /// `invariant_subspace`) and further restarts do nothing.
     pub fn restart(
         &mut self,
-        A: &dyn Fn(ArrayView1<Complex<U>>) -> Array1<Complex<U>>,
+        A: &dyn LinearOperator<Complex<U>>,
         p: usize,
         tol: U,
     ) -> Result<(), ArnoldiErr> {
+        if self.is_invariant() {
+            return Ok(());
+        }
         let mut temp_arnoldi = self.clone();
         let k = self.H.len();
         for _i in 0..p {
-            temp_arnoldi.iter(A)?;
+            match temp_arnoldi.iter(A) {
+                Ok(()) => {}
+                // the space found is invariant, no restart needed
+                Err(ArnoldiErr::HMM1Zero) => {
+                    *self = temp_arnoldi;
+                    return Ok(());
+                }
+                Err(e) => return Err(e),
+            }
         }
 
         let m = k + p;
@@ -83,7 +111,6 @@ where
         }
 
         let beta_k = Hm[(k, k - 1)];
-        println!("{:?}", beta_k);
         let sigma_k = Q[(m - 1, k - 1)];
         let fm = temp_arnoldi.get_f();
 
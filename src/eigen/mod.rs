@@ -1,2 +1,3 @@
 pub mod iram;
 pub mod qr;
+pub mod tridiag;
@@ -174,7 +174,6 @@ where
     let mut i = 0;
     let mut result = Vec::new();
     while i < n {
-        eprintln!("{}", i);
         if i >= n - 1 || a[(i + 1, i)].abs() < tol {
             result.push(Complex::from(a[(i, i)]));
             i += 1;
@@ -0,0 +1,74 @@
+use num_traits::Float;
+
+/// Number of eigenvalues smaller than `x` of the symmetric tridiagonal matrix
+/// with diagonal `diag` and off-diagonal `offdiag` (Sturm sequence count).
+pub fn sturm_count<U>(diag: &[U], offdiag: &[U], x: U) -> usize
+where
+    U: Float,
+{
+    let mut count = 0;
+    let mut q = U::one();
+    for (i, &d) in diag.iter().enumerate() {
+        q = if i == 0 {
+            d - x
+        } else {
+            let e = offdiag[i - 1];
+            let q1 = if q == U::zero() {
+                U::epsilon() * (e.abs() + U::epsilon())
+            } else {
+                q
+            };
+            d - x - e * e / q1
+        };
+        if q < U::zero() {
+            count += 1;
+        }
+    }
+    count
+}
+
+/// Gershgorin interval containing all eigenvalues.
+pub fn gershgorin_bounds<U>(diag: &[U], offdiag: &[U]) -> (U, U)
+where
+    U: Float,
+{
+    let n = diag.len();
+    let mut lo = U::infinity();
+    let mut hi = U::neg_infinity();
+    for i in 0..n {
+        let mut r = U::zero();
+        if i > 0 {
+            r = r + offdiag[i - 1].abs();
+        }
+        if i + 1 < n {
+            r = r + offdiag[i].abs();
+        }
+        lo = lo.min(diag[i] - r);
+        hi = hi.max(diag[i] + r);
+    }
+    (lo, hi)
+}
+
+/// The `k`-th smallest (0-based) eigenvalue of a symmetric tridiagonal matrix,
+/// by bisection to relative accuracy `tol`.
+pub fn tridiag_eigval<U>(diag: &[U], offdiag: &[U], k: usize, tol: U) -> U
+where
+    U: Float,
+{
+    assert!(k < diag.len());
+    assert_eq!(diag.len(), offdiag.len() + 1);
+    let two = U::one() + U::one();
+    let (mut lo, mut hi) = gershgorin_bounds(diag, offdiag);
+    for _i in 0..200 {
+        let mid = (lo + hi) / two;
+        if hi - lo <= tol * lo.abs().max(hi.abs()) || mid == lo || mid == hi {
+            break;
+        }
+        if sturm_count(diag, offdiag, mid) > k {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+    (lo + hi) / two
+}
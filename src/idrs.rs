@@ -0,0 +1,306 @@
+#![allow(non_snake_case)]
+#![allow(clippy::many_single_char_names)]
+
+use crate::monitor::{Monitor, MonitorAction};
+use crate::operator::{check_shape, LinearOperator};
+use crate::solver::{IterativeSolver, SolveReport};
+use crate::stopping::{IterInfo, StopReason, StoppingCriterion};
+use crate::utils::{inner, norm, ComplexOrReal};
+use ndarray::{Array1, Array2, ArrayView1};
+use num_traits::Float;
+use std::time::Instant;
+
+/// State of IDR(s) in the biorthogonal variant of van Gijzen and Sonneveld.
+/// The residuals are forced into nested subspaces orthogonal to the `s`
+/// shadow vectors; one step consists of `s + 1` products with `A`. IDR(1) is
+/// mathematically equivalent to BiCGStab, larger `s` converges in fewer
+/// products at the price of `3 s` stored vectors. The preconditioner
+/// `M ≈ A^-1` is applied from the right, so `res` is the residual of the
+/// original system.
+pub struct IdrsState<T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    pub x: Array1<T>,
+    pub res: Array1<T>,
+    /// orthonormal shadow vectors `P`
+    pub shadow: Vec<Array1<T>>,
+    /// `G = A U`, with `P^H G` lower triangular
+    pub g: Vec<Array1<T>>,
+    /// search directions
+    pub u: Vec<Array1<T>>,
+    /// `P^H G`
+    pub m: Array2<T>,
+    pub omega: T,
+    pub tol: U,
+    pub converged: bool,
+}
+
+/// `omega` is pushed away from the minimal residual value when `t` and `r`
+/// are closer to orthogonal than this, which keeps it from vanishing
+const KAPPA: f64 = 0.7;
+
+/// `s` orthonormal vectors with pseudo-random entries; the generator is
+/// seeded so that runs are reproducible.
+fn shadow_space<T, U>(n: usize, s: usize) -> Vec<Array1<T>>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut rand = || {
+        seed = seed
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        U::from((seed >> 11) as f64 / (1u64 << 53) as f64 - 0.5).unwrap()
+    };
+    let mut p: Vec<Array1<T>> = Vec::with_capacity(s);
+    for _ in 0..s {
+        let mut v: Array1<T> = (0..n).map(|_| T::from(rand())).collect();
+        for q in &p {
+            let c = inner(q.view(), v.view());
+            v = v - &(q * c);
+        }
+        let nv: U = norm(v.view());
+        p.push(v.map(|&x| x / T::from(nv)));
+    }
+    p
+}
+
+pub fn idrs_iter<T, U>(
+    lhs: &dyn LinearOperator<T>,
+    M: Option<&dyn LinearOperator<T>>,
+    s_last: &IdrsState<T, U>,
+) -> Option<IdrsState<T, U>>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    let s = s_last.shadow.len();
+    let P = &s_last.shadow;
+    let precond = |v: Array1<T>| match M {
+        Some(M) => M.apply(v.view()),
+        None => v,
+    };
+    let mut x = s_last.x.clone();
+    let mut res = s_last.res.clone();
+    let mut g = s_last.g.clone();
+    let mut u = s_last.u.clone();
+    let mut m = s_last.m.clone();
+    let mut f: Vec<T> = P.iter().map(|p| inner(p.view(), res.view())).collect();
+
+    for k in 0..s {
+        // solve the lower triangular m[k.., k..] c = f[k..]
+        let mut c = vec![T::zero(); s - k];
+        for i in 0..s - k {
+            let mut t = f[k + i];
+            for j in 0..i {
+                t = t - m[(k + i, k + j)] * c[j];
+            }
+            c[i] = t / m[(k + i, k + i)];
+        }
+        let mut v = res.clone();
+        for (i, &ci) in c.iter().enumerate() {
+            v = v - &(&g[k + i] * ci);
+        }
+        let v = precond(v);
+        let mut uk = &v * s_last.omega;
+        for (i, &ci) in c.iter().enumerate() {
+            uk = uk + &(&u[k + i] * ci);
+        }
+        let mut gk = lhs.apply(uk.view());
+        // make the new G orthogonal to P[..k]
+        for i in 0..k {
+            let alpha = inner(P[i].view(), gk.view()) / m[(i, i)];
+            gk = gk - &(&g[i] * alpha);
+            uk = uk - &(&u[i] * alpha);
+        }
+        for i in k..s {
+            m[(i, k)] = inner(P[i].view(), gk.view());
+        }
+        if m[(k, k)] == T::zero() {
+            return None;
+        }
+        let beta = f[k] / m[(k, k)];
+        res = res - &(&gk * beta);
+        x = x + &(&uk * beta);
+        for i in k + 1..s {
+            f[i] = f[i] - beta * m[(i, k)];
+        }
+        g[k] = gk;
+        u[k] = uk;
+    }
+
+    // enter the next subspace by a minimal residual step
+    let v = precond(res.clone());
+    let t = lhs.apply(v.view());
+    let nt: U = norm(t.view());
+    let nr: U = norm(res.view());
+    if nt == U::zero() {
+        return None;
+    }
+    let ts = inner(t.view(), res.view());
+    let rho = ts.abs() / (nt * nr);
+    let mut omega = ts / T::from(nt * nt);
+    let kappa = U::from(KAPPA).unwrap();
+    if rho < kappa {
+        omega = omega * T::from(kappa / rho);
+    }
+    if omega == T::zero() {
+        return None;
+    }
+    res = res - &(&t * omega);
+    x = x + &(&v * omega);
+    let converged = norm(res.view()) < s_last.tol;
+
+    Some(IdrsState {
+        x,
+        res,
+        shadow: s_last.shadow.clone(),
+        g,
+        u,
+        m,
+        omega,
+        tol: s_last.tol,
+        converged,
+    })
+}
+
+impl<T, U> IdrsState<T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    pub fn new(
+        lhs: &dyn LinearOperator<T>,
+        x: ArrayView1<T>,
+        b: ArrayView1<T>,
+        s: usize,
+        tol: U,
+    ) -> IdrsState<T, U> {
+        assert!(s >= 1, "IDR(s) needs s >= 1");
+        check_shape(lhs, b.len(), x.len());
+        let n = x.len();
+        assert!(s <= n, "IDR(s) needs s <= n");
+        let res = &b - &lhs.apply(x);
+        let converged = norm(res.view()) < tol;
+        IdrsState {
+            x: x.to_owned(),
+            res,
+            shadow: shadow_space(n, s),
+            g: vec![Array1::zeros(n); s],
+            u: vec![Array1::zeros(n); s],
+            m: Array2::eye(s),
+            omega: T::one(),
+            tol,
+            converged,
+        }
+    }
+
+    /// Returns `None` (and keeps the old state) on a breakdown, i.e. a
+    /// singular `P^H G` or a vanishing `omega`.
+    pub fn next(
+        &mut self,
+        lhs: &dyn LinearOperator<T>,
+        M: Option<&dyn LinearOperator<T>>,
+    ) -> Option<()> {
+        let ns = idrs_iter(lhs, M, self)?;
+        if ns.valid() {
+            *self = ns;
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    pub fn calc_resid(&self, lhs: &dyn LinearOperator<T>, b: &Array1<T>) -> Array1<T> {
+        b - &lhs.apply(self.x.view())
+    }
+
+    pub fn converged(&self, lhs: &dyn LinearOperator<T>, b: &Array1<T>, th: U) -> bool {
+        norm(self.calc_resid(lhs, b).view()) < th
+    }
+
+    pub fn valid(&self) -> bool {
+        self.x.iter().all(|x| x.abs().is_finite())
+            && self.res.iter().all(|x| x.abs().is_finite())
+            && self.m.iter().all(|x| x.abs().is_finite())
+    }
+}
+
+/// IDR(s) as an `IterativeSolver`; an iteration is one step of `IdrsState`.
+pub struct Idrs<'a, T> {
+    pub s: usize,
+    pub M: Option<&'a dyn LinearOperator<T>>,
+}
+
+impl<'a, T> Idrs<'a, T> {
+    pub fn new(s: usize) -> Idrs<'a, T> {
+        Idrs { s, M: None }
+    }
+
+    /// right preconditioning by `M`
+    pub fn with_preconditioner(mut self, M: &'a dyn LinearOperator<T>) -> Idrs<'a, T> {
+        self.M = Some(M);
+        self
+    }
+}
+
+impl<'a, T, U> IterativeSolver<T, U> for Idrs<'a, T>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    type State = IdrsState<T, U>;
+
+    fn solve_with_monitor(
+        &self,
+        A: &dyn LinearOperator<T>,
+        b: ArrayView1<T>,
+        x0: ArrayView1<T>,
+        criterion: &StoppingCriterion<U>,
+        monitor: &mut dyn Monitor<IdrsState<T, U>, U>,
+    ) -> SolveReport<T, U> {
+        let start = Instant::now();
+        let b = b.to_owned();
+        let b_norm = norm(b.view());
+        let mut state = IdrsState::new(A, x0, b.view(), self.s, U::zero());
+        let r0_norm = norm(state.res.view());
+        let mut history = vec![r0_norm];
+        let mut iterations = 0;
+        let reason = loop {
+            let info = IterInfo::new(
+                iterations,
+                history[iterations],
+                r0_norm,
+                b_norm,
+                start.elapsed(),
+            );
+            if let Some(reason) = criterion.check(&info) {
+                break reason;
+            }
+            if state.next(A, self.M).is_none() {
+                break StopReason::Breakdown;
+            }
+            iterations += 1;
+            let r = norm(state.res.view());
+            history.push(r);
+            if monitor.observe(iterations, r, &state) == MonitorAction::Abort {
+                break StopReason::Aborted;
+            }
+        };
+        let resid_norm = norm(state.calc_resid(A, &b).view());
+        let reason = match reason {
+            StopReason::Breakdown => criterion.breakdown_reason(resid_norm, r0_norm, b_norm),
+            _ => reason,
+        };
+        SolveReport {
+            x: state.x,
+            iterations,
+            resid_norm,
+            reason,
+            history,
+        }
+    }
+}
@@ -355,7 +355,7 @@ where
             writeln!(&mut f).unwrap();
         }
         let mut entries = self.entries.clone();
-        entries[..].sort_by(|&a, &b| (a.j, a.i).cmp(&(b.j, b.i)));
+        entries[..].sort_by_key(|&a| (a.j, a.i));
 
         for RawEntry { i, j, value: v } in entries {
             if let Storage::Sparse = self.storage {
@@ -372,10 +372,9 @@ where
         let mut entries: Vec<_> = self
             .entries
             .iter()
-            .map(|x| self.qual.expand_items(x))
-            .flatten()
+            .flat_map(|x| self.qual.expand_items(x))
             .collect();
-        entries[..].sort_by(|&a, &b| (a.i, a.j).cmp(&(b.i, b.j)));
+        entries[..].sort_by_key(|&a| (a.i, a.j));
 
         let mut indptr = vec![0];
         let mut indices = vec![];
@@ -424,8 +423,7 @@ where
         let entries: Vec<_> = self
             .entries
             .iter()
-            .map(|x| self.qual.expand_items(x))
-            .flatten()
+            .flat_map(|x| self.qual.expand_items(x))
             .collect();
         let size=if self.width==1{self.height}else{self.width};
         let mut result = Array1::zeros(size);
@@ -464,8 +462,7 @@ where
         let entries: Vec<_> = self
             .entries
             .iter()
-            .map(|x| self.qual.expand_items(x))
-            .flatten()
+            .flat_map(|x| self.qual.expand_items(x))
             .collect();
 
         let mut result = Array2::zeros((self.height, self.width));
@@ -1,9 +1,28 @@
+pub mod amg;
 pub mod arnoldi;
 pub mod bicgstab;
+pub mod bicgstabl;
+pub mod block;
+pub mod cg;
+pub mod cgls;
+pub mod cgne;
+pub mod cgs;
+pub mod chebyshev;
+pub mod craig;
 pub mod eigen;
+pub mod idrs;
 pub mod io;
+pub mod lsmr;
 pub mod lsqr;
 pub mod minres;
+pub mod monitor;
+pub mod operator;
+pub mod precond;
 pub mod qr;
 pub mod qr_solve;
+pub mod regularization;
+pub mod solver;
+pub mod stationary;
+pub mod stopping;
+pub mod tfqmr;
 pub mod utils;
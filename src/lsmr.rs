@@ -0,0 +1,300 @@
+#![allow(non_snake_case)]
+#![allow(clippy::many_single_char_names)]
+
+use crate::lsqr::{bidiag_step, eculid_norm};
+use crate::minres::minres::sym_ortho;
+use crate::monitor::{Monitor, MonitorAction};
+use crate::operator::{check_adjoint, LinearOperator};
+use crate::solver::{IterativeSolver, SolveReport};
+use crate::stopping::{IterInfo, LsqEstimates, StopReason, StoppingCriterion};
+use ndarray::ScalarOperand;
+use ndarray::{Array1, ArrayView1};
+use num_traits::Float;
+use std::time::Instant;
+
+/// State of LSMR (Fong and Saunders) for `min ‖A x - b‖^2 + λ^2 ‖x‖^2`.
+/// It runs on the same Golub–Kahan bidiagonalization as LSQR but is
+/// equivalent to MINRES on the normal equations, so `‖A^T r‖` decreases
+/// monotonically and stopping early is safe.
+#[derive(Clone)]
+pub struct LsmrState<T>
+where
+    T: Float + Copy + Default + ScalarOperand,
+{
+    /// the damping `λ`
+    pub damp: T,
+    pub x: Array1<T>,
+    pub alpha: T,
+    pub u: Array1<T>,
+    pub v: Array1<T>,
+    pub h: Array1<T>,
+    pub h_bar: Array1<T>,
+    pub alpha_bar: T,
+    pub rho: T,
+    pub rho_bar: T,
+    pub c_bar: T,
+    pub s_bar: T,
+    pub zeta: T,
+    pub zeta_bar: T,
+    // quantities of the ‖r‖ estimate
+    pub beta_dd: T,
+    pub beta_d: T,
+    pub rho_d_old: T,
+    pub tau_tilde_old: T,
+    pub theta_tilde: T,
+    pub d: T,
+    /// running ‖A‖_F^2 without the last `alpha^2`
+    pub anorm2: T,
+    pub max_rbar: T,
+    pub min_rbar: T,
+    pub iter: usize,
+    /// estimate of the norm of the damped residual `(b - A x, -λ x)`, which
+    /// is ‖b - A x‖ for `λ = 0`
+    pub rnorm: T,
+    /// estimate of ‖A^T r - λ^2 x‖
+    pub arnorm: T,
+    /// estimate of ‖A‖_F
+    pub anorm: T,
+    /// estimate of cond(A)
+    pub acond: T,
+}
+
+pub fn lsmr_iter<T>(A: &dyn LinearOperator<T>, s_last: &LsmrState<T>) -> LsmrState<T>
+where
+    T: Float + Copy + Default + ScalarOperand + std::fmt::Debug,
+{
+    let mut s = s_last.clone();
+    let (beta, u, alpha, v) = bidiag_step(A, &s_last.u, &s_last.v, s_last.alpha);
+    s.iter += 1;
+
+    // rotation eliminating the damping
+    let (c_hat, s_hat, alpha_hat) = sym_ortho(s.alpha_bar, s.damp);
+
+    // rotation P_k
+    let rho_old = s.rho;
+    let (c, sn, rho) = sym_ortho(alpha_hat, beta);
+    let theta_new = sn * alpha;
+    s.alpha_bar = c * alpha;
+
+    // rotation P~_k
+    let rho_bar_old = s.rho_bar;
+    let zeta_old = s.zeta;
+    let theta_bar = s.s_bar * rho;
+    let rho_temp = s.c_bar * rho;
+    let (c_bar, s_bar, rho_bar) = sym_ortho(s.c_bar * rho, theta_new);
+    s.zeta = c_bar * s.zeta_bar;
+    s.zeta_bar = -s_bar * s.zeta_bar;
+
+    s.h_bar = &s.h - &(&s.h_bar * (theta_bar * rho / (rho_old * rho_bar_old)));
+    s.x = &s.x + &(&s.h_bar * (s.zeta / (rho * rho_bar)));
+    s.h = &v - &(&s.h * (theta_new / rho));
+
+    // ‖r‖
+    let beta_acute = c_hat * s.beta_dd;
+    let beta_check = -s_hat * s.beta_dd;
+    let beta_hat = c * beta_acute;
+    s.beta_dd = -sn * beta_acute;
+    let theta_tilde_old = s.theta_tilde;
+    let (c_tilde_old, s_tilde_old, rho_tilde_old) = sym_ortho(s.rho_d_old, theta_bar);
+    s.theta_tilde = s_tilde_old * rho_bar;
+    s.rho_d_old = c_tilde_old * rho_bar;
+    s.beta_d = -s_tilde_old * s.beta_d + c_tilde_old * beta_hat;
+    s.tau_tilde_old = (zeta_old - theta_tilde_old * s.tau_tilde_old) / rho_tilde_old;
+    let tau_d = (s.zeta - s.theta_tilde * s.tau_tilde_old) / s.rho_d_old;
+    s.d = s.d + beta_check * beta_check;
+    s.rnorm = (s.d + (s.beta_d - tau_d).powi(2) + s.beta_dd * s.beta_dd).sqrt();
+
+    // ‖A‖ and cond(A)
+    s.anorm2 = s.anorm2 + beta * beta;
+    s.anorm = s.anorm2.sqrt();
+    s.anorm2 = s.anorm2 + alpha * alpha;
+    s.max_rbar = s.max_rbar.max(rho_bar_old);
+    if s.iter > 1 {
+        s.min_rbar = s.min_rbar.min(rho_bar_old);
+    }
+    s.acond = s.max_rbar.max(rho_temp) / s.min_rbar.min(rho_temp);
+    s.arnorm = s.zeta_bar.abs();
+
+    s.alpha = alpha;
+    s.u = u;
+    s.v = v;
+    s.rho = rho;
+    s.rho_bar = rho_bar;
+    s.c_bar = c_bar;
+    s.s_bar = s_bar;
+    s
+}
+
+pub fn lsmr_init<T>(A: &dyn LinearOperator<T>, b: &Array1<T>, damp: T) -> LsmrState<T>
+where
+    T: Float + Copy + Default + ScalarOperand,
+{
+    assert_eq!(A.nrows(), b.len());
+    check_adjoint(A, "LSMR");
+    let beta = eculid_norm(b);
+    let u = if beta > T::zero() {
+        b / beta
+    } else {
+        b.clone()
+    };
+    let mut v = A.apply_adjoint(u.view());
+    let alpha = eculid_norm(&v);
+    if alpha > T::zero() {
+        v = v / alpha;
+    }
+    LsmrState {
+        damp,
+        x: Array1::zeros(A.ncols()),
+        alpha,
+        u,
+        h: v.clone(),
+        h_bar: Array1::zeros(v.len()),
+        v,
+        alpha_bar: alpha,
+        rho: T::one(),
+        rho_bar: T::one(),
+        c_bar: T::one(),
+        s_bar: T::zero(),
+        zeta: T::zero(),
+        zeta_bar: alpha * beta,
+        beta_dd: beta,
+        beta_d: T::zero(),
+        rho_d_old: T::one(),
+        tau_tilde_old: T::zero(),
+        theta_tilde: T::zero(),
+        d: T::zero(),
+        anorm2: alpha * alpha,
+        max_rbar: T::zero(),
+        min_rbar: T::max_value(),
+        iter: 0,
+        rnorm: beta,
+        arnorm: alpha * beta,
+        anorm: T::zero(),
+        acond: T::zero(),
+    }
+}
+
+impl<T> LsmrState<T>
+where
+    T: Float + Copy + Default + ScalarOperand + std::fmt::Debug,
+{
+    pub fn new(A: &dyn LinearOperator<T>, b: &Array1<T>, damp: T) -> LsmrState<T> {
+        lsmr_init(A, b, damp)
+    }
+
+    pub fn next(&mut self, A: &dyn LinearOperator<T>) -> Option<()> {
+        let ns = lsmr_iter(A, self);
+        if ns.valid() {
+            *self = ns;
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    pub fn calc_resid(&self, A: &dyn LinearOperator<T>, b: &Array1<T>) -> Array1<T> {
+        b - &A.apply(self.x.view())
+    }
+
+    pub fn valid(&self) -> bool {
+        self.x.iter().all(|x| x.is_finite())
+            && self.h.iter().all(|x| x.is_finite())
+            && self.h_bar.iter().all(|x| x.is_finite())
+            && self.rho.is_finite()
+            && self.rho_bar.is_finite()
+            && self.zeta_bar.is_finite()
+    }
+}
+
+/// LSMR as an `IterativeSolver`; `x0` is handled by solving for the correction
+/// `A dx = b - A x0`, so with damping the penalty is on `‖dx‖`.
+///
+/// The residual tests use the running ‖r‖ estimate, the Paige–Saunders tests
+/// the ‖A‖, ‖A^T r‖ and cond(A) estimates of `LsmrState`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Lsmr<T> {
+    pub damp: T,
+}
+
+impl<T> Lsmr<T>
+where
+    T: Float,
+{
+    pub fn new() -> Lsmr<T> {
+        Lsmr { damp: T::zero() }
+    }
+
+    /// solve the damped problem `min ‖A x - b‖^2 + λ^2 ‖x‖^2`
+    pub fn with_damping(mut self, damp: T) -> Lsmr<T> {
+        self.damp = damp;
+        self
+    }
+}
+
+impl<T> IterativeSolver<T, T> for Lsmr<T>
+where
+    T: Float + Copy + Default + ScalarOperand + std::fmt::Debug,
+{
+    type State = LsmrState<T>;
+
+    fn solve_with_monitor(
+        &self,
+        A: &dyn LinearOperator<T>,
+        b: ArrayView1<T>,
+        x0: ArrayView1<T>,
+        criterion: &StoppingCriterion<T>,
+        monitor: &mut dyn Monitor<LsmrState<T>, T>,
+    ) -> SolveReport<T, T> {
+        let start = Instant::now();
+        let b = b.to_owned();
+        let b_norm = eculid_norm(&b);
+        let r0 = &b - &A.apply(x0);
+        let r0_norm = eculid_norm(&r0);
+        let mut history = vec![r0_norm];
+        let mut iterations = 0;
+        let mut state = None;
+        let reason = loop {
+            let mut info = IterInfo::new(
+                iterations,
+                history[iterations],
+                r0_norm,
+                b_norm,
+                start.elapsed(),
+            );
+            info.lsq = state.as_ref().map(|s: &LsmrState<T>| LsqEstimates {
+                arnorm: s.arnorm,
+                anorm: s.anorm,
+                acond: s.acond,
+                xnorm: eculid_norm(&s.x),
+            });
+            if let Some(reason) = criterion.check(&info) {
+                break reason;
+            }
+            let s = state.get_or_insert_with(|| LsmrState::new(A, &r0, self.damp));
+            if s.next(A).is_none() {
+                break StopReason::Breakdown;
+            }
+            iterations += 1;
+            history.push(s.rnorm);
+            if monitor.observe(iterations, s.rnorm, s) == MonitorAction::Abort {
+                break StopReason::Aborted;
+            }
+        };
+        let x = match state {
+            Some(s) => &x0 + &s.x,
+            None => x0.to_owned(),
+        };
+        let resid_norm = eculid_norm(&(&b - &A.apply(x.view())));
+        let reason = match reason {
+            StopReason::Breakdown => criterion.breakdown_reason(resid_norm, r0_norm, b_norm),
+            _ => reason,
+        };
+        SolveReport {
+            x,
+            iterations,
+            resid_norm,
+            reason,
+            history,
+        }
+    }
+}
@@ -1,9 +1,15 @@
 #![allow(non_snake_case)]
 #![allow(clippy::many_single_char_names)]
 
+use crate::minres::minres::sym_ortho;
+use crate::monitor::{Monitor, MonitorAction};
+use crate::operator::{check_adjoint, LinearOperator};
+use crate::solver::{IterativeSolver, SolveReport};
+use crate::stopping::{IterInfo, LsqEstimates, StopReason, StoppingCriterion};
 use ndarray::ScalarOperand;
 use ndarray::{Array1, ArrayView1};
 use num_traits::Float;
+use std::time::Instant;
 
 pub fn eculid_norm<T>(x: &Array1<T>) -> T
 where
@@ -12,11 +18,42 @@ where
     (x.dot(x)).sqrt()
 }
 
+/// One step of the Golub–Kahan bidiagonalization of `A`: from `u_k`, `v_k`
+/// and `alpha_k` it computes `beta_{k+1} u_{k+1} = A v_k - alpha_k u_k` and
+/// `alpha_{k+1} v_{k+1} = A^T u_{k+1} - beta_{k+1} v_k`. A vector whose norm
+/// vanishes is left unnormalized (zero).
+pub fn bidiag_step<T>(
+    A: &dyn LinearOperator<T>,
+    u: &Array1<T>,
+    v: &Array1<T>,
+    alpha: T,
+) -> (T, Array1<T>, T, Array1<T>)
+where
+    T: Float + Copy + Default + ScalarOperand,
+{
+    let mut u = A.apply(v.view()) - u * alpha;
+    let beta = eculid_norm(&u);
+    if beta > T::zero() {
+        u = u / beta;
+    }
+    let mut v = A.apply_adjoint(u.view()) - v * beta;
+    let alpha = eculid_norm(&v);
+    if alpha > T::zero() {
+        v = v / alpha;
+    }
+    (beta, u, alpha, v)
+}
+
+/// State of LSQR (Paige and Saunders) for `min ‖A x - b‖^2 + λ^2 ‖x‖^2`.
+/// Besides the iterate it carries the running estimates of the original
+/// paper, which are all the Paige–Saunders stopping tests need.
 #[derive(Clone)]
 pub struct LsqrState<T>
 where
     T: Float + Copy + Default + ScalarOperand,
 {
+    /// the damping `λ`
+    pub damp: T,
     pub x: Array1<T>,
     pub alpha: T,
     pub u: Array1<T>,
@@ -24,34 +61,65 @@ where
     pub w: Array1<T>,
     pub phi_bar: T,
     pub rho_bar: T,
+    // quantities of the ‖x‖ and cond(A) estimates
+    pub res2: T,
+    pub ddnorm: T,
+    pub xxnorm: T,
+    pub z: T,
+    pub cs2: T,
+    pub sn2: T,
+    /// running estimate of ‖(A; λ I)‖_F from the bidiagonal entries
+    pub anorm: T,
+    /// estimate of ‖A^T r - λ^2 x‖
+    pub arnorm: T,
+    /// estimate of the norm of the damped residual `(b - A x, -λ x)`, which
+    /// is ‖b - A x‖ for `λ = 0`
+    pub rnorm: T,
+    /// estimate of cond((A; λ I))
+    pub acond: T,
+    /// estimate of ‖x‖
+    pub xnorm: T,
 }
 
-pub fn lsqr_iter<T>(
-    fl: &dyn Fn(ArrayView1<T>) -> Array1<T>,
-    fr: &dyn Fn(ArrayView1<T>) -> Array1<T>,
-    s_last: &LsqrState<T>,
-) -> LsqrState<T>
+pub fn lsqr_iter<T>(A: &dyn LinearOperator<T>, s_last: &LsqrState<T>) -> LsqrState<T>
 where
     T: Float + Copy + Default + ScalarOperand + std::fmt::Debug,
 {
-    let rhs_beta = fl(s_last.v.view()) - (&s_last.u) * (s_last.alpha);
-    let beta = eculid_norm(&rhs_beta);
-    let u = rhs_beta / beta;
-    //let rhs_alpha = sp_mul_a1(&A.transpose_view(), &u) - (&s_last.v) * beta;
-    let rhs_alpha = fr(u.view()) - (&s_last.v) * beta;
-    let alpha = eculid_norm(&rhs_alpha);
-    let v = &rhs_alpha / alpha;
-    let rho = (s_last.rho_bar.powi(2) + beta.powi(2)).sqrt();
-    let c = s_last.rho_bar / rho;
-    let s = beta / rho;
+    let damp = s_last.damp;
+    let (beta, u, alpha, v) = bidiag_step(A, &s_last.u, &s_last.v, s_last.alpha);
+    let anorm = (s_last.anorm.powi(2) + s_last.alpha.powi(2) + beta.powi(2) + damp.powi(2)).sqrt();
+
+    // eliminate the damping, then the subdiagonal `beta`
+    let (c1, s1, rho_bar1) = sym_ortho(s_last.rho_bar, damp);
+    let psi = s1 * s_last.phi_bar;
+    let phi_bar1 = c1 * s_last.phi_bar;
+    let (c, s, rho) = sym_ortho(rho_bar1, beta);
     let theta = s * alpha;
     let rho_bar = -c * alpha;
-    let phi = (s_last.phi_bar) * c;
-    let phi_bar = (s_last.phi_bar) * s;
-    let x = ((&s_last.w) * (phi / rho)) + (&s_last.x);
+    let phi = c * phi_bar1;
+    let phi_bar = s * phi_bar1;
+    let tau = s * phi;
 
+    let dk = &s_last.w / rho;
+    let ddnorm = s_last.ddnorm + dk.dot(&dk);
+    let x = ((&s_last.w) * (phi / rho)) + (&s_last.x);
     let w = (&v) - &((&s_last.w) * (theta / rho));
+
+    // ‖x‖ from the rotations of the lower bidiagonal system
+    let delta = s_last.sn2 * rho;
+    let gamma_bar = -s_last.cs2 * rho;
+    let rhs = phi - delta * s_last.z;
+    let z_bar = rhs / gamma_bar;
+    let xnorm = (s_last.xxnorm + z_bar.powi(2)).sqrt();
+    let gamma = gamma_bar.hypot(theta);
+    let cs2 = gamma_bar / gamma;
+    let sn2 = theta / gamma;
+    let z = rhs / gamma;
+    let xxnorm = s_last.xxnorm + z.powi(2);
+
+    let res2 = s_last.res2 + psi.powi(2);
     LsqrState {
+        damp,
         x,
         alpha,
         u,
@@ -59,29 +127,42 @@ where
         w,
         phi_bar,
         rho_bar,
+        res2,
+        ddnorm,
+        xxnorm,
+        z,
+        cs2,
+        sn2,
+        anorm,
+        arnorm: alpha * tau.abs(),
+        rnorm: (phi_bar.powi(2) + res2).sqrt(),
+        acond: anorm * ddnorm.sqrt(),
+        xnorm,
     }
 }
 
 #[allow(non_snake_case)]
-pub fn lsqr_init<T>(
-    fr: &dyn Fn(ArrayView1<T>) -> Array1<T>,
-    ncols: usize,
-    b: &Array1<T>,
-) -> LsqrState<T>
+pub fn lsqr_init<T>(A: &dyn LinearOperator<T>, b: &Array1<T>, damp: T) -> LsqrState<T>
 where
     T: Float + Copy + Default + ScalarOperand,
 {
-    //assert!(ncols<b.len());
-    let x0 = Array1::from(vec![<T as Default>::default(); ncols]);
-    let beta = eculid_norm(&b);
-    let u = (b) / beta;
-    let ATu = fr(u.view());
+    assert_eq!(A.nrows(), b.len());
+    check_adjoint(A, "LSQR");
+    let x0 = Array1::from(vec![<T as Default>::default(); A.ncols()]);
+    let beta = eculid_norm(b);
+    let u = if beta > T::zero() {
+        b / beta
+    } else {
+        b.clone()
+    };
+    let ATu = A.apply_adjoint(u.view());
     let alpha = eculid_norm(&ATu);
-    let v = ATu / alpha;
+    let v = if alpha > T::zero() { ATu / alpha } else { ATu };
     let w = v.clone();
     let phi_bar = beta;
     let rho_bar = alpha;
     LsqrState {
+        damp,
         x: x0,
         alpha,
         u,
@@ -89,6 +170,17 @@ where
         w,
         phi_bar,
         rho_bar,
+        res2: T::zero(),
+        ddnorm: T::zero(),
+        xxnorm: T::zero(),
+        z: T::zero(),
+        cs2: -T::one(),
+        sn2: T::zero(),
+        anorm: T::zero(),
+        arnorm: alpha * beta,
+        rnorm: beta,
+        acond: T::zero(),
+        xnorm: T::zero(),
     }
 }
 
@@ -96,21 +188,12 @@ impl<T> LsqrState<T>
 where
     T: Float + Copy + Default + ScalarOperand + std::fmt::Debug,
 {
-    pub fn new(
-        fr: &dyn Fn(ArrayView1<T>) -> Array1<T>,
-        ncols: usize,
-        b: &Array1<T>,
-    ) -> LsqrState<T> {
-        lsqr_init(fr, ncols, b)
+    pub fn new(A: &dyn LinearOperator<T>, b: &Array1<T>, damp: T) -> LsqrState<T> {
+        lsqr_init(A, b, damp)
     }
 
-    pub fn next(
-        &mut self,
-        fl: &dyn Fn(ArrayView1<T>) -> Array1<T>,
-        fr: &dyn Fn(ArrayView1<T>) -> Array1<T>,
-    ) -> Option<()> {
-        let ns = lsqr_iter(fl, fr, self);
-        println!("{}", ns.valid());
+    pub fn next(&mut self, A: &dyn LinearOperator<T>) -> Option<()> {
+        let ns = lsqr_iter(A, self);
         if ns.valid() {
             *self = ns;
             Some(())
@@ -119,8 +202,8 @@ where
         }
     }
 
-    pub fn calc_resid(&self, fl: &dyn Fn(ArrayView1<T>) -> Array1<T>, b: &Array1<T>) -> Array1<T> {
-        b - &fl(self.x.view())
+    pub fn calc_resid(&self, A: &dyn LinearOperator<T>, b: &Array1<T>) -> Array1<T> {
+        b - &A.apply(self.x.view())
     }
 
     pub fn valid(&self) -> bool {
@@ -133,3 +216,98 @@ where
             && self.rho_bar.is_finite()
     }
 }
+
+/// LSQR as an `IterativeSolver`; `x0` is handled by solving for the correction
+/// `A dx = b - A x0`, so with damping the penalty is on `‖dx‖`.
+///
+/// The residual tests use the running ‖r‖ estimate, the Paige–Saunders tests
+/// the ‖A‖, ‖A^T r‖, cond(A) and ‖x‖ estimates of `LsqrState`; the
+/// `StopReason` of such a stop maps to the classic `istop` code by
+/// `StopReason::istop`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Lsqr<T> {
+    pub damp: T,
+}
+
+impl<T> Lsqr<T>
+where
+    T: Float,
+{
+    pub fn new() -> Lsqr<T> {
+        Lsqr { damp: T::zero() }
+    }
+
+    /// solve the damped problem `min ‖A x - b‖^2 + λ^2 ‖x‖^2`
+    pub fn with_damping(mut self, damp: T) -> Lsqr<T> {
+        self.damp = damp;
+        self
+    }
+}
+
+impl<T> IterativeSolver<T, T> for Lsqr<T>
+where
+    T: Float + Copy + Default + ScalarOperand + std::fmt::Debug,
+{
+    type State = LsqrState<T>;
+
+    fn solve_with_monitor(
+        &self,
+        A: &dyn LinearOperator<T>,
+        b: ArrayView1<T>,
+        x0: ArrayView1<T>,
+        criterion: &StoppingCriterion<T>,
+        monitor: &mut dyn Monitor<LsqrState<T>, T>,
+    ) -> SolveReport<T, T> {
+        let start = Instant::now();
+        let b = b.to_owned();
+        let b_norm = eculid_norm(&b);
+        let r0 = &b - &A.apply(x0);
+        let r0_norm = eculid_norm(&r0);
+        let mut history = vec![r0_norm];
+        let mut iterations = 0;
+        let mut state = None;
+        let reason = loop {
+            let mut info = IterInfo::new(
+                iterations,
+                history[iterations],
+                r0_norm,
+                b_norm,
+                start.elapsed(),
+            );
+            info.lsq = state.as_ref().map(|s: &LsqrState<T>| LsqEstimates {
+                arnorm: s.arnorm,
+                anorm: s.anorm,
+                acond: s.acond,
+                xnorm: s.xnorm,
+            });
+            if let Some(reason) = criterion.check(&info) {
+                break reason;
+            }
+            let s = state.get_or_insert_with(|| LsqrState::new(A, &r0, self.damp));
+            if s.next(A).is_none() {
+                break StopReason::Breakdown;
+            }
+            iterations += 1;
+            history.push(s.rnorm);
+            if monitor.observe(iterations, s.rnorm, s) == MonitorAction::Abort {
+                break StopReason::Aborted;
+            }
+        };
+        let x = match state {
+            Some(s) => &x0 + &s.x,
+            None => x0.to_owned(),
+        };
+        let resid_norm = eculid_norm(&(&b - &A.apply(x.view())));
+        let reason = match reason {
+            StopReason::Breakdown => criterion.breakdown_reason(resid_norm, r0_norm, b_norm),
+            _ => reason,
+        };
+        SolveReport {
+            x,
+            iterations,
+            resid_norm,
+            reason,
+            history,
+        }
+    }
+}
@@ -4,11 +4,21 @@
 #![allow(clippy::too_many_arguments)]
 use ndarray::{Array1, ArrayView1};
 use num_traits::Float;
+use std::time::Instant;
 
+use super::gmres::Preconditioning;
 use super::utils::{apply_plane_rotation, generate_plane_rotation, update2};
 use crate::arnoldi::ArnoldiSpace;
+use crate::monitor::{Monitor, MonitorAction, SilentMonitor};
+use crate::operator::{check_shape, LinearOperator};
+use crate::solver::{IterativeSolver, SolveReport};
+use crate::stopping::{IterInfo, StopReason, StoppingCriterion};
 use crate::utils::norm;
 use crate::utils::ComplexOrReal;
+
+/// State of adaptive GMRES(m) for `A x = b`. `tol` and `resid` are absolute,
+/// with the same meaning (and the same change from the squared, relative
+/// test of earlier versions) as in `GmresState`.
 pub struct AGmresState<T, U>
 where
     T: ComplexOrReal<U> + std::fmt::Debug,
@@ -19,6 +29,7 @@ where
     pub m_min: usize,
     pub m_step: usize,
     pub cf: U,
+    /// absolute threshold on `resid`
     pub tol: U,
     pub x: Array1<T>,
     pub b: Array1<T>,
@@ -28,25 +39,43 @@ where
     pub sn: Array1<T>,
     //pub av: Array1<T>,
     pub beta: U,
+    /// absolute residual norm at the end of the last step
     pub resid: U,
     pub r: Array1<T>,
     //pub v: Vec<Array1<T>>,
     pub converged: bool,
+    /// the last cycle ended in a breakdown of the Arnoldi process; with the
+    /// default `arn.breakdown_tol` of zero, `x` then solves the system
+    pub breakdown: bool,
     pub arn: ArnoldiSpace<T, U>,
 }
 
-pub fn agmres1<T, U>(
+pub fn agmres1<T, U>(ags: &mut AGmresState<T, U>, A: &dyn LinearOperator<T>, M: Preconditioning<T>)
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    agmres1_with_monitor(ags, A, M, 0, &mut SilentMonitor);
+}
+
+/// `agmres1`, calling `monitor` after every Arnoldi step with the Givens
+/// residual estimate; the steps are numbered from `iter + 1`. An
+/// `Abort` ends the cycle early, with `x` updated as usual, and is returned.
+pub fn agmres1_with_monitor<T, U>(
     ags: &mut AGmresState<T, U>,
-    A: &dyn Fn(ArrayView1<T>) -> Array1<T>,
-    M: Option<&dyn Fn(ArrayView1<T>) -> Array1<T>>,
-) where
+    A: &dyn LinearOperator<T>,
+    M: Preconditioning<T>,
+    iter: usize,
+    monitor: &mut dyn Monitor<AGmresState<T, U>, U>,
+) -> MonitorAction
+where
     T: ComplexOrReal<U> + std::fmt::Debug,
     U: Float + std::fmt::Debug,
 {
     //println!("{:?}", ags.beta);
     if ags.beta == U::zero() {
         ags.converged = true;
-        return;
+        return MonitorAction::Continue;
     }
     //ags.v[0] = &ags.r / ags.beta;
     ags.arn.reset(ags.r.view());
@@ -54,18 +83,14 @@ pub fn agmres1<T, U>(
     s[0] = <T as From<U>>::from(ags.beta);
     let r1 = ags.beta;
 
+    let op = M.operator(A);
     let mut i = 0;
+    let mut action = MonitorAction::Continue;
+    ags.breakdown = false;
     while i < ags.m {
-        ags.arn
-            .iter(&|x| {
-                let av = A(x);
-                if let Some(ref M) = M {
-                    M(av.view())
-                } else {
-                    av
-                }
-            })
-            .unwrap();
+        // a breakdown leaves the last column of H with a zero below the
+        // diagonal, and the Givens rotations below then give a zero residual
+        ags.breakdown = ags.arn.iter(&op).is_err();
 
         for k in 0..i {
             let (dx, dy) =
@@ -98,25 +123,36 @@ pub fn agmres1<T, U>(
         //std::process::exit(0);
 
         ags.resid = s[i + 1].abs();
-        if ags.resid.powi(2) < ags.tol {
+        i += 1;
+        if monitor.observe(iter + i, ags.resid, ags) == MonitorAction::Abort {
+            action = MonitorAction::Abort;
+            break;
+        }
+        if ags.resid < ags.tol || ags.breakdown {
             //println!("resid={:?}, {:?}", resid, tol);
-            update2(&mut ags.x, i, &ags.arn.H, &s, &ags.arn.Q[..]);
-            //*tol = ags.resid.powi(2);
-            ags.converged = true;
-            return;
+            break;
         }
-        i += 1;
     }
-    update2(&mut ags.x, i - 1, &ags.arn.H, &s, &ags.arn.Q[..]);
+    let mut y = Array1::<T>::zeros(ags.x.len());
+    update2(&mut y, i - 1, &ags.arn.H, &s, &ags.arn.Q[..]);
+    ags.x = &ags.x + &M.apply_right(y);
     //ags.r = ;
-    let w = &ags.b - &A(ags.x.view());
-    ags.r = if let Some(M) = M { M(w.view()) } else { w };
+    let w = &ags.b - &A.apply(ags.x.view());
+    if M.true_residual() {
+        ags.resid = norm(w.view());
+    }
+    ags.r = M.apply_left(w);
 
     //ags.r = M(w.view());
     ags.beta = norm(ags.r.view());
-    if ags.resid.powi(2) < ags.tol {
+    // the Givens estimate is zero after any breakdown, also an inexact one
+    // with a nonzero `breakdown_tol`
+    if !M.true_residual() {
+        ags.resid = ags.beta;
+    }
+    if ags.resid < ags.tol {
         ags.converged = true;
-        return;
+        return action;
     }
 
     if ags.beta / r1 > ags.cf {
@@ -127,13 +163,14 @@ pub fn agmres1<T, U>(
         }
     }
     ags.converged = false;
+    action
 }
 
 pub fn agmres<T, U>(
-    A: &dyn Fn(ArrayView1<T>) -> Array1<T>,
+    A: &dyn LinearOperator<T>,
     x: ArrayView1<T>,
     b: ArrayView1<T>,
-    M: Option<&dyn Fn(ArrayView1<T>) -> Array1<T>>,
+    M: Preconditioning<T>,
     max_iter: usize,
     m_max: usize,
     m_min: usize,
@@ -159,7 +196,7 @@ where
     ags.init(A, x, b, M);
 
     //println!("w={:?}", w);
-    if ags.resid.powi(2) <= ags.tol {
+    if ags.resid <= ags.tol {
         ags.converged = true;
         return ags;
     }
@@ -212,42 +249,38 @@ where
             resid: U::zero(),
             r: Array1::<T>::zeros(problem_size),
             converged: false,
+            breakdown: false,
             arn: ArnoldiSpace::empty(),
         }
     }
 
     pub fn init(
         &mut self,
-        A: &dyn Fn(ArrayView1<T>) -> Array1<T>,
+        A: &dyn LinearOperator<T>,
         x: ArrayView1<T>,
         b: ArrayView1<T>,
-        M: Option<&dyn Fn(ArrayView1<T>) -> Array1<T>>,
+        M: Preconditioning<T>,
     ) {
-        let w = M.map_or(b.to_owned(), |m| m(b.view()));
-        let normb = {
-            let nb = norm(w.view());
-            if nb == U::zero() {
-                U::one()
-            } else {
-                nb
-            }
-        };
+        check_shape(A, b.len(), x.len());
         self.b = b.to_owned();
         self.x = x.to_owned();
-        self.r = A(x.view());
+        self.r = A.apply(x.view());
         let w = &b - &self.r;
-        self.r = if let Some(M) = M { M(w.view()) } else { w };
+        self.resid = norm(w.view());
+        self.r = M.apply_left(w);
 
         self.beta = norm(self.r.view());
-        self.resid = self.beta / normb;
+        if !M.true_residual() {
+            self.resid = self.beta;
+        }
         self.converged = false;
     }
 
     pub fn new(
-        A: &dyn Fn(ArrayView1<T>) -> Array1<T>,
+        A: &dyn LinearOperator<T>,
         x: ArrayView1<T>,
         b: ArrayView1<T>,
-        M: Option<&dyn Fn(ArrayView1<T>) -> Array1<T>>,
+        M: Preconditioning<T>,
         m_max: usize,
         m_min: usize,
         m_step: usize,
@@ -259,18 +292,150 @@ where
         result
     }
 
-    pub fn next(
-        &mut self,
-        A: &dyn Fn(ArrayView1<T>) -> Array1<T>,
-        M: Option<&dyn Fn(ArrayView1<T>) -> Array1<T>>,
-    ) {
+    pub fn next(&mut self, A: &dyn LinearOperator<T>, M: Preconditioning<T>) {
         if self.converged {
             return;
         }
         agmres1(self, A, M);
     }
 
-    pub fn calc_resid(&self, lhs: &dyn Fn(ArrayView1<T>) -> Array1<T>, b: &Array1<T>) -> Array1<T> {
-        b - &lhs(self.x.view())
+    /// `next` with a per-step `monitor`, see `agmres1_with_monitor`
+    pub fn next_with_monitor(
+        &mut self,
+        A: &dyn LinearOperator<T>,
+        M: Preconditioning<T>,
+        iter: usize,
+        monitor: &mut dyn Monitor<Self, U>,
+    ) -> MonitorAction {
+        if self.converged {
+            return MonitorAction::Continue;
+        }
+        agmres1_with_monitor(self, A, M, iter, monitor)
+    }
+
+    pub fn calc_resid(&self, lhs: &dyn LinearOperator<T>, b: &Array1<T>) -> Array1<T> {
+        b - &lhs.apply(self.x.view())
+    }
+}
+
+/// Adaptive-restart GMRES as an `IterativeSolver`.
+///
+/// The restart length starts at `m_max` and is reduced by `m_step` (down to
+/// `m_min`) whenever a cycle reduces the residual by less than the factor `cf`.
+/// Residual tests see the same residual as in `Gmres`, and breakdowns are
+/// handled in the same way.
+pub struct AGmres<'a, T, U> {
+    pub m_max: usize,
+    pub m_min: usize,
+    pub m_step: usize,
+    pub cf: U,
+    pub M: Preconditioning<'a, T>,
+    /// see `ArnoldiSpace::breakdown_tol`
+    pub breakdown_tol: U,
+}
+
+impl<'a, T, U> AGmres<'a, T, U>
+where
+    U: Float,
+{
+    pub fn new(m_max: usize, m_min: usize, m_step: usize, cf: U) -> AGmres<'a, T, U> {
+        AGmres {
+            m_max,
+            m_min,
+            m_step,
+            cf,
+            M: Preconditioning::None,
+            breakdown_tol: U::zero(),
+        }
+    }
+
+    /// left preconditioning by `M`
+    pub fn with_preconditioner(mut self, M: &'a dyn LinearOperator<T>) -> AGmres<'a, T, U> {
+        self.M = Preconditioning::Left(M);
+        self
+    }
+
+    pub fn with_preconditioning(mut self, M: Preconditioning<'a, T>) -> AGmres<'a, T, U> {
+        self.M = M;
+        self
+    }
+
+    pub fn with_breakdown_tol(mut self, tol: U) -> AGmres<'a, T, U> {
+        self.breakdown_tol = tol;
+        self
+    }
+}
+
+impl<'a, T, U> IterativeSolver<T, U> for AGmres<'a, T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    type State = AGmresState<T, U>;
+
+    fn solve_with_monitor(
+        &self,
+        A: &dyn LinearOperator<T>,
+        b: ArrayView1<T>,
+        x0: ArrayView1<T>,
+        criterion: &StoppingCriterion<U>,
+        monitor: &mut dyn Monitor<AGmresState<T, U>, U>,
+    ) -> SolveReport<T, U> {
+        let start = Instant::now();
+        let b_norm = if self.M.true_residual() {
+            norm(b)
+        } else {
+            norm(self.M.apply_left(b.to_owned()).view())
+        };
+        let mut ags = AGmresState::new(
+            A,
+            x0,
+            b,
+            self.M,
+            self.m_max,
+            self.m_min,
+            self.m_step,
+            self.cf,
+            U::zero(),
+        );
+        ags.arn.breakdown_tol = self.breakdown_tol;
+        let r0_norm = ags.resid;
+        ags.tol = criterion
+            .residual_threshold(r0_norm, b_norm)
+            .map_or(U::zero(), |(th, _)| th);
+        let mut history = vec![r0_norm];
+        let mut iterations = 0;
+        let reason = loop {
+            let info = IterInfo::new(iterations, ags.resid, r0_norm, b_norm, start.elapsed());
+            if let Some(reason) = criterion.check(&info) {
+                break reason;
+            }
+            if ags.beta == U::zero() {
+                // x is exact, a cycle would have nothing to iterate on
+                break criterion.exact_reason(r0_norm, b_norm);
+            }
+            let action = ags.next_with_monitor(A, self.M, iterations, monitor);
+            iterations += ags.arn.H.len();
+            history.push(ags.resid);
+            if action == MonitorAction::Abort {
+                break StopReason::Aborted;
+            }
+            if ags.breakdown {
+                break StopReason::Breakdown;
+            }
+        };
+        // the residual tests were applied to `ags.resid`
+        let reason = match reason {
+            StopReason::Breakdown => criterion.breakdown_reason(ags.resid, r0_norm, b_norm),
+            _ => reason,
+        };
+        let resid_norm = norm(ags.calc_resid(A, &ags.b).view());
+        SolveReport {
+            x: ags.x,
+            iterations,
+            resid_norm,
+            reason,
+            history,
+        }
     }
 }
@@ -0,0 +1,372 @@
+#![allow(non_snake_case)]
+#![allow(clippy::many_single_char_names)]
+use ndarray::{Array1, ArrayView1};
+use num_traits::Float;
+use std::time::Instant;
+
+use super::utils::{apply_plane_rotation, generate_plane_rotation, update2};
+use crate::arnoldi::ArnoldiSpace;
+use crate::monitor::{Monitor, MonitorAction, SilentMonitor};
+use crate::operator::{check_shape, IdentityOperator, LinearOperator};
+use crate::solver::IterativeSolver;
+use crate::solver::SolveReport;
+use crate::stopping::{IterInfo, StopReason, StoppingCriterion};
+use crate::utils::norm;
+use crate::utils::ComplexOrReal;
+
+/// Right preconditioner of FGMRES, `z_k ≈ A^-1 v_k`, which may differ from
+/// one Arnoldi step `k` to the next.
+pub trait FlexiblePreconditioner<T> {
+    fn precondition(&self, k: usize, v: ArrayView1<T>) -> Array1<T>;
+}
+
+/// Any fixed operator is a flexible preconditioner that ignores `k`.
+impl<T, O> FlexiblePreconditioner<T> for O
+where
+    O: LinearOperator<T> + ?Sized,
+{
+    fn precondition(&self, _k: usize, v: ArrayView1<T>) -> Array1<T> {
+        self.apply(v)
+    }
+}
+
+/// A few iterations of an inner `IterativeSolver` on `A z = v`, started from
+/// zero. The result depends nonlinearly on `v`, so this is only a valid
+/// preconditioner for flexible methods such as FGMRES.
+pub struct SolverPreconditioner<'a, S, T, U> {
+    pub solver: S,
+    pub A: &'a dyn LinearOperator<T>,
+    pub criterion: StoppingCriterion<U>,
+}
+
+impl<'a, S, T, U> SolverPreconditioner<'a, S, T, U> {
+    pub fn new(
+        solver: S,
+        A: &'a dyn LinearOperator<T>,
+        criterion: StoppingCriterion<U>,
+    ) -> SolverPreconditioner<'a, S, T, U> {
+        SolverPreconditioner {
+            solver,
+            A,
+            criterion,
+        }
+    }
+}
+
+impl<'a, S, T, U> LinearOperator<T> for SolverPreconditioner<'a, S, T, U>
+where
+    S: IterativeSolver<T, U>,
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    fn shape(&self) -> (usize, usize) {
+        let (m, n) = self.A.shape();
+        (n, m)
+    }
+
+    fn apply(&self, v: ArrayView1<T>) -> Array1<T> {
+        let x0 = Array1::<T>::zeros(self.A.ncols());
+        self.solver.solve(self.A, v, x0.view(), &self.criterion).x
+    }
+
+    fn apply_adjoint(&self, _v: ArrayView1<T>) -> Array1<T> {
+        panic!("SolverPreconditioner has no adjoint")
+    }
+
+    fn has_adjoint(&self) -> bool {
+        false
+    }
+}
+
+pub struct FgmresState<T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    pub m: usize,
+    pub tol: U,
+    pub x: Array1<T>,
+    pub b: Array1<T>,
+    pub cs: Array1<T>,
+    pub sn: Array1<T>,
+    pub beta: U,
+    pub resid: U,
+    pub r: Array1<T>,
+    /// preconditioned directions z_k = M_k q_k of the current cycle
+    pub Z: Vec<Array1<T>>,
+    /// total number of Arnoldi steps, passed to the preconditioner
+    pub nsteps: usize,
+    pub converged: bool,
+    /// the last cycle ended in a breakdown of the Arnoldi process
+    pub breakdown: bool,
+    pub arn: ArnoldiSpace<T, U>,
+}
+
+pub fn fgmres1<T, U>(
+    ags: &mut FgmresState<T, U>,
+    A: &dyn LinearOperator<T>,
+    M: &dyn FlexiblePreconditioner<T>,
+) where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    fgmres1_with_monitor(ags, A, M, 0, &mut SilentMonitor);
+}
+
+/// `fgmres1`, calling `monitor` after every Arnoldi step with the Givens
+/// residual estimate; the steps are numbered from `iter + 1`. An
+/// `Abort` ends the cycle early, with `x` updated as usual, and is returned.
+pub fn fgmres1_with_monitor<T, U>(
+    ags: &mut FgmresState<T, U>,
+    A: &dyn LinearOperator<T>,
+    M: &dyn FlexiblePreconditioner<T>,
+    iter: usize,
+    monitor: &mut dyn Monitor<FgmresState<T, U>, U>,
+) -> MonitorAction
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    ags.Z.clear();
+    ags.breakdown = false;
+    if ags.beta == U::zero() {
+        ags.converged = true;
+        return MonitorAction::Continue;
+    }
+    ags.arn.reset(ags.r.view());
+    let mut s = Array1::<T>::zeros(ags.m + 1);
+    s[0] = <T as From<U>>::from(ags.beta);
+
+    // A Z spans at most the whole space
+    let m = ags.m.min(ags.x.len());
+    let mut i = 0;
+    let mut action = MonitorAction::Continue;
+    while i < m {
+        let z = M.precondition(ags.nsteps, ags.arn.Q[i].view());
+        ags.nsteps += 1;
+        ags.breakdown = ags.arn.extend(A.apply(z.view())).is_err();
+        ags.Z.push(z);
+
+        for k in 0..i {
+            let (dx, dy) =
+                apply_plane_rotation(ags.arn.H[i][k], ags.arn.H[i][k + 1], ags.cs[k], ags.sn[k]);
+            ags.arn.H[i][k] = dx;
+            ags.arn.H[i][k + 1] = dy;
+        }
+
+        let (cs1, sn1) = generate_plane_rotation(ags.arn.H[i][i], ags.arn.H[i][i + 1]);
+        ags.cs[i] = cs1;
+        ags.sn[i] = sn1;
+        {
+            let (dx, dy) =
+                apply_plane_rotation(ags.arn.H[i][i], ags.arn.H[i][i + 1], ags.cs[i], ags.sn[i]);
+            ags.arn.H[i][i] = dx;
+            ags.arn.H[i][i + 1] = dy;
+        }
+        {
+            let (dx, dy) = apply_plane_rotation(s[i], s[i + 1], ags.cs[i], ags.sn[i]);
+            s[i] = dx;
+            s[i + 1] = dy;
+        }
+
+        ags.resid = s[i + 1].abs();
+        i += 1;
+        if monitor.observe(iter + i, ags.resid, ags) == MonitorAction::Abort {
+            action = MonitorAction::Abort;
+            break;
+        }
+        // after a breakdown of the Arnoldi process there is no q_{i+1} to
+        // continue with; unlike GMRES this need not mean x is exact
+        if ags.resid < ags.tol || ags.breakdown {
+            break;
+        }
+    }
+    // with a flexible preconditioner `A z_i` may lie in the span of the
+    // earlier directions; the last diagonal entry is then zero and `z_i`
+    // adds nothing to the least squares solution
+    if ags.breakdown && ags.arn.H[i - 1][i - 1].abs() == U::zero() {
+        i -= 1;
+    }
+    if i > 0 {
+        update2(&mut ags.x, i - 1, &ags.arn.H, &s, &ags.Z[..]);
+    }
+    ags.r = &ags.b - &A.apply(ags.x.view());
+    ags.beta = norm(ags.r.view());
+    ags.resid = ags.beta;
+    ags.converged = ags.resid < ags.tol;
+    action
+}
+
+impl<T, U> FgmresState<T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    pub fn create(problem_size: usize, m: usize, tol: U) -> FgmresState<T, U> {
+        FgmresState {
+            m,
+            tol,
+            x: Array1::<T>::zeros(problem_size),
+            b: Array1::<T>::zeros(problem_size),
+            cs: Array1::<T>::zeros(m + 1),
+            sn: Array1::<T>::zeros(m + 1),
+            beta: U::zero(),
+            resid: U::zero(),
+            r: Array1::<T>::zeros(problem_size),
+            Z: Vec::with_capacity(m),
+            nsteps: 0,
+            converged: false,
+            breakdown: false,
+            arn: ArnoldiSpace::empty(),
+        }
+    }
+
+    pub fn init(&mut self, A: &dyn LinearOperator<T>, x: ArrayView1<T>, b: ArrayView1<T>) {
+        check_shape(A, b.len(), x.len());
+        self.b = b.to_owned();
+        self.x = x.to_owned();
+        self.r = &b - &A.apply(x.view());
+        self.beta = norm(self.r.view());
+        self.resid = self.beta;
+        self.converged = self.resid < self.tol;
+        self.nsteps = 0;
+        self.arn.reset(self.r.view());
+    }
+
+    pub fn new(
+        A: &dyn LinearOperator<T>,
+        x: ArrayView1<T>,
+        b: ArrayView1<T>,
+        m: usize,
+        tol: U,
+    ) -> Self {
+        let mut result = Self::create(b.len(), m, tol);
+        result.init(A, x, b);
+        result
+    }
+
+    pub fn next(&mut self, A: &dyn LinearOperator<T>, M: &dyn FlexiblePreconditioner<T>) {
+        if self.converged {
+            return;
+        }
+        fgmres1(self, A, M);
+    }
+
+    /// `next` with a per-step `monitor`, see `fgmres1_with_monitor`
+    pub fn next_with_monitor(
+        &mut self,
+        A: &dyn LinearOperator<T>,
+        M: &dyn FlexiblePreconditioner<T>,
+        iter: usize,
+        monitor: &mut dyn Monitor<Self, U>,
+    ) -> MonitorAction {
+        if self.converged {
+            return MonitorAction::Continue;
+        }
+        fgmres1_with_monitor(self, A, M, iter, monitor)
+    }
+
+    pub fn calc_resid(&self, lhs: &dyn LinearOperator<T>, b: &Array1<T>) -> Array1<T> {
+        b - &lhs.apply(self.x.view())
+    }
+}
+
+/// Restarted flexible GMRES(m) as an `IterativeSolver`.
+///
+/// The preconditioner acts on the right, so the residual tests use the true
+/// residual `b - A x`, which FGMRES recomputes at the end of every cycle.
+/// A breakdown of the Arnoldi process ends the solve, reported by the
+/// residual test the solution passes, or as `Breakdown` if it passes none.
+pub struct Fgmres<'a, T, U> {
+    pub m: usize,
+    pub M: Option<&'a dyn FlexiblePreconditioner<T>>,
+    /// see `ArnoldiSpace::breakdown_tol`
+    pub breakdown_tol: U,
+}
+
+impl<'a, T, U> Fgmres<'a, T, U>
+where
+    U: Float,
+{
+    pub fn new(m: usize) -> Fgmres<'a, T, U> {
+        Fgmres {
+            m,
+            M: None,
+            breakdown_tol: U::zero(),
+        }
+    }
+
+    pub fn with_preconditioner(mut self, M: &'a dyn FlexiblePreconditioner<T>) -> Self {
+        self.M = Some(M);
+        self
+    }
+
+    pub fn with_breakdown_tol(mut self, tol: U) -> Fgmres<'a, T, U> {
+        self.breakdown_tol = tol;
+        self
+    }
+}
+
+impl<'a, T, U> IterativeSolver<T, U> for Fgmres<'a, T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    type State = FgmresState<T, U>;
+
+    fn solve_with_monitor(
+        &self,
+        A: &dyn LinearOperator<T>,
+        b: ArrayView1<T>,
+        x0: ArrayView1<T>,
+        criterion: &StoppingCriterion<U>,
+        monitor: &mut dyn Monitor<FgmresState<T, U>, U>,
+    ) -> SolveReport<T, U> {
+        let ident = IdentityOperator::new(b.len());
+        let M: &dyn FlexiblePreconditioner<T> = match self.M {
+            Some(M) => M,
+            None => &ident,
+        };
+        let start = Instant::now();
+        let b_norm = norm(b);
+        let mut ags = FgmresState::new(A, x0, b, self.m, U::zero());
+        ags.arn.breakdown_tol = self.breakdown_tol;
+        let r0_norm = ags.beta;
+        ags.tol = criterion
+            .residual_threshold(r0_norm, b_norm)
+            .map_or(U::zero(), |(th, _)| th);
+        let mut history = vec![r0_norm];
+        let mut iterations = 0;
+        let reason = loop {
+            let info = IterInfo::new(iterations, ags.resid, r0_norm, b_norm, start.elapsed());
+            if let Some(reason) = criterion.check(&info) {
+                break reason;
+            }
+            if ags.beta == U::zero() {
+                // x is exact, a cycle would have nothing to iterate on
+                break criterion.exact_reason(r0_norm, b_norm);
+            }
+            let action = ags.next_with_monitor(A, M, iterations, monitor);
+            iterations += ags.Z.len();
+            history.push(ags.resid);
+            if action == MonitorAction::Abort {
+                break StopReason::Aborted;
+            }
+            if ags.breakdown {
+                break StopReason::Breakdown;
+            }
+        };
+        let reason = match reason {
+            StopReason::Breakdown => criterion.breakdown_reason(ags.resid, r0_norm, b_norm),
+            _ => reason,
+        };
+        let resid_norm = norm(ags.calc_resid(A, &ags.b).view());
+        SolveReport {
+            x: ags.x,
+            iterations,
+            resid_norm,
+            reason,
+            history,
+        }
+    }
+}
@@ -0,0 +1,557 @@
+#![allow(non_snake_case)]
+#![allow(clippy::many_single_char_names)]
+#![allow(clippy::too_many_arguments)]
+use ndarray::{s, Array1, Array2, ArrayView1, Axis};
+use num_complex::Complex;
+use num_traits::Float;
+use std::cell::{Ref, RefCell};
+use std::time::Instant;
+
+use super::gmres::Preconditioning;
+use super::gmresdr::{orthonormalize, qr_solve_dense, smallest_eigenspace, triangularize};
+use crate::arnoldi::ArnoldiSpace;
+use crate::monitor::{Monitor, MonitorAction, SilentMonitor};
+use crate::operator::{check_shape, LinearOperator};
+use crate::qr::householder_reflection;
+use crate::solver::{IterativeSolver, SolveReport};
+use crate::stopping::{IterInfo, StopReason, StoppingCriterion};
+use crate::utils::{hermit, inner, norm, ComplexOrReal};
+
+/// A recycled subspace for GCRO-DR: `U` spans the approximate invariant
+/// subspace of the `k` harmonic Ritz values of smallest modulus, and
+/// `C = A U` has orthonormal columns.
+///
+/// The space outlives a solve and can be handed to the next one, e.g. along
+/// a time-stepping or parameter sweep where the matrix changes slowly.
+/// `update_operator` makes it consistent with a new matrix.
+#[derive(Clone)]
+pub struct RecycleSpace<T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    /// the number of vectors to recycle
+    pub k: usize,
+    pub U: Vec<Array1<T>>,
+    pub C: Vec<Array1<T>>,
+    /// harmonic Ritz values belonging to `U` at the last update
+    pub ritz: Vec<Complex<U>>,
+}
+
+impl<T, U> RecycleSpace<T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    pub fn new(k: usize) -> RecycleSpace<T, U> {
+        RecycleSpace {
+            k,
+            U: Vec::new(),
+            C: Vec::new(),
+            ritz: Vec::new(),
+        }
+    }
+
+    pub fn dim(&self) -> usize {
+        self.U.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.U.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.U.clear();
+        self.C.clear();
+        self.ritz.clear();
+    }
+
+    /// Recomputes `C = A U` for a new operator and orthonormalizes it by
+    /// Gram–Schmidt, applying the same transformation to `U` so that
+    /// `A U = C` still holds. Costs `dim()` operator applications; vectors
+    /// that `A` maps to (numerically) dependent ones are dropped.
+    pub fn update_operator(&mut self, A: &dyn LinearOperator<T>) {
+        let mut U = Vec::with_capacity(self.dim());
+        let mut C: Vec<Array1<T>> = Vec::with_capacity(self.dim());
+        for u in self.U.iter() {
+            let mut u = u.clone();
+            let mut c = A.apply(u.view());
+            let c_norm = norm(c.view());
+            for _ in 0..2 {
+                for (ui, ci) in U.iter().zip(C.iter()) {
+                    let h = inner(ci.view(), c.view());
+                    c = &c - &(ci * h);
+                    u = &u - &(ui * h);
+                }
+            }
+            let rjj: U = norm(c.view());
+            if rjj <= U::epsilon() * c_norm || !rjj.is_finite() {
+                continue;
+            }
+            U.push(u / T::from(rjj));
+            C.push(c / T::from(rjj));
+        }
+        self.U = U;
+        self.C = C;
+    }
+}
+
+/// State of GCRO-DR(m, k) (Parks, de Sturler, Mackey, Johnson and Maiti,
+/// 2006), restarted GMRES that keeps a recycled subspace `space` across
+/// restarts and across systems.
+///
+/// Each cycle first removes the component of the residual in `range(C)`,
+/// then runs `m - dim` Arnoldi steps with `(I - C C^H) A`, and finally
+/// replaces `space` by the harmonic Ritz vectors of the combined space
+/// `[U, V]`. When `space` is empty the first cycle is plain GMRES(m).
+pub struct GcroDrState<T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    pub m: usize,
+    pub tol: U,
+    pub x: Array1<T>,
+    pub b: Array1<T>,
+    pub beta: U,
+    pub resid: U,
+    pub r: Array1<T>,
+    /// number of Arnoldi steps in the last cycle
+    pub steps: usize,
+    pub converged: bool,
+    /// the last cycle ended in a breakdown of the Arnoldi process
+    pub breakdown: bool,
+    pub space: RecycleSpace<T, U>,
+    pub arn: ArnoldiSpace<T, U>,
+}
+
+/// `x += U C^H r` and `r -= C C^H r`, which minimizes the residual over
+/// `range(U)` since `A U = C`.
+fn project_out<T, U>(ags: &mut GcroDrState<T, U>, M: Preconditioning<T>)
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    if ags.space.is_empty() {
+        return;
+    }
+    let n = ags.x.len();
+    let mut dx = Array1::zeros(n);
+    for (u, c) in ags.space.U.iter().zip(ags.space.C.iter()) {
+        let h = inner(c.view(), ags.r.view());
+        dx = &dx + &(u * h);
+        ags.r = &ags.r - &(c * h);
+    }
+    ags.x = &ags.x + &M.apply_right(dx);
+    ags.beta = norm(ags.r.view());
+}
+
+/// Replaces the recycled space by the harmonic Ritz vectors of the cycle.
+/// `B` holds `C^H A v_j` for the new Arnoldi vectors `v_j`.
+fn update_space<T, U>(ags: &mut GcroDrState<T, U>, B: &[Array1<T>])
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    let k = ags.space.dim();
+    let j = ags.arn.H.len();
+    let mk = k + j;
+    // A W = Vhat G with W = [U D, V_j], Vhat = [C, V_{j+1}] and
+    // G = [D, B; 0, Hbar], D = diag(1 / ‖u_i‖)
+    let d: Vec<U> = ags
+        .space
+        .U
+        .iter()
+        .map(|u| U::one() / norm(u.view()))
+        .collect();
+    let mut G = Array2::zeros((mk + 1, mk));
+    for i in 0..k {
+        G[(i, i)] = T::from(d[i]);
+    }
+    for (l, b) in B.iter().enumerate() {
+        G.slice_mut(s![..k, k + l]).assign(b);
+    }
+    G.slice_mut(s![k.., k..]).assign(&ags.arn.get_H_star());
+
+    let W: Vec<Array1<T>> = ags
+        .space
+        .U
+        .iter()
+        .zip(d.iter())
+        .map(|(u, &di)| u * T::from(di))
+        .chain(ags.arn.Q.iter().take(j).cloned())
+        .collect();
+    let Vhat: Vec<&Array1<T>> = ags.space.C.iter().chain(ags.arn.Q.iter()).collect();
+    let VhW = Array2::from_shape_fn((mk + 1, mk), |(a, b)| inner(Vhat[a].view(), W[b].view()));
+
+    // harmonic Ritz pairs: G^H G z = θ G^H Vhat^H W z
+    let Gh = hermit(G.view());
+    let (q, r) = householder_reflection(Gh.dot(&VhW).view());
+    let S = qr_solve_dense(&q, &r, Gh.dot(&G).view());
+    let (P, ritz) = smallest_eigenspace(S.view(), ags.space.k.min(mk - 1));
+    let kk = P.ncols();
+
+    // Y = W P = U_new R and G P = Q R, so that A U_new = Vhat Q
+    let GP = G.dot(&P);
+    let mut q = GP.clone();
+    orthonormalize(&mut q);
+    let r = hermit(q.view()).dot(&GP);
+    let combine = |vs: &[&Array1<T>], coef: ArrayView1<T>| {
+        vs.iter()
+            .zip(coef.iter())
+            .fold(Array1::zeros(ags.x.len()), |acc, (v, &c)| acc + &(*v * c))
+    };
+    let Wr: Vec<&Array1<T>> = W.iter().collect();
+    let C: Vec<Array1<T>> = (0..kk).map(|i| combine(&Vhat, q.column(i))).collect();
+    let mut Unew: Vec<Array1<T>> = Vec::with_capacity(kk);
+    for (i, p) in P.axis_iter(Axis(1)).enumerate() {
+        let mut y = combine(&Wr, p);
+        for (l, ul) in Unew.iter().enumerate() {
+            y = &y - &(ul * r[(l, i)]);
+        }
+        Unew.push(y / r[(i, i)]);
+    }
+    ags.space.U = Unew;
+    ags.space.C = C;
+    ags.space.ritz = ritz;
+}
+
+pub fn gcrodr1<T, U>(ags: &mut GcroDrState<T, U>, A: &dyn LinearOperator<T>, M: Preconditioning<T>)
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    gcrodr1_with_monitor(ags, A, M, 0, &mut SilentMonitor);
+}
+
+/// `gcrodr1`, calling `monitor` after every Arnoldi step with the Givens
+/// residual estimate; the steps are numbered from `iter + 1`. An
+/// `Abort` ends the cycle early, with `x` updated as usual, and is returned.
+pub fn gcrodr1_with_monitor<T, U>(
+    ags: &mut GcroDrState<T, U>,
+    A: &dyn LinearOperator<T>,
+    M: Preconditioning<T>,
+    iter: usize,
+    monitor: &mut dyn Monitor<GcroDrState<T, U>, U>,
+) -> MonitorAction
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    ags.breakdown = false;
+    project_out(ags, M);
+    // range(U) already contains the solution
+    if ags.beta == U::zero() {
+        if M.true_residual() {
+            ags.resid = norm(ags.calc_resid(A, &ags.b).view());
+        } else {
+            ags.resid = ags.beta;
+        }
+        ags.steps = 0;
+        ags.converged = true;
+        return MonitorAction::Continue;
+    }
+    ags.arn.reset(ags.r.view());
+    let op = M.operator(A);
+    let m = (ags.m - ags.space.dim()).min(ags.x.len());
+    let mut s = Array1::<T>::zeros(m + 1);
+    s[0] = T::from(ags.beta);
+    let mut R: Vec<Array1<T>> = Vec::with_capacity(m);
+    let mut B: Vec<Array1<T>> = Vec::with_capacity(m);
+    let mut rots = Vec::new();
+    let mut i = 0;
+    let mut action = MonitorAction::Continue;
+    while i < m {
+        let mut v = op.apply(ags.arn.Q[i].view());
+        let b: Array1<T> = ags
+            .space
+            .C
+            .iter()
+            .map(|c| inner(c.view(), v.view()))
+            .collect();
+        for (c, &h) in ags.space.C.iter().zip(b.iter()) {
+            v = &v - &(c * h);
+        }
+        B.push(b);
+        ags.breakdown = ags.arn.extend(v).is_err();
+        R.push(triangularize(&ags.arn.H[i], i, &mut rots, &mut s));
+        ags.resid = s[i + 1].abs();
+        i += 1;
+        if monitor.observe(iter + i, ags.resid, ags) == MonitorAction::Abort {
+            action = MonitorAction::Abort;
+            break;
+        }
+        if ags.resid < ags.tol || ags.breakdown {
+            break;
+        }
+    }
+
+    // the least-squares problem is solved by the GMRES solution y of the
+    // Hessenberg part, with the U component -B y cancelling C B y
+    let mut y = s.slice(s![..i]).to_owned();
+    for l in (0..i).rev() {
+        y[l] = y[l] / R[l][l];
+        for j in 0..l {
+            y[j] = y[j] - R[l][j] * y[l];
+        }
+    }
+    let mut dx = y
+        .iter()
+        .zip(ags.arn.Q.iter())
+        .fold(Array1::zeros(ags.x.len()), |acc, (&yj, q)| acc + &(q * yj));
+    for (l, u) in ags.space.U.iter().enumerate() {
+        let by = B
+            .iter()
+            .zip(y.iter())
+            .fold(T::zero(), |acc, (b, &yj)| acc + b[l] * yj);
+        dx = &dx - &(u * by);
+    }
+    ags.x = &ags.x + &M.apply_right(dx);
+    let w = &ags.b - &A.apply(ags.x.view());
+    if M.true_residual() {
+        ags.resid = norm(w.view());
+    }
+    ags.r = M.apply_left(w);
+    ags.beta = norm(ags.r.view());
+    if !M.true_residual() {
+        ags.resid = ags.beta;
+    }
+    ags.steps = i;
+    ags.converged = ags.resid < ags.tol || ags.beta == U::zero();
+    if ags.space.k > 0 && !ags.breakdown && ags.space.dim() + i > 1 {
+        update_space(ags, &B);
+    }
+    action
+}
+
+impl<T, U> GcroDrState<T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    pub fn create(
+        problem_size: usize,
+        m: usize,
+        space: RecycleSpace<T, U>,
+        tol: U,
+    ) -> GcroDrState<T, U> {
+        assert!(space.k < m, "GCRO-DR needs k < m");
+        GcroDrState {
+            m,
+            tol,
+            x: Array1::<T>::zeros(problem_size),
+            b: Array1::<T>::zeros(problem_size),
+            beta: U::zero(),
+            resid: U::zero(),
+            r: Array1::<T>::zeros(problem_size),
+            steps: 0,
+            converged: false,
+            breakdown: false,
+            space,
+            arn: ArnoldiSpace::empty(),
+        }
+    }
+
+    /// Also brings the recycled space up to date with `A`, see
+    /// `RecycleSpace::update_operator`.
+    pub fn init(
+        &mut self,
+        A: &dyn LinearOperator<T>,
+        x: ArrayView1<T>,
+        b: ArrayView1<T>,
+        M: Preconditioning<T>,
+    ) {
+        check_shape(A, b.len(), x.len());
+        self.b = b.to_owned();
+        self.x = x.to_owned();
+        let w = &b - &A.apply(x.view());
+        self.resid = norm(w.view());
+        self.r = M.apply_left(w);
+        self.beta = norm(self.r.view());
+        if !M.true_residual() {
+            self.resid = self.beta;
+        }
+        self.converged = false;
+        self.steps = 0;
+        if !self.space.is_empty() {
+            self.space.update_operator(&M.operator(A));
+        }
+    }
+
+    pub fn new(
+        A: &dyn LinearOperator<T>,
+        x: ArrayView1<T>,
+        b: ArrayView1<T>,
+        M: Preconditioning<T>,
+        m: usize,
+        space: RecycleSpace<T, U>,
+        tol: U,
+    ) -> Self {
+        let mut result = Self::create(b.len(), m, space, tol);
+        result.init(A, x, b, M);
+        result
+    }
+
+    pub fn next(&mut self, A: &dyn LinearOperator<T>, M: Preconditioning<T>) {
+        if self.converged {
+            return;
+        }
+        gcrodr1(self, A, M);
+    }
+
+    /// `next` with a per-step `monitor`, see `gcrodr1_with_monitor`
+    pub fn next_with_monitor(
+        &mut self,
+        A: &dyn LinearOperator<T>,
+        M: Preconditioning<T>,
+        iter: usize,
+        monitor: &mut dyn Monitor<Self, U>,
+    ) -> MonitorAction {
+        if self.converged {
+            return MonitorAction::Continue;
+        }
+        gcrodr1_with_monitor(self, A, M, iter, monitor)
+    }
+
+    pub fn calc_resid(&self, lhs: &dyn LinearOperator<T>, b: &Array1<T>) -> Array1<T> {
+        b - &lhs.apply(self.x.view())
+    }
+}
+
+/// GCRO-DR(m, k) as an `IterativeSolver` that recycles its subspace from
+/// one `solve` to the next. The space is created empty by `new`, can be
+/// seeded with `with_recycle_space` and inspected or taken out afterwards.
+/// A solve works on a copy of the space, which replaces the stored one when
+/// the solve returns; if the operator panics, the old space is kept.
+///
+/// Preconditioning and the residual tests are as for `Gmres`; the recycled
+/// space belongs to the preconditioned operator. `iterations` counts Arnoldi
+/// steps and leaves out the `k` operator applications per solve that adapt
+/// the space to the (possibly changed) operator. A breakdown of the Arnoldi
+/// process ends the solve as in `Gmres`.
+pub struct GcroDr<'a, T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    pub m: usize,
+    pub M: Preconditioning<'a, T>,
+    pub space: RefCell<RecycleSpace<T, U>>,
+    /// see `ArnoldiSpace::breakdown_tol`
+    pub breakdown_tol: U,
+}
+
+impl<'a, T, U> GcroDr<'a, T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    pub fn new(m: usize, k: usize) -> GcroDr<'a, T, U> {
+        assert!(k < m, "GCRO-DR needs k < m");
+        GcroDr {
+            m,
+            M: Preconditioning::None,
+            space: RefCell::new(RecycleSpace::new(k)),
+            breakdown_tol: U::zero(),
+        }
+    }
+
+    /// left preconditioning by `M`
+    pub fn with_preconditioner(mut self, M: &'a dyn LinearOperator<T>) -> GcroDr<'a, T, U> {
+        self.M = Preconditioning::Left(M);
+        self
+    }
+
+    pub fn with_preconditioning(mut self, M: Preconditioning<'a, T>) -> GcroDr<'a, T, U> {
+        self.M = M;
+        self
+    }
+
+    pub fn with_breakdown_tol(mut self, tol: U) -> GcroDr<'a, T, U> {
+        self.breakdown_tol = tol;
+        self
+    }
+
+    pub fn with_recycle_space(self, space: RecycleSpace<T, U>) -> GcroDr<'a, T, U> {
+        assert!(space.k < self.m, "GCRO-DR needs k < m");
+        self.space.replace(space);
+        self
+    }
+
+    pub fn recycle_space(&self) -> Ref<'_, RecycleSpace<T, U>> {
+        self.space.borrow()
+    }
+
+    pub fn into_recycle_space(self) -> RecycleSpace<T, U> {
+        self.space.into_inner()
+    }
+}
+
+impl<'a, T, U> IterativeSolver<T, U> for GcroDr<'a, T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    type State = GcroDrState<T, U>;
+
+    fn solve_with_monitor(
+        &self,
+        A: &dyn LinearOperator<T>,
+        b: ArrayView1<T>,
+        x0: ArrayView1<T>,
+        criterion: &StoppingCriterion<U>,
+        monitor: &mut dyn Monitor<GcroDrState<T, U>, U>,
+    ) -> SolveReport<T, U> {
+        let M = self.M;
+        let start = Instant::now();
+        let b_norm = if M.true_residual() {
+            norm(b)
+        } else {
+            norm(M.apply_left(b.to_owned()).view())
+        };
+        // the solve works on a copy, so that a panic in the operator leaves
+        // the previous space in place
+        let space = self.space.borrow().clone();
+        let mut ags = GcroDrState::new(A, x0, b, M, self.m, space, U::zero());
+        ags.arn.breakdown_tol = self.breakdown_tol;
+        let r0_norm = ags.resid;
+        ags.tol = criterion
+            .residual_threshold(r0_norm, b_norm)
+            .map_or(U::zero(), |(th, _)| th);
+        let mut history = vec![r0_norm];
+        let mut iterations = 0;
+        let reason = loop {
+            let info = IterInfo::new(iterations, ags.resid, r0_norm, b_norm, start.elapsed());
+            if let Some(reason) = criterion.check(&info) {
+                break reason;
+            }
+            let action = ags.next_with_monitor(A, M, iterations, monitor);
+            if ags.steps == 0 {
+                // range(U) held the solution, x is exact after the projection
+                break criterion.exact_reason(r0_norm, b_norm);
+            }
+            iterations += ags.steps;
+            history.push(ags.resid);
+            if action == MonitorAction::Abort {
+                break StopReason::Aborted;
+            }
+            if ags.breakdown {
+                break StopReason::Breakdown;
+            }
+        };
+        // the residual tests were applied to `ags.resid`
+        let reason = match reason {
+            StopReason::Breakdown => criterion.breakdown_reason(ags.resid, r0_norm, b_norm),
+            _ => reason,
+        };
+        let resid_norm = norm(ags.calc_resid(A, &ags.b).view());
+        self.space.replace(ags.space);
+        SolveReport {
+            x: ags.x,
+            iterations,
+            resid_norm,
+            reason,
+            history,
+        }
+    }
+}
@@ -4,18 +4,108 @@
 #![allow(clippy::too_many_arguments)]
 use ndarray::{Array1, ArrayView1};
 use num_traits::Float;
+use std::time::Instant;
 
 use super::utils::{apply_plane_rotation, generate_plane_rotation, update2};
-use crate::arnoldi::ArnoldiSpace;
+use crate::arnoldi::{ArnoldiSpace, Orthogonalization};
+use crate::monitor::{Monitor, MonitorAction, SilentMonitor};
+use crate::operator::{check_shape, FnOperator, LinearOperator};
+use crate::solver::{IterativeSolver, SolveReport};
+use crate::stopping::{IterInfo, StopReason, StoppingCriterion};
 use crate::utils::norm;
 use crate::utils::ComplexOrReal;
 
+/// How the preconditioner enters GMRES. With `M ≈ A^-1`:
+/// `Left(M)` solves `M A x = M b`, `Right(M)` solves `A M y = b` with
+/// `x = M y`, and `Split(M1, M2)` solves `M1 A M2 y = M1 b` with `x = M2 y`.
+///
+/// With a right factor (`Right` or `Split`) `resid` is the true residual
+/// `‖b - A x‖` at the end of every cycle, and `x` is always the solution of
+/// the original system.
+pub enum Preconditioning<'a, T> {
+    None,
+    Left(&'a dyn LinearOperator<T>),
+    Right(&'a dyn LinearOperator<T>),
+    Split(&'a dyn LinearOperator<T>, &'a dyn LinearOperator<T>),
+}
+
+// `#[default]` on enum variants needs Rust 1.62
+#[allow(clippy::derivable_impls)]
+impl<'a, T> Default for Preconditioning<'a, T> {
+    fn default() -> Self {
+        Preconditioning::None
+    }
+}
+
+impl<'a, T> Clone for Preconditioning<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T> Copy for Preconditioning<'a, T> {}
+
+impl<'a, T> Preconditioning<'a, T> {
+    pub fn left(&self) -> Option<&'a dyn LinearOperator<T>> {
+        match *self {
+            Preconditioning::Left(M) | Preconditioning::Split(M, _) => Some(M),
+            _ => None,
+        }
+    }
+
+    pub fn right(&self) -> Option<&'a dyn LinearOperator<T>> {
+        match *self {
+            Preconditioning::Right(M) | Preconditioning::Split(_, M) => Some(M),
+            _ => None,
+        }
+    }
+
+    /// whether `resid` of a finished cycle is the unpreconditioned residual
+    pub fn true_residual(&self) -> bool {
+        self.left().is_none() || self.right().is_some()
+    }
+
+    pub fn apply_left(&self, v: Array1<T>) -> Array1<T> {
+        match self.left() {
+            Some(M) => M.apply(v.view()),
+            None => v,
+        }
+    }
+
+    pub fn apply_right(&self, v: Array1<T>) -> Array1<T> {
+        match self.right() {
+            Some(M) => M.apply(v.view()),
+            None => v,
+        }
+    }
+
+    /// `M1 A M2`, the operator GMRES actually sees
+    pub fn operator<'b>(&'b self, A: &'b dyn LinearOperator<T>) -> FnOperator<'b, T> {
+        FnOperator::new(A.shape(), move |x| {
+            let av = match self.right() {
+                Some(M) => A.apply(M.apply(x).view()),
+                None => A.apply(x),
+            };
+            self.apply_left(av)
+        })
+    }
+}
+
+/// State of restarted GMRES(m) for `A x = b`.
+///
+/// `tol` and `resid` are absolute norms of the same residual (see
+/// `Preconditioning`), and a cycle stops once `resid < tol`. Before
+/// `StoppingCriterion` was introduced `tol` was compared with `resid^2`, and
+/// `init` stored `resid` relative to `‖M b‖` (the steps always stored it
+/// absolute); callers that drive the state directly must pass the square
+/// root of their old `tol`.
 pub struct GmresState<T, U>
 where
     T: ComplexOrReal<U> + std::fmt::Debug,
     U: Float + std::fmt::Debug,
 {
     pub m: usize,
+    /// absolute threshold on `resid`
     pub tol: U,
     pub x: Array1<T>,
     pub b: Array1<T>,
@@ -25,39 +115,57 @@ where
     pub sn: Array1<T>,
     //pub av: Array1<T>,
     pub beta: U,
+    /// absolute residual norm at the end of the last step
     pub resid: U,
     pub r: Array1<T>,
     //pub v: Vec<Array1<T>>,
     pub converged: bool,
+    /// the last cycle ended in a breakdown of the Arnoldi process; with the
+    /// default `arn.breakdown_tol` of zero, `x` then solves the system
+    pub breakdown: bool,
     pub arn: ArnoldiSpace<T, U>,
 }
 
-pub fn gmres1<T, U>(
+pub fn gmres1<T, U>(ags: &mut GmresState<T, U>, A: &dyn LinearOperator<T>, M: Preconditioning<T>)
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    gmres1_with_monitor(ags, A, M, 0, &mut SilentMonitor);
+}
+
+/// `gmres1`, calling `monitor` after every Arnoldi step with the Givens
+/// residual estimate; the steps are numbered from `iter + 1`. An
+/// `Abort` ends the cycle early, with `x` updated as usual, and is returned.
+pub fn gmres1_with_monitor<T, U>(
     ags: &mut GmresState<T, U>,
-    A: &dyn Fn(ArrayView1<T>) -> Array1<T>,
-    M: Option<&dyn Fn(ArrayView1<T>) -> Array1<T>>,
-) where
+    A: &dyn LinearOperator<T>,
+    M: Preconditioning<T>,
+    iter: usize,
+    monitor: &mut dyn Monitor<GmresState<T, U>, U>,
+) -> MonitorAction
+where
     T: ComplexOrReal<U> + std::fmt::Debug,
     U: Float + std::fmt::Debug,
 {
+    if ags.beta == U::zero() {
+        ags.converged = true;
+        return MonitorAction::Continue;
+    }
     //ags.v[0] = &ags.r / ags.beta;
     ags.arn.reset(ags.r.view());
     //println!("v={:?}", v);
     let mut s = Array1::<T>::zeros(ags.m + 1);
     s[0] = <T as From<U>>::from(ags.beta);
 
+    let op = M.operator(A);
     let mut i = 0;
+    let mut action = MonitorAction::Continue;
+    ags.breakdown = false;
     while i < ags.m {
-        ags.arn
-            .iter(&|x| {
-                let av = A(x);
-                if let Some(ref M) = M {
-                    M(av.view())
-                } else {
-                    av
-                }
-            })
-            .unwrap();
+        // a breakdown leaves the last column of H with a zero below the
+        // diagonal, and the Givens rotations below then give a zero residual
+        ags.breakdown = ags.arn.iter(&op).is_err();
 
         for k in 0..i {
             let (dx, dy) =
@@ -91,25 +199,33 @@ pub fn gmres1<T, U>(
         //std::process::exit(0);
 
         ags.resid = s[i + 1].abs();
-        if ags.resid.powi(2) < ags.tol {
+        i += 1;
+        if monitor.observe(iter + i, ags.resid, ags) == MonitorAction::Abort {
+            action = MonitorAction::Abort;
+            break;
+        }
+        if ags.resid < ags.tol || ags.breakdown {
             //println!("resid={:?}, {:?}", resid, tol);
-            update2(&mut ags.x, i, &ags.arn.H, &s, &ags.arn.Q[..]);
-            //*tol = ags.resid.powi(2);
-            ags.converged = true;
-            return;
+            break;
         }
-        i += 1;
     }
-    update2(&mut ags.x, i - 1, &ags.arn.H, &s, &ags.arn.Q[..]);
+    let mut y = Array1::<T>::zeros(ags.x.len());
+    update2(&mut y, i - 1, &ags.arn.H, &s, &ags.arn.Q[..]);
+    ags.x = &ags.x + &M.apply_right(y);
     //ags.r = ;
-    let w = &ags.b - &A(ags.x.view());
-    ags.r = if let Some(ref M) = M { M(w.view()) } else { w };
+    let w = &ags.b - &A.apply(ags.x.view());
+    if M.true_residual() {
+        ags.resid = norm(w.view());
+    }
+    ags.r = M.apply_left(w);
     ags.beta = norm(ags.r.view());
-    if ags.resid.powi(2) < ags.tol {
-        ags.converged = true;
-        return;
+    // the Givens estimate is zero after any breakdown, also an inexact one
+    // with a nonzero `breakdown_tol`
+    if !M.true_residual() {
+        ags.resid = ags.beta;
     }
-    ags.converged = false;
+    ags.converged = ags.resid < ags.tol;
+    action
 }
 
 impl<T, U> GmresState<T, U>
@@ -133,42 +249,38 @@ where
             r: Array1::<T>::zeros(problem_size),
             //v: (0..=m).map(|_| Array1::<T>::zeros(problem_size)).collect(),
             converged: false,
+            breakdown: false,
             arn: ArnoldiSpace::empty(),
         }
     }
 
     pub fn init(
         &mut self,
-        A: &dyn Fn(ArrayView1<T>) -> Array1<T>,
+        A: &dyn LinearOperator<T>,
         x: ArrayView1<T>,
         b: ArrayView1<T>,
-        M: &dyn Fn(ArrayView1<T>) -> Array1<T>,
+        M: Preconditioning<T>,
     ) {
-        let w = M(b.view());
-        let normb = {
-            let nb = norm(w.view());
-            if nb == U::zero() {
-                U::one()
-            } else {
-                nb
-            }
-        };
+        check_shape(A, b.len(), x.len());
         self.b = b.to_owned();
         self.x = x.to_owned();
-        self.r = A(x.view());
+        self.r = A.apply(x.view());
         let w = &b - &self.r;
-        self.r = M(w.view());
+        self.resid = norm(w.view());
+        self.r = M.apply_left(w);
         self.beta = norm(self.r.view());
-        self.resid = self.beta / normb;
+        if !M.true_residual() {
+            self.resid = self.beta;
+        }
         self.converged = false;
         self.arn.reset(self.r.view());
     }
 
     pub fn new(
-        A: &dyn Fn(ArrayView1<T>) -> Array1<T>,
+        A: &dyn LinearOperator<T>,
         x: ArrayView1<T>,
         b: ArrayView1<T>,
-        M: &dyn Fn(ArrayView1<T>) -> Array1<T>,
+        M: Preconditioning<T>,
         m: usize,
         tol: U,
     ) -> Self {
@@ -177,18 +289,159 @@ where
         result
     }
 
-    pub fn next(
-        &mut self,
-        A: &dyn Fn(ArrayView1<T>) -> Array1<T>,
-        M: Option<&dyn Fn(ArrayView1<T>) -> Array1<T>>,
-    ) {
+    pub fn next(&mut self, A: &dyn LinearOperator<T>, M: Preconditioning<T>) {
         if self.converged {
             return;
         }
         gmres1(self, A, M);
     }
 
-    pub fn calc_resid(&self, lhs: &dyn Fn(ArrayView1<T>) -> Array1<T>, b: &Array1<T>) -> Array1<T> {
-        b - &lhs(self.x.view())
+    /// `next` with a per-step `monitor`, see `gmres1_with_monitor`
+    pub fn next_with_monitor(
+        &mut self,
+        A: &dyn LinearOperator<T>,
+        M: Preconditioning<T>,
+        iter: usize,
+        monitor: &mut dyn Monitor<Self, U>,
+    ) -> MonitorAction {
+        if self.converged {
+            return MonitorAction::Continue;
+        }
+        gmres1_with_monitor(self, A, M, iter, monitor)
+    }
+
+    pub fn calc_resid(&self, lhs: &dyn LinearOperator<T>, b: &Array1<T>) -> Array1<T> {
+        b - &lhs.apply(self.x.view())
+    }
+}
+
+/// Restarted GMRES(m) as an `IterativeSolver`.
+///
+/// With left preconditioning the residual tests apply to the preconditioned
+/// residual `M (b - A x)`, and `‖b‖` is replaced by `‖M b‖`; with right or
+/// split preconditioning they apply to the true residual. The criterion is
+/// tested once per restart cycle, and inside a cycle against the loosest
+/// residual bound it implies. A breakdown of the Arnoldi process ends the
+/// solve; it is reported by the residual test that the solution passes, and
+/// as `Breakdown` only if it passes none (possible with a nonzero
+/// `breakdown_tol`).
+pub struct Gmres<'a, T, U> {
+    pub m: usize,
+    pub M: Preconditioning<'a, T>,
+    /// how the Arnoldi basis is orthogonalized; `Householder` gives
+    /// Walker's HGMRES
+    pub orth: Orthogonalization,
+    /// record `‖I - Q^H Q‖` in `state.arn.loss`, for monitors
+    pub measure_loss: bool,
+    /// see `ArnoldiSpace::breakdown_tol`
+    pub breakdown_tol: U,
+}
+
+impl<'a, T, U> Gmres<'a, T, U>
+where
+    U: Float,
+{
+    pub fn new(m: usize) -> Gmres<'a, T, U> {
+        Gmres {
+            m,
+            M: Preconditioning::None,
+            orth: Orthogonalization::Mgs,
+            measure_loss: false,
+            breakdown_tol: U::zero(),
+        }
+    }
+
+    /// left preconditioning by `M`
+    pub fn with_preconditioner(mut self, M: &'a dyn LinearOperator<T>) -> Gmres<'a, T, U> {
+        self.M = Preconditioning::Left(M);
+        self
+    }
+
+    pub fn with_preconditioning(mut self, M: Preconditioning<'a, T>) -> Gmres<'a, T, U> {
+        self.M = M;
+        self
+    }
+
+    pub fn with_orthogonalization(mut self, orth: Orthogonalization) -> Gmres<'a, T, U> {
+        self.orth = orth;
+        self
+    }
+
+    pub fn with_loss_measurement(mut self, on: bool) -> Gmres<'a, T, U> {
+        self.measure_loss = on;
+        self
+    }
+
+    pub fn with_breakdown_tol(mut self, tol: U) -> Gmres<'a, T, U> {
+        self.breakdown_tol = tol;
+        self
+    }
+}
+
+impl<'a, T, U> IterativeSolver<T, U> for Gmres<'a, T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    type State = GmresState<T, U>;
+
+    fn solve_with_monitor(
+        &self,
+        A: &dyn LinearOperator<T>,
+        b: ArrayView1<T>,
+        x0: ArrayView1<T>,
+        criterion: &StoppingCriterion<U>,
+        monitor: &mut dyn Monitor<GmresState<T, U>, U>,
+    ) -> SolveReport<T, U> {
+        let M = self.M;
+        let start = Instant::now();
+        let b_norm = if M.true_residual() {
+            norm(b)
+        } else {
+            norm(M.apply_left(b.to_owned()).view())
+        };
+        let mut ags = GmresState::create(b.len(), self.m, U::zero());
+        ags.arn.orth = self.orth;
+        ags.arn.measure_loss = self.measure_loss;
+        ags.arn.breakdown_tol = self.breakdown_tol;
+        ags.init(A, x0, b, M);
+        let r0_norm = ags.resid;
+        ags.tol = criterion
+            .residual_threshold(r0_norm, b_norm)
+            .map_or(U::zero(), |(th, _)| th);
+        let mut history = vec![r0_norm];
+        let mut iterations = 0;
+        let reason = loop {
+            let info = IterInfo::new(iterations, ags.resid, r0_norm, b_norm, start.elapsed());
+            if let Some(reason) = criterion.check(&info) {
+                break reason;
+            }
+            if ags.beta == U::zero() {
+                // x is exact, a cycle would have nothing to iterate on
+                break criterion.exact_reason(r0_norm, b_norm);
+            }
+            let action = ags.next_with_monitor(A, M, iterations, monitor);
+            iterations += ags.arn.H.len();
+            history.push(ags.resid);
+            if action == MonitorAction::Abort {
+                break StopReason::Aborted;
+            }
+            if ags.breakdown {
+                break StopReason::Breakdown;
+            }
+        };
+        // the residual tests were applied to `ags.resid`
+        let reason = match reason {
+            StopReason::Breakdown => criterion.breakdown_reason(ags.resid, r0_norm, b_norm),
+            _ => reason,
+        };
+        let resid_norm = norm(ags.calc_resid(A, &ags.b).view());
+        SolveReport {
+            x: ags.x,
+            iterations,
+            resid_norm,
+            reason,
+            history,
+        }
     }
 }
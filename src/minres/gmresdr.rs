@@ -0,0 +1,557 @@
+#![allow(non_snake_case)]
+#![allow(clippy::many_single_char_names)]
+#![allow(clippy::too_many_arguments)]
+use ndarray::{s, Array1, Array2, ArrayView1, ArrayView2, Axis};
+use num_complex::Complex;
+use num_traits::Float;
+use std::time::Instant;
+
+use super::gmres::Preconditioning;
+use super::utils::{apply_plane_rotation, generate_plane_rotation};
+use crate::arnoldi::ArnoldiSpace;
+use crate::eigen::qr::qr_naive_eigvals;
+use crate::monitor::{Monitor, MonitorAction, SilentMonitor};
+use crate::operator::{check_shape, LinearOperator};
+use crate::qr::householder_reflection;
+use crate::solver::{IterativeSolver, SolveReport};
+use crate::stopping::{IterInfo, StopReason, StoppingCriterion};
+use crate::utils::{hermit, inner, norm, ComplexOrReal};
+
+/// cap on the inverse subspace iteration for the harmonic Ritz vectors
+const MAX_INVERSE_ITER: usize = 500;
+
+/// State of GMRES with deflated restarting, GMRES-DR(m, k) (Morgan, 2002).
+///
+/// At a restart the `k` harmonic Ritz vectors of the cycle with the smallest
+/// harmonic Ritz values are kept together with the residual, so the next
+/// cycle starts from an Arnoldi-like relation `A V_k = V_{k+1} H_k` and only
+/// adds `m - k` new vectors. Eigenvalues near the origin, which make
+/// restarted GMRES stagnate, are thereby deflated instead of being
+/// rediscovered in every cycle.
+///
+/// After a deflated restart the first `k` columns of `arn.H` are full
+/// columns of length `k + 1` rather than Hessenberg columns, so
+/// `ArnoldiSpace::get_H` and friends do not apply to them.
+pub struct GmresDrState<T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    pub m: usize,
+    pub k: usize,
+    pub tol: U,
+    pub x: Array1<T>,
+    pub b: Array1<T>,
+    pub beta: U,
+    pub resid: U,
+    pub r: Array1<T>,
+    /// coordinates of `r` in the basis `arn.Q` at the start of a cycle
+    pub c: Array1<T>,
+    /// harmonic Ritz values kept at the last restart, smallest first
+    pub ritz: Vec<Complex<U>>,
+    /// number of operator applications in the last cycle
+    pub steps: usize,
+    pub converged: bool,
+    /// the last cycle ended in a breakdown of the Arnoldi process
+    pub breakdown: bool,
+    pub arn: ArnoldiSpace<T, U>,
+}
+
+/// Solves `A X = B` for a small dense nonsingular `A` given as `A = Q R`.
+pub(crate) fn qr_solve_dense<T, U>(q: &Array2<T>, r: &Array2<T>, B: ArrayView2<T>) -> Array2<T>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float,
+{
+    let n = r.ncols();
+    let mut X = hermit(q.view()).dot(&B);
+    for mut col in X.axis_iter_mut(Axis(1)) {
+        for i in (0..n).rev() {
+            let mut t = col[i];
+            for j in i + 1..n {
+                t = t - r[(i, j)] * col[j];
+            }
+            col[i] = t / r[(i, i)];
+        }
+    }
+    X.slice_move(s![..n, ..])
+}
+
+/// Orthonormalizes the columns of `X` in place by two passes of modified
+/// Gram–Schmidt.
+pub(crate) fn orthonormalize<T, U>(X: &mut Array2<T>)
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float,
+{
+    for j in 0..X.ncols() {
+        for _ in 0..2 {
+            for i in 0..j {
+                let h = inner(X.column(i), X.column(j));
+                let v = &X.column(j) - &(&X.column(i) * h);
+                X.column_mut(j).assign(&v);
+            }
+        }
+        let nrm = T::from(norm(X.column(j)));
+        X.column_mut(j).mapv_inplace(|x| x / nrm);
+    }
+}
+
+fn frobenius<T, U>(A: &Array2<T>) -> U
+where
+    T: ComplexOrReal<U>,
+    U: Float,
+{
+    A.iter()
+        .fold(U::zero(), |acc, x| acc + x.abs() * x.abs())
+        .sqrt()
+}
+
+/// Harmonic Ritz pairs of the `(m+1) x m` Arnoldi matrix `Hbar`, i.e. the
+/// eigenpairs of `H_m + |h_{m+1,m}|^2 H_m^-H e_m e_m^T`.
+///
+/// Returns an orthonormal basis of the span of the harmonic Ritz vectors
+/// belonging to the `k` harmonic Ritz values of smallest modulus, and those
+/// values, as `smallest_eigenspace` does.
+pub fn harmonic_ritz<T, U>(Hbar: ArrayView2<T>, k: usize) -> (Array2<T>, Vec<Complex<U>>)
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    let m = Hbar.ncols();
+    assert_eq!(Hbar.nrows(), m + 1);
+
+    let Hm = Hbar.slice(s![..m, ..]);
+    let h = Hbar[(m, m - 1)].abs();
+    let (q, r) = householder_reflection(hermit(Hm).view());
+    let mut em = Array2::zeros((m, 1));
+    em[(m - 1, 0)] = T::one();
+    let f = qr_solve_dense(&q, &r, em.view());
+    let mut G = Hm.to_owned();
+    for i in 0..m {
+        G[(i, m - 1)] = G[(i, m - 1)] + f[(i, 0)] * T::from(h * h);
+    }
+    smallest_eigenspace(G.view(), k)
+}
+
+/// Invariant subspace of a small dense `G` for its `k` eigenvalues of
+/// smallest modulus: an orthonormal basis and those eigenvalues.
+///
+/// The values come from `qr_naive_eigvals`; the basis from inverse subspace
+/// iteration, which works in the arithmetic of `T` and so returns a real
+/// basis for a real `G`. Eigenvalues of equal modulus, such as a complex
+/// conjugate pair, cannot be separated that way, so `k` is increased until
+/// it does not split such a group (but stays below the order of `G`).
+pub fn smallest_eigenspace<T, U>(G: ArrayView2<T>, k: usize) -> (Array2<T>, Vec<Complex<U>>)
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    let m = G.nrows();
+    assert!(0 < k && k < m, "smallest_eigenspace needs 0 < k < m");
+    let sqrt_eps = U::epsilon().sqrt();
+
+    let mut theta = qr_naive_eigvals(G, sqrt_eps);
+    theta.sort_by(|a, b| {
+        a.norm()
+            .partial_cmp(&b.norm())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let mut k = k;
+    while k < m - 1 && theta[k].norm() - theta[k - 1].norm() <= sqrt_eps * theta[k].norm() {
+        k += 1;
+    }
+
+    let G = G.to_owned();
+    let (q, r) = householder_reflection(G.view());
+    let mut X = Array2::from_shape_fn((m, k), |(i, j)| {
+        T::from(U::from((i + 1) * (j + 1)).unwrap().cos())
+    });
+    orthonormalize(&mut X);
+    let th = U::from(100).unwrap() * U::epsilon() * frobenius(&G);
+    for _ in 0..MAX_INVERSE_ITER {
+        X = qr_solve_dense(&q, &r, X.view());
+        orthonormalize(&mut X);
+        let GX = G.dot(&X);
+        let res = &GX - &X.dot(&hermit(X.view()).dot(&GX));
+        if frobenius(&res) <= th {
+            break;
+        }
+    }
+    theta.truncate(k);
+    (X, theta)
+}
+
+/// Applies the rotations of the previous columns to column `j` of the
+/// Arnoldi matrix, then zeroes it below the diagonal from the bottom up.
+/// New rotations are also applied to the right hand side `s`.
+pub(crate) fn triangularize<T, U>(
+    h: &Array1<T>,
+    j: usize,
+    rots: &mut Vec<(usize, T, T)>,
+    s: &mut Array1<T>,
+) -> Array1<T>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    let mut r = Array1::zeros(s.len());
+    r.slice_mut(s![..h.len()]).assign(h);
+    for &(l, cs, sn) in rots.iter() {
+        let (dx, dy) = apply_plane_rotation(r[l], r[l + 1], cs, sn);
+        r[l] = dx;
+        r[l + 1] = dy;
+    }
+    for l in (j..h.len() - 1).rev() {
+        let (cs, sn) = generate_plane_rotation(r[l], r[l + 1]);
+        let (dx, dy) = apply_plane_rotation(r[l], r[l + 1], cs, sn);
+        r[l] = dx;
+        r[l + 1] = dy;
+        let (dx, dy) = apply_plane_rotation(s[l], s[l + 1], cs, sn);
+        s[l] = dx;
+        s[l + 1] = dy;
+        rots.push((l, cs, sn));
+    }
+    r
+}
+
+/// Replaces the Arnoldi space of a full cycle by the harmonic Ritz vectors
+/// and the residual; `y` is the least-squares solution of the cycle.
+fn deflate<T, U>(ags: &mut GmresDrState<T, U>, y: &Array1<T>)
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    let m = ags.m;
+    let mut Hbar = Array2::zeros((m + 1, m));
+    for (j, h) in ags.arn.H.iter().enumerate() {
+        Hbar.slice_mut(s![..h.len(), j]).assign(h);
+    }
+    // the residual of the cycle in the basis arn.Q
+    let z = &ags.c - &Hbar.dot(y);
+    let (X, ritz) = harmonic_ritz(Hbar.view(), ags.k);
+    let k = X.ncols();
+
+    let mut P = Array2::zeros((m + 1, k + 1));
+    P.slice_mut(s![..m, ..k]).assign(&X);
+    P.column_mut(k).assign(&z);
+    orthonormalize(&mut P);
+    let Ph = hermit(P.view());
+    let Hk = Ph.dot(&Hbar.dot(&P.slice(s![..m, ..k])));
+    let c = Ph.dot(&z);
+
+    let n = ags.x.len();
+    let Q = P
+        .axis_iter(Axis(1))
+        .map(|p| {
+            p.iter()
+                .zip(ags.arn.Q.iter())
+                .fold(Array1::zeros(n), |acc, (&pj, q)| acc + &(q * pj))
+        })
+        .collect();
+    ags.arn.Q = Q;
+    ags.arn.H = Hk.axis_iter(Axis(1)).map(|h| h.to_owned()).collect();
+    ags.c.fill(T::zero());
+    ags.c.slice_mut(s![..=k]).assign(&c);
+    ags.ritz = ritz;
+}
+
+pub fn gmresdr1<T, U>(
+    ags: &mut GmresDrState<T, U>,
+    A: &dyn LinearOperator<T>,
+    M: Preconditioning<T>,
+) where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    gmresdr1_with_monitor(ags, A, M, 0, &mut SilentMonitor);
+}
+
+/// `gmresdr1`, calling `monitor` after every Arnoldi step with the Givens
+/// residual estimate; the new steps are numbered from `iter + 1`. An
+/// `Abort` ends the cycle early, with `x` updated as usual, and is returned.
+pub fn gmresdr1_with_monitor<T, U>(
+    ags: &mut GmresDrState<T, U>,
+    A: &dyn LinearOperator<T>,
+    M: Preconditioning<T>,
+    iter: usize,
+    monitor: &mut dyn Monitor<GmresDrState<T, U>, U>,
+) -> MonitorAction
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    ags.breakdown = false;
+    if ags.beta == U::zero() {
+        ags.steps = 0;
+        ags.converged = true;
+        return MonitorAction::Continue;
+    }
+    let op = M.operator(A);
+    // columns carried over from the last cycle
+    let p = ags.arn.H.len();
+    let m = ags.m.min(ags.x.len());
+    let mut s = ags.c.clone();
+    let mut R: Vec<Array1<T>> = Vec::with_capacity(m);
+    let mut rots = Vec::new();
+    let mut i = 0;
+    let mut action = MonitorAction::Continue;
+    while i < m {
+        if i >= p {
+            let v = op.apply(ags.arn.Q[i].view());
+            ags.breakdown = ags.arn.extend(v).is_err();
+        }
+        R.push(triangularize(&ags.arn.H[i], i, &mut rots, &mut s));
+        ags.resid = norm(s.slice(s![i + 1..]));
+        i += 1;
+        if i <= p {
+            // the carried-over columns are not new steps
+            continue;
+        }
+        if monitor.observe(iter + i - p, ags.resid, ags) == MonitorAction::Abort {
+            action = MonitorAction::Abort;
+            break;
+        }
+        if ags.resid < ags.tol || ags.breakdown {
+            break;
+        }
+    }
+
+    let mut y = s.slice(s![..i]).to_owned();
+    for l in (0..i).rev() {
+        y[l] = y[l] / R[l][l];
+        for j in 0..l {
+            y[j] = y[j] - R[l][j] * y[l];
+        }
+    }
+    let dx = y
+        .iter()
+        .zip(ags.arn.Q.iter())
+        .fold(Array1::zeros(ags.x.len()), |acc, (&yj, q)| acc + &(q * yj));
+    ags.x = &ags.x + &M.apply_right(dx);
+    let w = &ags.b - &A.apply(ags.x.view());
+    if M.true_residual() {
+        ags.resid = norm(w.view());
+    }
+    ags.r = M.apply_left(w);
+    ags.beta = norm(ags.r.view());
+    if !M.true_residual() {
+        ags.resid = ags.beta;
+    }
+    ags.steps = i - p;
+    // an exact solution leaves no residual to restart from
+    ags.converged = ags.resid < ags.tol || ags.beta == U::zero();
+    if ags.converged {
+        return action;
+    }
+    if ags.k > 0 && i == ags.m && !ags.breakdown {
+        deflate(ags, &y);
+    } else {
+        ags.arn.reset(ags.r.view());
+        ags.c.fill(T::zero());
+        ags.c[0] = T::from(ags.beta);
+        ags.ritz.clear();
+    }
+    action
+}
+
+impl<T, U> GmresDrState<T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    pub fn create(problem_size: usize, m: usize, k: usize, tol: U) -> GmresDrState<T, U> {
+        assert!(k < m, "GMRES-DR needs k < m");
+        GmresDrState {
+            m,
+            k,
+            tol,
+            x: Array1::<T>::zeros(problem_size),
+            b: Array1::<T>::zeros(problem_size),
+            beta: U::zero(),
+            resid: U::zero(),
+            r: Array1::<T>::zeros(problem_size),
+            c: Array1::<T>::zeros(m + 1),
+            ritz: Vec::new(),
+            steps: 0,
+            converged: false,
+            breakdown: false,
+            arn: ArnoldiSpace::empty(),
+        }
+    }
+
+    pub fn init(
+        &mut self,
+        A: &dyn LinearOperator<T>,
+        x: ArrayView1<T>,
+        b: ArrayView1<T>,
+        M: Preconditioning<T>,
+    ) {
+        check_shape(A, b.len(), x.len());
+        self.b = b.to_owned();
+        self.x = x.to_owned();
+        let w = &b - &A.apply(x.view());
+        self.resid = norm(w.view());
+        self.r = M.apply_left(w);
+        self.beta = norm(self.r.view());
+        if !M.true_residual() {
+            self.resid = self.beta;
+        }
+        self.converged = false;
+        self.steps = 0;
+        self.ritz.clear();
+        self.c.fill(T::zero());
+        self.c[0] = T::from(self.beta);
+        self.arn.reset(self.r.view());
+    }
+
+    pub fn new(
+        A: &dyn LinearOperator<T>,
+        x: ArrayView1<T>,
+        b: ArrayView1<T>,
+        M: Preconditioning<T>,
+        m: usize,
+        k: usize,
+        tol: U,
+    ) -> Self {
+        let mut result = Self::create(b.len(), m, k, tol);
+        result.init(A, x, b, M);
+        result
+    }
+
+    pub fn next(&mut self, A: &dyn LinearOperator<T>, M: Preconditioning<T>) {
+        if self.converged {
+            return;
+        }
+        gmresdr1(self, A, M);
+    }
+
+    /// `next` with a per-step `monitor`, see `gmresdr1_with_monitor`
+    pub fn next_with_monitor(
+        &mut self,
+        A: &dyn LinearOperator<T>,
+        M: Preconditioning<T>,
+        iter: usize,
+        monitor: &mut dyn Monitor<Self, U>,
+    ) -> MonitorAction {
+        if self.converged {
+            return MonitorAction::Continue;
+        }
+        gmresdr1_with_monitor(self, A, M, iter, monitor)
+    }
+
+    pub fn calc_resid(&self, lhs: &dyn LinearOperator<T>, b: &Array1<T>) -> Array1<T> {
+        b - &lhs.apply(self.x.view())
+    }
+}
+
+/// GMRES-DR(m, k) as an `IterativeSolver`: restarted GMRES with a Krylov
+/// space of dimension `m`, of which `k` harmonic Ritz vectors are kept
+/// across restarts. `k = 0` is GMRES(m).
+///
+/// Preconditioning and the residual tests are as for `Gmres`; `iterations`
+/// counts operator applications, `m` in the first cycle and `m - k` after.
+/// A breakdown of the Arnoldi process ends the solve as in `Gmres`.
+pub struct GmresDr<'a, T, U> {
+    pub m: usize,
+    pub k: usize,
+    pub M: Preconditioning<'a, T>,
+    /// see `ArnoldiSpace::breakdown_tol`
+    pub breakdown_tol: U,
+}
+
+impl<'a, T, U> GmresDr<'a, T, U>
+where
+    U: Float,
+{
+    pub fn new(m: usize, k: usize) -> GmresDr<'a, T, U> {
+        assert!(k < m, "GMRES-DR needs k < m");
+        GmresDr {
+            m,
+            k,
+            M: Preconditioning::None,
+            breakdown_tol: U::zero(),
+        }
+    }
+
+    /// left preconditioning by `M`
+    pub fn with_preconditioner(mut self, M: &'a dyn LinearOperator<T>) -> GmresDr<'a, T, U> {
+        self.M = Preconditioning::Left(M);
+        self
+    }
+
+    pub fn with_preconditioning(mut self, M: Preconditioning<'a, T>) -> GmresDr<'a, T, U> {
+        self.M = M;
+        self
+    }
+
+    pub fn with_breakdown_tol(mut self, tol: U) -> GmresDr<'a, T, U> {
+        self.breakdown_tol = tol;
+        self
+    }
+}
+
+impl<'a, T, U> IterativeSolver<T, U> for GmresDr<'a, T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    type State = GmresDrState<T, U>;
+
+    fn solve_with_monitor(
+        &self,
+        A: &dyn LinearOperator<T>,
+        b: ArrayView1<T>,
+        x0: ArrayView1<T>,
+        criterion: &StoppingCriterion<U>,
+        monitor: &mut dyn Monitor<GmresDrState<T, U>, U>,
+    ) -> SolveReport<T, U> {
+        let M = self.M;
+        let start = Instant::now();
+        let b_norm = if M.true_residual() {
+            norm(b)
+        } else {
+            norm(M.apply_left(b.to_owned()).view())
+        };
+        let mut ags = GmresDrState::new(A, x0, b, M, self.m, self.k, U::zero());
+        ags.arn.breakdown_tol = self.breakdown_tol;
+        let r0_norm = ags.resid;
+        ags.tol = criterion
+            .residual_threshold(r0_norm, b_norm)
+            .map_or(U::zero(), |(th, _)| th);
+        let mut history = vec![r0_norm];
+        let mut iterations = 0;
+        let reason = loop {
+            let info = IterInfo::new(iterations, ags.resid, r0_norm, b_norm, start.elapsed());
+            if let Some(reason) = criterion.check(&info) {
+                break reason;
+            }
+            if ags.beta == U::zero() {
+                // x is exact, a cycle would have nothing to iterate on
+                break criterion.exact_reason(r0_norm, b_norm);
+            }
+            let action = ags.next_with_monitor(A, M, iterations, monitor);
+            iterations += ags.steps;
+            history.push(ags.resid);
+            if action == MonitorAction::Abort {
+                break StopReason::Aborted;
+            }
+            if ags.breakdown {
+                break StopReason::Breakdown;
+            }
+        };
+        // the residual tests were applied to `ags.resid`
+        let reason = match reason {
+            StopReason::Breakdown => criterion.breakdown_reason(ags.resid, r0_norm, b_norm),
+            _ => reason,
+        };
+        let resid_norm = norm(ags.calc_resid(A, &ags.b).view());
+        SolveReport {
+            x: ags.x,
+            iterations,
+            resid_norm,
+            reason,
+            history,
+        }
+    }
+}
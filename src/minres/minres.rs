@@ -0,0 +1,576 @@
+#![allow(non_snake_case)]
+#![allow(clippy::many_single_char_names)]
+
+use ndarray::{Array1, ArrayView1};
+use num_complex::Complex;
+use num_traits::Float;
+use std::time::Instant;
+
+use super::utils::{apply_plane_rotation, generate_plane_rotation};
+use crate::monitor::{Monitor, MonitorAction};
+use crate::operator::{check_shape, LinearOperator};
+use crate::solver::{IterativeSolver, SolveReport};
+use crate::stopping::{IterInfo, LsqEstimates, StopReason, StoppingCriterion};
+use crate::utils::{inner, norm, ComplexOrReal};
+
+fn re<T, U>(x: T) -> U
+where
+    T: ComplexOrReal<U>,
+    U: Float,
+{
+    Into::<Complex<U>>::into(x).re
+}
+
+/// Symmetric reflector `[c s; s -c]` with `[c s; s -c] [a; b] = [r; 0]` and `r >= 0`.
+pub fn sym_ortho<U>(a: U, b: U) -> (U, U, U)
+where
+    U: Float,
+{
+    if b == U::zero() {
+        let c = if a == U::zero() { U::one() } else { a.signum() };
+        (c, U::zero(), a.abs())
+    } else if a == U::zero() {
+        (U::zero(), b.signum(), b.abs())
+    } else if b.abs() > a.abs() {
+        let t = a / b;
+        let s = b.signum() / (U::one() + t * t).sqrt();
+        let c = s * t;
+        (c, s, b / s)
+    } else {
+        let t = b / a;
+        let c = a.signum() / (U::one() + t * t).sqrt();
+        let s = c * t;
+        (c, s, a / c)
+    }
+}
+
+/// Givens QR of the Lanczos tridiagonal matrix, plain MINRES updates.
+#[derive(Clone)]
+pub struct MinresUpdate<T, U> {
+    c: T,
+    s: T,
+    dbar: T,
+    eps: T,
+    phibar: T,
+    w1: Array1<T>,
+    w2: Array1<T>,
+    gmax: U,
+    gmin: U,
+}
+
+/// QLP factorization of the Lanczos tridiagonal matrix (Choi, Paige and
+/// Saunders 2011); gives the minimum-length solution of singular systems.
+#[derive(Clone)]
+pub struct QlpUpdate<T, U> {
+    x0: Array1<T>,
+    xl2: Array1<T>,
+    w: Array1<T>,
+    wl: Array1<T>,
+    cs: U,
+    sn: U,
+    cr1: U,
+    sr1: U,
+    cr2: U,
+    sr2: U,
+    dltan: U,
+    eplnn: U,
+    gama: U,
+    gamal: U,
+    gamal2: U,
+    phi: U,
+    tau: U,
+    taul: U,
+    taul2: U,
+    vepln: U,
+    veplnl: U,
+    veplnl2: U,
+    eta: U,
+    etal: U,
+    etal2: U,
+    u: U,
+    ul: U,
+    ul2: U,
+    ul3: U,
+    xl2norm: U,
+    gmin: U,
+    gminl: U,
+}
+
+#[derive(Clone)]
+pub enum MinresUpdateKind<T, U> {
+    Minres(MinresUpdate<T, U>),
+    Qlp(QlpUpdate<T, U>),
+}
+
+/// State of preconditioned MINRES for Hermitian (possibly indefinite) `A`.
+///
+/// `M` approximates `A^-1` and must be Hermitian positive definite. `resid`
+/// estimates the residual in the `M`-norm, i.e. the 2-norm when `M` is absent.
+#[derive(Clone)]
+pub struct MinresState<T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    pub x: Array1<T>,
+    /// Lanczos vectors r_{k-1}, r_k (unnormalized) and y = M r_k
+    pub r1: Array1<T>,
+    pub r2: Array1<T>,
+    pub y: Array1<T>,
+    pub beta1: U,
+    pub oldb: U,
+    pub beta: U,
+    pub resid: U,
+    /// ‖A‖_F estimate from the tridiagonal entries
+    pub anorm: U,
+    /// ‖A r‖ estimate for the previous iterate, zero at least-squares solutions
+    pub arnorm: U,
+    /// condition estimate
+    pub acond: U,
+    pub xnorm: U,
+    pub tnorm2: U,
+    /// MINRES-QLP drops solution coordinates that would make ‖x‖ exceed this
+    pub max_xnorm: U,
+    pub niter: usize,
+    pub tol: U,
+    pub converged: bool,
+    pub update: MinresUpdateKind<T, U>,
+}
+
+impl<T, U> MinresState<T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    pub fn new(
+        A: &dyn LinearOperator<T>,
+        x: ArrayView1<T>,
+        b: ArrayView1<T>,
+        M: Option<&dyn LinearOperator<T>>,
+        tol: U,
+        qlp: bool,
+    ) -> MinresState<T, U> {
+        check_shape(A, b.len(), x.len());
+        let n = b.len();
+        let r1 = &b - &A.apply(x);
+        let y = match M {
+            Some(M) => M.apply(r1.view()),
+            None => r1.clone(),
+        };
+        let beta1 = re::<T, U>(inner(r1.view(), y.view())).sqrt();
+        let update = if qlp {
+            MinresUpdateKind::Qlp(QlpUpdate {
+                x0: x.to_owned(),
+                xl2: Array1::zeros(n),
+                w: Array1::zeros(n),
+                wl: Array1::zeros(n),
+                cs: -U::one(),
+                sn: U::zero(),
+                cr1: -U::one(),
+                sr1: U::zero(),
+                cr2: -U::one(),
+                sr2: U::zero(),
+                dltan: U::zero(),
+                eplnn: U::zero(),
+                gama: U::zero(),
+                gamal: U::zero(),
+                gamal2: U::zero(),
+                phi: beta1,
+                tau: U::zero(),
+                taul: U::zero(),
+                taul2: U::zero(),
+                vepln: U::zero(),
+                veplnl: U::zero(),
+                veplnl2: U::zero(),
+                eta: U::zero(),
+                etal: U::zero(),
+                etal2: U::zero(),
+                u: U::zero(),
+                ul: U::zero(),
+                ul2: U::zero(),
+                ul3: U::zero(),
+                xl2norm: U::zero(),
+                gmin: U::zero(),
+                gminl: U::zero(),
+            })
+        } else {
+            MinresUpdateKind::Minres(MinresUpdate {
+                c: T::one(),
+                s: T::zero(),
+                dbar: T::zero(),
+                eps: T::zero(),
+                phibar: T::from(beta1),
+                w1: Array1::zeros(n),
+                w2: Array1::zeros(n),
+                gmax: U::zero(),
+                gmin: U::infinity(),
+            })
+        };
+        MinresState {
+            x: x.to_owned(),
+            r2: r1.clone(),
+            r1,
+            y,
+            beta1,
+            oldb: U::zero(),
+            beta: beta1,
+            resid: beta1,
+            anorm: U::zero(),
+            arnorm: U::zero(),
+            acond: U::one(),
+            xnorm: norm(x),
+            tnorm2: U::zero(),
+            max_xnorm: U::from(1e7).unwrap() * beta1.max(U::one()),
+            niter: 0,
+            tol,
+            converged: beta1 < tol,
+            update,
+        }
+    }
+
+    /// Returns `None` if the iteration cannot continue: the Krylov space is
+    /// exhausted, or `M` is not positive definite.
+    pub fn next(
+        &mut self,
+        A: &dyn LinearOperator<T>,
+        M: Option<&dyn LinearOperator<T>>,
+    ) -> Option<()> {
+        if self.exhausted() || !self.beta.is_finite() {
+            return None;
+        }
+        minres_iter(self, A, M);
+        if self.valid() {
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    pub fn calc_resid(&self, A: &dyn LinearOperator<T>, b: &Array1<T>) -> Array1<T> {
+        b - &A.apply(self.x.view())
+    }
+
+    pub fn valid(&self) -> bool {
+        self.x.iter().all(|x| x.abs().is_finite()) && self.resid.is_finite()
+    }
+
+    /// The Lanczos process has found an invariant subspace; `x` is then a
+    /// least-squares solution (the minimum-length one for MINRES-QLP).
+    pub fn exhausted(&self) -> bool {
+        self.beta <= U::epsilon() * self.anorm
+    }
+
+    /// Checks that `x` solves the least-squares problem, i.e. that
+    /// `‖Â r̂‖ <= sqrt(eps) ‖Â‖ ‖r̂‖` for the preconditioned operator
+    /// `Â = M^(1/2) A M^(1/2)` and residual `r̂ = M^(1/2) (b - A x)`.
+    pub fn is_least_squares_solution(
+        &self,
+        A: &dyn LinearOperator<T>,
+        M: Option<&dyn LinearOperator<T>>,
+        b: &Array1<T>,
+    ) -> bool {
+        let r = self.calc_resid(A, b);
+        let z = match M {
+            Some(M) => M.apply(r.view()),
+            None => r.clone(),
+        };
+        let az = A.apply(z.view());
+        let maz = match M {
+            Some(M) => M.apply(az.view()),
+            None => az.clone(),
+        };
+        let arnorm = re::<T, U>(inner(az.view(), maz.view())).sqrt();
+        let rnorm = re::<T, U>(inner(r.view(), z.view())).sqrt();
+        arnorm <= U::epsilon().sqrt() * self.anorm * rnorm
+    }
+}
+
+/// One Lanczos step followed by the MINRES or MINRES-QLP update of `x`.
+pub fn minres_iter<T, U>(
+    ms: &mut MinresState<T, U>,
+    A: &dyn LinearOperator<T>,
+    M: Option<&dyn LinearOperator<T>>,
+) where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    // Lanczos: beta_k v_k = y, alpha_k = v_k^H A v_k, beta_{k+1}
+    let v = &ms.y / T::from(ms.beta);
+    let mut y = A.apply(v.view());
+    if ms.niter > 0 {
+        y = &y - &(&ms.r1 * T::from(ms.beta / ms.oldb));
+    }
+    let alfa = re::<T, U>(inner(v.view(), y.view()));
+    y = &y - &(&ms.r2 * T::from(alfa / ms.beta));
+    ms.r1 = std::mem::replace(&mut ms.r2, y);
+    ms.y = match M {
+        Some(M) => M.apply(ms.r2.view()),
+        None => ms.r2.clone(),
+    };
+    let betal = ms.oldb;
+    let rnorml = ms.resid;
+    ms.oldb = ms.beta;
+    ms.beta = re::<T, U>(inner(ms.r2.view(), ms.y.view())).sqrt();
+    ms.tnorm2 = ms.tnorm2 + alfa * alfa + ms.oldb * ms.oldb + ms.beta * ms.beta;
+    ms.niter += 1;
+
+    let (alfa_t, beta_t) = (T::from(alfa), T::from(ms.beta));
+    match ms.update {
+        MinresUpdateKind::Minres(ref mut mu) => {
+            // previous rotation on the current column and on the next one
+            let (delta, gbar) = apply_plane_rotation(mu.dbar, alfa_t, mu.c, mu.s);
+            let (eps_next, dbar_next) = apply_plane_rotation(T::zero(), beta_t, mu.c, mu.s);
+            // new rotation annihilating beta_{k+1}
+            let (c, s) = generate_plane_rotation(gbar, beta_t);
+            let gamma = apply_plane_rotation(gbar, beta_t, c, s).0;
+            let (phi, phibar) = apply_plane_rotation(mu.phibar, T::zero(), c, s);
+
+            let w = (&v - &(&mu.w2 * mu.eps) - &mu.w1 * delta) / gamma;
+            ms.x = &ms.x + &(&w * phi);
+            mu.w2 = std::mem::replace(&mut mu.w1, w);
+            mu.c = c;
+            mu.s = s;
+            mu.eps = eps_next;
+            mu.dbar = dbar_next;
+            mu.phibar = phibar;
+            mu.gmax = mu.gmax.max(gamma.abs());
+            mu.gmin = mu.gmin.min(gamma.abs());
+
+            ms.resid = phibar.abs();
+            ms.arnorm = rnorml * gbar.abs().hypot(dbar_next.abs());
+            ms.acond = mu.gmax / mu.gmin;
+            ms.xnorm = norm(ms.x.view());
+        }
+        MinresUpdateKind::Qlp(ref mut q) => {
+            let k = ms.niter;
+            let betan = ms.beta;
+            let pnorm = (betal * betal + alfa * alfa + betan * betan).sqrt();
+            ms.anorm = ms.anorm.max(pnorm);
+
+            // previous left reflection Q_{k-1,k}
+            let dbar = q.dltan;
+            let mut dlta = q.cs * dbar + q.sn * alfa;
+            let gbar = q.sn * dbar - q.cs * alfa;
+            ms.arnorm = rnorml * gbar.hypot(q.dltan);
+            q.eplnn = q.sn * betan;
+            q.dltan = -q.cs * betan;
+
+            // current left reflection Q_{k,k+1}
+            q.gamal2 = q.gamal;
+            q.gamal = q.gama;
+            let (cs, sn, gama) = sym_ortho(gbar, betan);
+            q.cs = cs;
+            q.sn = sn;
+            q.gama = gama;
+            q.taul2 = q.taul;
+            q.taul = q.tau;
+            q.tau = cs * q.phi;
+            q.phi = sn * q.phi;
+
+            // previous right reflection P_{k-2,k}
+            if k > 2 {
+                q.veplnl2 = q.veplnl;
+                q.etal2 = q.etal;
+                q.etal = q.eta;
+                let dlta_tmp = q.sr2 * q.vepln - q.cr2 * dlta;
+                q.veplnl = q.cr2 * q.vepln + q.sr2 * dlta;
+                dlta = dlta_tmp;
+                q.eta = q.sr2 * q.gama;
+                q.gama = -q.cr2 * q.gama;
+            }
+
+            // current right reflection P_{k-1,k}
+            if k > 1 {
+                let (cr1, sr1, gamal) = sym_ortho(q.gamal, dlta);
+                q.cr1 = cr1;
+                q.sr1 = sr1;
+                q.gamal = gamal;
+                q.vepln = sr1 * q.gama;
+                q.gama = -cr1 * q.gama;
+            }
+
+            // forward substitution for the solution coordinates
+            let ul4 = q.ul3;
+            q.ul3 = q.ul2;
+            if k > 2 {
+                q.ul2 = (q.taul2 - q.etal2 * ul4 - q.veplnl2 * q.ul3) / q.gamal2;
+            }
+            if k > 1 {
+                q.ul = (q.taul - q.etal * q.ul3 - q.veplnl * q.ul2) / q.gamal;
+            }
+            // a (numerically) zero gama means T_k is singular; the last
+            // coordinate is then dropped, which gives the minimum-length
+            // solution, and its equation is left in the residual
+            let rhs = q.tau - q.eta * q.ul2 - q.vepln * q.ul;
+            let xnorm_tmp = q.xl2norm.hypot(q.ul2).hypot(q.ul);
+            q.u = U::zero();
+            let mut resid = q.phi.abs();
+            if q.gama.abs() > U::min_positive_value() && xnorm_tmp < ms.max_xnorm {
+                let u = rhs / q.gama;
+                if xnorm_tmp.hypot(u) <= ms.max_xnorm {
+                    q.u = u;
+                } else {
+                    resid = resid.hypot(rhs);
+                }
+            } else {
+                resid = resid.hypot(rhs);
+            }
+            q.xl2norm = q.xl2norm.hypot(q.ul2);
+
+            // update W = V P and x = W u
+            let (cr1, sr1) = (T::from(q.cr1), T::from(q.sr1));
+            let (cr2, sr2) = (T::from(q.cr2), T::from(q.sr2));
+            let wl2 = if k == 1 {
+                let wl2 = std::mem::replace(&mut q.wl, &v * sr1);
+                q.w = &v * (-cr1);
+                wl2
+            } else if k == 2 {
+                let wl2 = q.wl.clone();
+                q.wl = &q.w * cr1 + &v * sr1;
+                q.w = &q.w * sr1 - &v * cr1;
+                wl2
+            } else {
+                let wl2 = q.wl.clone();
+                let wl = q.w.clone();
+                let w = &wl2 * sr2 - &v * cr2;
+                let wl2 = &wl2 * cr2 + &v * sr2;
+                q.wl = &wl * cr1 + &w * sr1;
+                q.w = &wl * sr1 - &w * cr1;
+                wl2
+            };
+            q.xl2 = &q.xl2 + &(&wl2 * T::from(q.ul2));
+            ms.x = &q.x0 + &q.xl2 + &q.wl * T::from(q.ul) + &q.w * T::from(q.u);
+
+            // next right reflection P_{k-1,k+1}
+            let (cr2, sr2, gamal) = sym_ortho(q.gamal, q.eplnn);
+            q.cr2 = cr2;
+            q.sr2 = sr2;
+            q.gamal = gamal;
+
+            ms.anorm = ms.anorm.max(q.gamal).max(q.gama.abs());
+            if k == 1 {
+                q.gmin = q.gama.abs();
+                q.gminl = q.gmin;
+            } else {
+                let gminl2 = q.gminl;
+                q.gminl = q.gmin;
+                q.gmin = gminl2.min(q.gamal).min(q.gama.abs());
+            }
+            ms.resid = resid;
+            ms.acond = ms.anorm / q.gmin;
+            ms.xnorm = (q.xl2norm * q.xl2norm + q.ul * q.ul + q.u * q.u).sqrt();
+        }
+    }
+    if let MinresUpdateKind::Minres(_) = ms.update {
+        ms.anorm = ms.tnorm2.sqrt();
+    }
+    ms.converged = ms.resid < ms.tol;
+}
+
+/// MINRES as an `IterativeSolver`; set `qlp` for MINRES-QLP, which returns
+/// the minimum-length solution of singular (possibly incompatible) systems.
+/// Plain MINRES residual estimates are not reliable on singular incompatible
+/// systems.
+///
+/// Residual tests see the `M`-norm residual estimate. The criterion also gets
+/// `LsqEstimates`, so `with_paige_saunders` stops singular incompatible
+/// systems at a least-squares solution.
+pub struct Minres<'a, T> {
+    pub M: Option<&'a dyn LinearOperator<T>>,
+    pub qlp: bool,
+}
+
+impl<'a, T> Minres<'a, T> {
+    pub fn new() -> Minres<'a, T> {
+        Minres {
+            M: None,
+            qlp: false,
+        }
+    }
+
+    pub fn qlp() -> Minres<'a, T> {
+        Minres { M: None, qlp: true }
+    }
+
+    pub fn with_preconditioner(mut self, M: &'a dyn LinearOperator<T>) -> Minres<'a, T> {
+        self.M = Some(M);
+        self
+    }
+}
+
+impl<'a, T> Default for Minres<'a, T> {
+    fn default() -> Minres<'a, T> {
+        Minres::new()
+    }
+}
+
+impl<'a, T, U> IterativeSolver<T, U> for Minres<'a, T>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    type State = MinresState<T, U>;
+
+    fn solve_with_monitor(
+        &self,
+        A: &dyn LinearOperator<T>,
+        b: ArrayView1<T>,
+        x0: ArrayView1<T>,
+        criterion: &StoppingCriterion<U>,
+        monitor: &mut dyn Monitor<MinresState<T, U>, U>,
+    ) -> SolveReport<T, U> {
+        let start = Instant::now();
+        let b = b.to_owned();
+        let b_norm = match self.M {
+            Some(M) => re::<T, U>(inner(b.view(), M.apply(b.view()).view())).sqrt(),
+            None => norm(b.view()),
+        };
+        let mut state = MinresState::new(A, x0, b.view(), self.M, U::zero(), self.qlp);
+        let r0_norm = state.beta1;
+        let mut history = vec![r0_norm];
+        let mut iterations = 0;
+        let reason = loop {
+            let mut info = IterInfo::new(iterations, state.resid, r0_norm, b_norm, start.elapsed());
+            if iterations > 0 {
+                info.lsq = Some(LsqEstimates {
+                    arnorm: state.arnorm,
+                    anorm: state.anorm,
+                    acond: state.acond,
+                    xnorm: state.xnorm,
+                });
+            }
+            if let Some(reason) = criterion.check(&info) {
+                break reason;
+            }
+            if state.next(A, self.M).is_none() {
+                break StopReason::Breakdown;
+            }
+            iterations += 1;
+            history.push(state.resid);
+            if monitor.observe(iterations, state.resid, &state) == MonitorAction::Abort {
+                break StopReason::Aborted;
+            }
+        };
+        let resid_norm = norm(state.calc_resid(A, &b).view());
+        let reason = match reason {
+            StopReason::Breakdown => {
+                match criterion.breakdown_reason(resid_norm, r0_norm, b_norm) {
+                    StopReason::Breakdown
+                        if state.exhausted() && state.is_least_squares_solution(A, self.M, &b) =>
+                    {
+                        StopReason::LeastSquaresSolution
+                    }
+                    reason => reason,
+                }
+            }
+            _ => reason,
+        };
+        SolveReport {
+            x: state.x,
+            iterations,
+            resid_norm,
+            reason,
+            history,
+        }
+    }
+}
@@ -1,6 +1,15 @@
 pub mod agmres;
+pub mod fgmres;
+pub mod gcrodr;
 pub mod gmres;
+pub mod gmresdr;
+#[allow(clippy::module_inception)]
+pub mod minres;
 pub mod utils;
 
-pub use agmres::{agmres, agmres1, AGmresState};
-pub use gmres::{gmres1, GmresState};
+pub use agmres::{agmres, agmres1, AGmres, AGmresState};
+pub use fgmres::{fgmres1, Fgmres, FgmresState, FlexiblePreconditioner, SolverPreconditioner};
+pub use gcrodr::{gcrodr1, GcroDr, GcroDrState, RecycleSpace};
+pub use gmres::{gmres1, Gmres, GmresState, Preconditioning};
+pub use gmresdr::{gmresdr1, harmonic_ritz, smallest_eigenspace, GmresDr, GmresDrState};
+pub use minres::{minres_iter, Minres, MinresState};
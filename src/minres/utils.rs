@@ -40,7 +40,7 @@ where
         (T::one(), T::zero())
     } else {
         //let temp=HasSqrt::sqrt(&(<T as From<U>>::from(dx.abs().powi(2)+dy.abs().powi(2))));
-        let temp = <T as From<U>>::from((dx.abs().powi(2) + dx.abs().powi(2)).sqrt());
+        let temp = <T as From<U>>::from((dx.abs().powi(2) + dy.abs().powi(2)).sqrt());
         let s = dy / temp;
         let c = dx / temp;
         (c, s)
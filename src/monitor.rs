@@ -0,0 +1,137 @@
+/// What a monitor asks the solver to do after an iteration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MonitorAction {
+    Continue,
+    Abort,
+}
+
+/// Per-iteration hook called by `IterativeSolver::solve_with_monitor` with
+/// the iteration index, the current residual estimate and the solver state.
+/// The GMRES variants call it after every Arnoldi step with the Givens
+/// estimate of the residual; the state's `x` is only updated at the end of
+/// each restart cycle.
+pub trait Monitor<S: ?Sized, U> {
+    fn observe(&mut self, iter: usize, resid: U, state: &S) -> MonitorAction;
+}
+
+/// Does nothing; the default monitor of `IterativeSolver::solve`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SilentMonitor;
+
+impl<S: ?Sized, U> Monitor<S, U> for SilentMonitor {
+    fn observe(&mut self, _iter: usize, _resid: U, _state: &S) -> MonitorAction {
+        MonitorAction::Continue
+    }
+}
+
+/// Prints an `iter  resid` table to stderr, every `every` iterations.
+#[derive(Debug, Clone, Copy)]
+pub struct StderrMonitor {
+    pub every: usize,
+    header_printed: bool,
+}
+
+impl StderrMonitor {
+    pub fn new() -> StderrMonitor {
+        StderrMonitor {
+            every: 1,
+            header_printed: false,
+        }
+    }
+
+    pub fn every(mut self, every: usize) -> StderrMonitor {
+        self.every = every.max(1);
+        self
+    }
+}
+
+impl Default for StderrMonitor {
+    fn default() -> StderrMonitor {
+        StderrMonitor::new()
+    }
+}
+
+impl<S: ?Sized, U> Monitor<S, U> for StderrMonitor
+where
+    U: std::fmt::LowerExp,
+{
+    fn observe(&mut self, iter: usize, resid: U, _state: &S) -> MonitorAction {
+        if !self.header_printed {
+            eprintln!("{:>8} {:>14}", "iter", "resid");
+            self.header_printed = true;
+        }
+        // `usize::is_multiple_of` needs Rust 1.87
+        #[allow(clippy::manual_is_multiple_of)]
+        if iter % self.every == 0 {
+            eprintln!("{:>8} {:>14.6e}", iter, resid);
+        }
+        MonitorAction::Continue
+    }
+}
+
+/// Records `(iter, resid)` of every call.
+#[derive(Debug, Clone, Default)]
+pub struct HistoryMonitor<U> {
+    pub history: Vec<(usize, U)>,
+}
+
+impl<U> HistoryMonitor<U> {
+    pub fn new() -> HistoryMonitor<U> {
+        HistoryMonitor {
+            history: Vec::new(),
+        }
+    }
+}
+
+impl<S: ?Sized, U> Monitor<S, U> for HistoryMonitor<U>
+where
+    U: Copy,
+{
+    fn observe(&mut self, iter: usize, resid: U, _state: &S) -> MonitorAction {
+        self.history.push((iter, resid));
+        MonitorAction::Continue
+    }
+}
+
+/// Aborts the solve as soon as `pred(iter, resid)` returns true, e.g. on
+/// divergence or stagnation detected by the caller.
+pub struct AbortMonitor<F> {
+    pub pred: F,
+}
+
+impl<F> AbortMonitor<F> {
+    pub fn new(pred: F) -> AbortMonitor<F> {
+        AbortMonitor { pred }
+    }
+}
+
+impl<S: ?Sized, U, F> Monitor<S, U> for AbortMonitor<F>
+where
+    F: FnMut(usize, U) -> bool,
+{
+    fn observe(&mut self, iter: usize, resid: U, _state: &S) -> MonitorAction {
+        if (self.pred)(iter, resid) {
+            MonitorAction::Abort
+        } else {
+            MonitorAction::Continue
+        }
+    }
+}
+
+/// Two monitors run one after the other; either may abort.
+impl<S: ?Sized, U, M1, M2> Monitor<S, U> for (M1, M2)
+where
+    U: Copy,
+    M1: Monitor<S, U>,
+    M2: Monitor<S, U>,
+{
+    fn observe(&mut self, iter: usize, resid: U, state: &S) -> MonitorAction {
+        let a1 = self.0.observe(iter, resid, state);
+        let a2 = self.1.observe(iter, resid, state);
+        if a1 == MonitorAction::Abort || a2 == MonitorAction::Abort {
+            MonitorAction::Abort
+        } else {
+            MonitorAction::Continue
+        }
+    }
+}
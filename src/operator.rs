@@ -0,0 +1,322 @@
+#![allow(non_snake_case)]
+
+use crate::utils::{sp_mul_a2, HasConj};
+use ndarray::{
+    Array1, Array2, ArrayBase, ArrayView1, ArrayView2, ArrayViewMut1, Axis, Data, Ix2, LinalgScalar,
+};
+use num_traits::{Num, Zero};
+use sprs::SpIndex;
+
+/// A linear map `A: T^ncols -> T^nrows`, the common argument of all solvers.
+pub trait LinearOperator<T> {
+    /// `(nrows, ncols)` of the operator
+    fn shape(&self) -> (usize, usize);
+
+    fn nrows(&self) -> usize {
+        self.shape().0
+    }
+
+    fn ncols(&self) -> usize {
+        self.shape().1
+    }
+
+    /// y = A x
+    fn apply(&self, x: ArrayView1<T>) -> Array1<T>;
+
+    /// y = A^H x
+    fn apply_adjoint(&self, x: ArrayView1<T>) -> Array1<T>;
+
+    /// whether `apply_adjoint` is available
+    fn has_adjoint(&self) -> bool {
+        true
+    }
+
+    /// y = A x, written into an existing buffer
+    fn apply_into(&self, x: ArrayView1<T>, mut y: ArrayViewMut1<T>)
+    where
+        T: Clone,
+    {
+        y.assign(&self.apply(x));
+    }
+
+    /// Y = A X for a block of vectors; block solvers call this once per
+    /// step, so operators that can batch the columns should override it
+    fn apply_block(&self, X: ArrayView2<T>) -> Array2<T>
+    where
+        T: Clone + Zero,
+    {
+        let mut Y = Array2::zeros((self.nrows(), X.ncols()));
+        for (x, mut y) in X.axis_iter(Axis(1)).zip(Y.axis_iter_mut(Axis(1))) {
+            y.assign(&self.apply(x));
+        }
+        Y
+    }
+}
+
+impl<T, O> LinearOperator<T> for &O
+where
+    O: LinearOperator<T> + ?Sized,
+{
+    fn shape(&self) -> (usize, usize) {
+        (**self).shape()
+    }
+
+    fn apply(&self, x: ArrayView1<T>) -> Array1<T> {
+        (**self).apply(x)
+    }
+
+    fn apply_adjoint(&self, x: ArrayView1<T>) -> Array1<T> {
+        (**self).apply_adjoint(x)
+    }
+
+    fn has_adjoint(&self) -> bool {
+        (**self).has_adjoint()
+    }
+
+    fn apply_into(&self, x: ArrayView1<T>, y: ArrayViewMut1<T>)
+    where
+        T: Clone,
+    {
+        (**self).apply_into(x, y)
+    }
+
+    fn apply_block(&self, X: ArrayView2<T>) -> Array2<T>
+    where
+        T: Clone + Zero,
+    {
+        (**self).apply_block(X)
+    }
+}
+
+impl<T, I, IptrStorage, IndStorage, DataStorage> LinearOperator<T>
+    for sprs::CsMatBase<T, I, IptrStorage, IndStorage, DataStorage>
+where
+    T: Num + Copy + HasConj,
+    I: SpIndex,
+    IptrStorage: std::ops::Deref<Target = [I]>,
+    IndStorage: std::ops::Deref<Target = [I]>,
+    DataStorage: std::ops::Deref<Target = [T]>,
+{
+    fn shape(&self) -> (usize, usize) {
+        (self.rows(), self.cols())
+    }
+
+    fn apply(&self, x: ArrayView1<T>) -> Array1<T> {
+        assert_eq!(self.cols(), x.len());
+        let mut result = Array1::zeros(self.rows());
+        for (&a, (i, j)) in self.iter() {
+            let (i, j) = (i.index(), j.index());
+            result[i] = result[i] + a * x[j];
+        }
+        result
+    }
+
+    fn apply_adjoint(&self, x: ArrayView1<T>) -> Array1<T> {
+        assert_eq!(self.rows(), x.len());
+        let mut result = Array1::zeros(self.cols());
+        for (&a, (i, j)) in self.iter() {
+            let (i, j) = (i.index(), j.index());
+            result[j] = result[j] + a.conj() * x[i];
+        }
+        result
+    }
+
+    fn apply_into(&self, x: ArrayView1<T>, mut y: ArrayViewMut1<T>) {
+        assert_eq!(self.cols(), x.len());
+        assert_eq!(self.rows(), y.len());
+        y.fill(T::zero());
+        for (&a, (i, j)) in self.iter() {
+            let (i, j) = (i.index(), j.index());
+            y[i] = y[i] + a * x[j];
+        }
+    }
+
+    fn apply_block(&self, X: ArrayView2<T>) -> Array2<T> {
+        assert_eq!(self.cols(), X.nrows());
+        sp_mul_a2(self, X)
+    }
+}
+
+impl<T, S> LinearOperator<T> for ArrayBase<S, Ix2>
+where
+    T: LinalgScalar + HasConj,
+    S: Data<Elem = T>,
+{
+    fn shape(&self) -> (usize, usize) {
+        self.dim()
+    }
+
+    fn apply(&self, x: ArrayView1<T>) -> Array1<T> {
+        assert_eq!(self.ncols(), x.len());
+        self.dot(&x)
+    }
+
+    fn apply_adjoint(&self, x: ArrayView1<T>) -> Array1<T> {
+        assert_eq!(self.nrows(), x.len());
+        let xc = x.map(|x| x.conj());
+        self.t().dot(&xc).map(|x| x.conj())
+    }
+
+    fn apply_block(&self, X: ArrayView2<T>) -> Array2<T> {
+        assert_eq!(self.ncols(), X.nrows());
+        self.dot(&X)
+    }
+}
+
+type BoxedFn<'a, T> = Box<dyn Fn(ArrayView1<T>) -> Array1<T> + 'a>;
+
+/// A matrix-free operator defined by closures.
+///
+/// The adjoint is optional; solvers that need it (e.g. LSQR) check
+/// `has_adjoint` before starting.
+pub struct FnOperator<'a, T> {
+    pub shape: (usize, usize),
+    pub f: BoxedFn<'a, T>,
+    pub fh: Option<BoxedFn<'a, T>>,
+}
+
+impl<'a, T> FnOperator<'a, T> {
+    pub fn new<F>(shape: (usize, usize), f: F) -> FnOperator<'a, T>
+    where
+        F: Fn(ArrayView1<T>) -> Array1<T> + 'a,
+    {
+        FnOperator {
+            shape,
+            f: Box::new(f),
+            fh: None,
+        }
+    }
+
+    /// square operator of size `n`
+    pub fn square<F>(n: usize, f: F) -> FnOperator<'a, T>
+    where
+        F: Fn(ArrayView1<T>) -> Array1<T> + 'a,
+    {
+        Self::new((n, n), f)
+    }
+
+    pub fn with_adjoint<G>(mut self, fh: G) -> FnOperator<'a, T>
+    where
+        G: Fn(ArrayView1<T>) -> Array1<T> + 'a,
+    {
+        self.fh = Some(Box::new(fh));
+        self
+    }
+}
+
+impl<'a, T> LinearOperator<T> for FnOperator<'a, T> {
+    fn shape(&self) -> (usize, usize) {
+        self.shape
+    }
+
+    fn apply(&self, x: ArrayView1<T>) -> Array1<T> {
+        (self.f)(x)
+    }
+
+    fn apply_adjoint(&self, x: ArrayView1<T>) -> Array1<T> {
+        match self.fh {
+            Some(ref fh) => fh(x),
+            None => panic!("adjoint of this FnOperator is not defined"),
+        }
+    }
+
+    fn has_adjoint(&self) -> bool {
+        self.fh.is_some()
+    }
+}
+
+/// The identity map of size `n`, i.e., no preconditioning.
+#[derive(Debug, Clone, Copy)]
+pub struct IdentityOperator {
+    pub n: usize,
+}
+
+impl IdentityOperator {
+    pub fn new(n: usize) -> IdentityOperator {
+        IdentityOperator { n }
+    }
+}
+
+impl<T> LinearOperator<T> for IdentityOperator
+where
+    T: Clone,
+{
+    fn shape(&self) -> (usize, usize) {
+        (self.n, self.n)
+    }
+
+    fn apply(&self, x: ArrayView1<T>) -> Array1<T> {
+        x.to_owned()
+    }
+
+    fn apply_adjoint(&self, x: ArrayView1<T>) -> Array1<T> {
+        x.to_owned()
+    }
+}
+
+/// `A^H A` or `A A^H` of an operator with an adjoint, e.g. to run CG on the
+/// normal equations. Both are Hermitian, so the adjoint is the map itself.
+pub struct NormalOperator<'a, T> {
+    pub A: &'a dyn LinearOperator<T>,
+    /// `A A^H` if set, `A^H A` otherwise
+    pub outer: bool,
+}
+
+impl<'a, T> NormalOperator<'a, T> {
+    /// `A^H A`, of size `ncols`
+    pub fn ata(A: &'a dyn LinearOperator<T>) -> NormalOperator<'a, T> {
+        check_adjoint(A, "A^H A");
+        NormalOperator { A, outer: false }
+    }
+
+    /// `A A^H`, of size `nrows`
+    pub fn aat(A: &'a dyn LinearOperator<T>) -> NormalOperator<'a, T> {
+        check_adjoint(A, "A A^H");
+        NormalOperator { A, outer: true }
+    }
+}
+
+impl<'a, T> LinearOperator<T> for NormalOperator<'a, T> {
+    fn shape(&self) -> (usize, usize) {
+        let n = if self.outer {
+            self.A.nrows()
+        } else {
+            self.A.ncols()
+        };
+        (n, n)
+    }
+
+    fn apply(&self, x: ArrayView1<T>) -> Array1<T> {
+        if self.outer {
+            self.A.apply(self.A.apply_adjoint(x).view())
+        } else {
+            self.A.apply_adjoint(self.A.apply(x).view())
+        }
+    }
+
+    fn apply_adjoint(&self, x: ArrayView1<T>) -> Array1<T> {
+        self.apply(x)
+    }
+}
+
+/// Check that `A` provides `apply_adjoint`, which `method` needs.
+pub fn check_adjoint<T>(A: &dyn LinearOperator<T>, method: &str) {
+    assert!(
+        A.has_adjoint(),
+        "{} requires the adjoint of the operator",
+        method
+    );
+}
+
+/// Check that `A` maps `T^n` into `T^m`, panicking with a readable message otherwise.
+pub fn check_shape<T>(A: &dyn LinearOperator<T>, m: usize, n: usize) {
+    let (nrows, ncols) = A.shape();
+    assert!(
+        nrows == m && ncols == n,
+        "operator shape ({}, {}) does not match the expected ({}, {})",
+        nrows,
+        ncols,
+        m,
+        n
+    );
+}
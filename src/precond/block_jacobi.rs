@@ -0,0 +1,134 @@
+use super::{square_csr, PrecondErr, SetupInfo};
+use crate::operator::LinearOperator;
+use crate::utils::ComplexOrReal;
+use ndarray::{s, Array1, Array2, ArrayView1};
+use num_traits::Float;
+use sprs::CsMat;
+use std::marker::PhantomData;
+use std::time::Instant;
+
+/// Block Jacobi: the diagonal blocks of size `block_size` (the last one may
+/// be smaller) are inverted densely.
+pub struct BlockJacobi<T, U> {
+    pub block_size: usize,
+    /// inverses of the diagonal blocks
+    pub inv: Vec<Array2<T>>,
+    pub info: SetupInfo,
+    pub phantom: PhantomData<U>,
+}
+
+/// Gauss-Jordan inversion with partial pivoting; `None` if `a` is singular.
+/// Adds the operation count to `flops`.
+fn invert<T, U>(mut a: Array2<T>, flops: &mut usize) -> Option<Array2<T>>
+where
+    T: ComplexOrReal<U>,
+    U: Float,
+{
+    let n = a.nrows();
+    let mut inv = Array2::<T>::eye(n);
+    for k in 0..n {
+        let p = (k..n)
+            .max_by(|&i, &j| a[(i, k)].abs().partial_cmp(&a[(j, k)].abs()).unwrap())
+            .unwrap();
+        if a[(p, k)].abs() == U::zero() || !a[(p, k)].abs().is_finite() {
+            return None;
+        }
+        for j in 0..n {
+            a.swap((k, j), (p, j));
+            inv.swap((k, j), (p, j));
+        }
+        let piv = T::one() / a[(k, k)];
+        for j in 0..n {
+            a[(k, j)] = a[(k, j)] * piv;
+            inv[(k, j)] = inv[(k, j)] * piv;
+        }
+        for i in 0..n {
+            if i != k && a[(i, k)] != T::zero() {
+                let f = a[(i, k)];
+                for j in 0..n {
+                    a[(i, j)] = a[(i, j)] - f * a[(k, j)];
+                    inv[(i, j)] = inv[(i, j)] - f * inv[(k, j)];
+                }
+                *flops += 4 * n;
+            }
+        }
+        *flops += 2 * n;
+    }
+    Some(inv)
+}
+
+impl<T, U> BlockJacobi<T, U>
+where
+    T: ComplexOrReal<U>,
+    U: Float,
+{
+    /// Fails with `SingularBlock` if a diagonal block is singular.
+    pub fn new(a: &CsMat<T>, block_size: usize) -> Result<BlockJacobi<T, U>, PrecondErr> {
+        if block_size == 0 {
+            return Err(PrecondErr::InvalidParameter("block_size must be positive"));
+        }
+        let start = Instant::now();
+        let a = square_csr(a)?;
+        let n = a.rows();
+        let (indptr, indices, data) = (a.indptr(), a.indices(), a.data());
+        let mut flops = 0;
+        let mut inv = Vec::new();
+        for (k, i0) in (0..n).step_by(block_size).enumerate() {
+            let i1 = (i0 + block_size).min(n);
+            let mut block = Array2::<T>::zeros((i1 - i0, i1 - i0));
+            for i in i0..i1 {
+                for p in indptr[i]..indptr[i + 1] {
+                    let j = indices[p];
+                    if j >= i0 && j < i1 {
+                        block[(i - i0, j - i0)] = data[p];
+                    }
+                }
+            }
+            inv.push(invert(block, &mut flops).ok_or(PrecondErr::SingularBlock(k))?);
+        }
+        let info = SetupInfo {
+            nnz: inv.iter().map(|b| b.len()).sum(),
+            flops,
+            time: start.elapsed(),
+        };
+        Ok(BlockJacobi {
+            block_size,
+            inv,
+            info,
+            phantom: PhantomData,
+        })
+    }
+}
+
+impl<T, U> LinearOperator<T> for BlockJacobi<T, U>
+where
+    T: ComplexOrReal<U>,
+    U: Float,
+{
+    fn shape(&self) -> (usize, usize) {
+        let n = self.inv.iter().map(|b| b.nrows()).sum();
+        (n, n)
+    }
+
+    fn apply(&self, x: ArrayView1<T>) -> Array1<T> {
+        let mut y = Array1::<T>::zeros(x.len());
+        for (k, b) in self.inv.iter().enumerate() {
+            let i0 = k * self.block_size;
+            let i1 = i0 + b.nrows();
+            y.slice_mut(s![i0..i1]).assign(&b.dot(&x.slice(s![i0..i1])));
+        }
+        y
+    }
+
+    fn apply_adjoint(&self, x: ArrayView1<T>) -> Array1<T> {
+        let mut y = Array1::<T>::zeros(x.len());
+        for (k, b) in self.inv.iter().enumerate() {
+            let i0 = k * self.block_size;
+            let i1 = i0 + b.nrows();
+            let xc = x.slice(s![i0..i1]).map(|x| x.conj());
+            y.slice_mut(s![i0..i1])
+                .assign(&b.t().dot(&xc).map(|x| x.conj()));
+        }
+        y
+    }
+}
@@ -0,0 +1,115 @@
+use super::{lower_adjoint_solve, lower_solve, square_csr, PrecondErr, SetupInfo};
+use crate::operator::LinearOperator;
+use crate::utils::ComplexOrReal;
+use ndarray::{Array1, ArrayView1};
+use num_complex::Complex;
+use num_traits::Float;
+use sprs::CsMat;
+use std::marker::PhantomData;
+use std::time::Instant;
+
+/// Zero fill-in incomplete Cholesky, `M = L L^H` with `L` restricted to the
+/// lower triangle of the sparsity pattern of the Hermitian `A`. Only the
+/// lower triangle of `A` is read.
+pub struct Ic0<T, U> {
+    /// strictly lower part of `L`
+    pub l: CsMat<T>,
+    /// diagonal of `L` (real, positive)
+    pub diag: Vec<T>,
+    pub info: SetupInfo,
+    pub phantom: PhantomData<U>,
+}
+
+impl<T, U> Ic0<T, U>
+where
+    T: ComplexOrReal<U>,
+    U: Float,
+{
+    /// Fails with `NotPositiveDefinite` when a pivot is not positive. This
+    /// can happen for Hermitian positive definite `A` too, unless it is e.g.
+    /// an M-matrix; a diagonal shift `A + αI` is the usual remedy.
+    pub fn new(a: &CsMat<T>) -> Result<Ic0<T, U>, PrecondErr> {
+        let start = Instant::now();
+        let a = square_csr(a)?;
+        let n = a.rows();
+        let mut indptr = vec![0];
+        let mut indices = Vec::new();
+        let mut data = Vec::new();
+        let mut diag = Vec::with_capacity(n);
+        let mut flops = 0;
+        // value of L[i, j] in the current row i, if in the pattern
+        let mut row = vec![None; n];
+        for i in 0..n {
+            let start_i = indices.len();
+            let mut aii = U::zero();
+            for (j, &v) in a.outer_view(i).unwrap().iter() {
+                if j < i {
+                    indices.push(j);
+                    data.push(v);
+                } else if j == i {
+                    aii = Into::<Complex<U>>::into(v).re;
+                }
+            }
+            for p in start_i..indices.len() {
+                row[indices[p]] = Some(p);
+            }
+            // L[i, k] = (A[i, k] - sum_{j<k} L[i, j] conj(L[k, j])) / L[k, k]
+            for p in start_i..indices.len() {
+                let k = indices[p];
+                let mut s = data[p];
+                for q in indptr[k]..indptr[k + 1] {
+                    if let Some(r) = row[indices[q]] {
+                        s = s - data[r] * data[q].conj();
+                        flops += 2;
+                    }
+                }
+                data[p] = s / diag[k];
+                flops += 1;
+            }
+            let mut d = aii;
+            for &v in &data[start_i..] {
+                d = d - v.abs() * v.abs();
+                flops += 2;
+            }
+            if !(d > U::zero() && d.is_finite()) {
+                return Err(PrecondErr::NotPositiveDefinite(i));
+            }
+            diag.push(T::from(d.sqrt()));
+            for &j in &indices[start_i..] {
+                row[j] = None;
+            }
+            indptr.push(indices.len());
+        }
+        let l = CsMat::new((n, n), indptr, indices, data);
+        let info = SetupInfo {
+            nnz: l.nnz() + n,
+            flops,
+            time: start.elapsed(),
+        };
+        Ok(Ic0 {
+            l,
+            diag,
+            info,
+            phantom: PhantomData,
+        })
+    }
+}
+
+impl<T, U> LinearOperator<T> for Ic0<T, U>
+where
+    T: ComplexOrReal<U>,
+    U: Float,
+{
+    fn shape(&self) -> (usize, usize) {
+        (self.diag.len(), self.diag.len())
+    }
+
+    fn apply(&self, x: ArrayView1<T>) -> Array1<T> {
+        let y = lower_solve(&self.l, Some(&self.diag), x);
+        lower_adjoint_solve(&self.l, Some(&self.diag), y.view())
+    }
+
+    fn apply_adjoint(&self, x: ArrayView1<T>) -> Array1<T> {
+        self.apply(x)
+    }
+}
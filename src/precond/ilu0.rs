@@ -0,0 +1,107 @@
+use super::{
+    lower_adjoint_solve, lower_solve, square_csr, upper_adjoint_solve, upper_solve, PrecondErr,
+    SetupInfo,
+};
+use crate::operator::LinearOperator;
+use crate::utils::ComplexOrReal;
+use ndarray::{Array1, ArrayView1};
+use num_traits::Float;
+use sprs::CsMat;
+use std::marker::PhantomData;
+use std::time::Instant;
+
+/// Zero fill-in incomplete LU, `M = L U` with `L` unit lower triangular and
+/// `L + U` restricted to the sparsity pattern of `A`.
+pub struct Ilu0<T, U> {
+    /// strictly lower part holds `L`, the rest `U`
+    pub lu: CsMat<T>,
+    /// diagonal of `U`
+    pub diag: Vec<T>,
+    pub info: SetupInfo,
+    pub phantom: PhantomData<U>,
+}
+
+impl<T, U> Ilu0<T, U>
+where
+    T: ComplexOrReal<U>,
+    U: Float,
+{
+    /// Fails with `ZeroPivot` on a missing diagonal entry or when a pivot
+    /// vanishes during the factorization; reordering `A` or a diagonal shift
+    /// usually helps.
+    pub fn new(a: &CsMat<T>) -> Result<Ilu0<T, U>, PrecondErr> {
+        let start = Instant::now();
+        let mut lu = square_csr(a)?;
+        let n = lu.rows();
+        let indptr = lu.indptr().to_vec();
+        let indices = lu.indices().to_vec();
+        let diag_pos: Vec<Option<usize>> = (0..n)
+            .map(|i| (indptr[i]..indptr[i + 1]).find(|&k| indices[k] == i))
+            .collect();
+        let data = lu.data_mut();
+        let mut flops = 0;
+        // position of column j in the current row, if present
+        let mut pos = vec![None; n];
+        for i in 0..n {
+            for k in indptr[i]..indptr[i + 1] {
+                pos[indices[k]] = Some(k);
+            }
+            for kk in indptr[i]..indptr[i + 1] {
+                let k = indices[kk];
+                if k >= i {
+                    break;
+                }
+                // rows before i have been checked for a pivot already
+                let dk = diag_pos[k].unwrap();
+                let lik = data[kk] / data[dk];
+                data[kk] = lik;
+                flops += 1;
+                for jj in dk + 1..indptr[k + 1] {
+                    if let Some(p) = pos[indices[jj]] {
+                        data[p] = data[p] - lik * data[jj];
+                        flops += 2;
+                    }
+                }
+            }
+            for k in indptr[i]..indptr[i + 1] {
+                pos[indices[k]] = None;
+            }
+            match diag_pos[i] {
+                Some(d) if data[d].abs() > U::zero() && data[d].abs().is_finite() => {}
+                _ => return Err(PrecondErr::ZeroPivot(i)),
+            }
+        }
+        let diag = diag_pos.iter().map(|&d| data[d.unwrap()]).collect();
+        let info = SetupInfo {
+            nnz: lu.nnz(),
+            flops,
+            time: start.elapsed(),
+        };
+        Ok(Ilu0 {
+            lu,
+            diag,
+            info,
+            phantom: PhantomData,
+        })
+    }
+}
+
+impl<T, U> LinearOperator<T> for Ilu0<T, U>
+where
+    T: ComplexOrReal<U>,
+    U: Float,
+{
+    fn shape(&self) -> (usize, usize) {
+        (self.diag.len(), self.diag.len())
+    }
+
+    fn apply(&self, x: ArrayView1<T>) -> Array1<T> {
+        let y = lower_solve(&self.lu, None, x);
+        upper_solve(&self.lu, Some(&self.diag), y.view())
+    }
+
+    fn apply_adjoint(&self, x: ArrayView1<T>) -> Array1<T> {
+        let y = upper_adjoint_solve(&self.lu, Some(&self.diag), x);
+        lower_adjoint_solve(&self.lu, None, y.view())
+    }
+}
@@ -0,0 +1,295 @@
+use super::{
+    lower_adjoint_solve, lower_solve, square_csr, upper_adjoint_solve, upper_solve, PrecondErr,
+    SetupInfo,
+};
+use crate::operator::LinearOperator;
+use crate::utils::ComplexOrReal;
+use ndarray::{Array1, ArrayView1};
+use num_traits::Float;
+use sprs::CsMat;
+use std::collections::BTreeSet;
+use std::marker::PhantomData;
+use std::time::Instant;
+
+/// Parameters of `Ilut`.
+#[derive(Debug, Clone, Copy)]
+pub struct IlutParams<U> {
+    /// `p`: at most this many entries are kept in each row of `L` and in each
+    /// row of `U`, not counting the diagonal
+    pub fill: usize,
+    /// `τ`: entries smaller than `τ` times the 2-norm of the row of `A` are dropped
+    pub drop_tol: U,
+    /// columns `i` and `j` are swapped at step `i` when
+    /// `perm_tol |u_ij| > |u_ii|`; zero disables pivoting
+    pub perm_tol: U,
+}
+
+impl<U> IlutParams<U>
+where
+    U: Float,
+{
+    pub fn new(fill: usize, drop_tol: U) -> IlutParams<U> {
+        IlutParams {
+            fill,
+            drop_tol,
+            perm_tol: U::zero(),
+        }
+    }
+
+    /// ILUTP: column pivoting with the given `perm_tol` in `[0, 1]`,
+    /// typically 0.01 to 0.5.
+    pub fn with_pivoting(mut self, perm_tol: U) -> IlutParams<U> {
+        self.perm_tol = perm_tol;
+        self
+    }
+}
+
+/// Saad's threshold incomplete LU, ILUT(p, τ), with optional column pivoting
+/// (ILUTP). Factorizes `A Q = L U` with `L` unit lower triangular and `Q` the
+/// column permutation (identity without pivoting).
+pub struct Ilut<T, U> {
+    /// strictly lower part of `L`
+    pub l: CsMat<T>,
+    /// strictly upper part of `U`
+    pub u: CsMat<T>,
+    /// diagonal of `U`
+    pub diag: Vec<T>,
+    /// column `j` of `A Q` is column `perm[j]` of `A`
+    pub perm: Vec<usize>,
+    /// rows whose pivot vanished and was replaced by `(1e-4 + τ) ‖a_i‖`
+    pub zero_pivots: Vec<usize>,
+    /// `nnz(L + U) / nnz(A)`
+    pub fill_ratio: f64,
+    pub info: SetupInfo,
+    pub phantom: PhantomData<U>,
+}
+
+/// Keeps the `p` entries of largest magnitude, sorted by column. The entries
+/// must be finite.
+fn keep_largest<T, U>(row: &mut Vec<(usize, T)>, p: usize)
+where
+    T: ComplexOrReal<U>,
+    U: Float,
+{
+    if row.len() > p {
+        row.sort_by(|x, y| y.1.abs().partial_cmp(&x.1.abs()).unwrap());
+        row.truncate(p);
+    }
+    row.sort_by_key(|x| x.0);
+}
+
+/// CSR matrix from rows of `(column, value)` pairs sorted by column.
+fn rows_to_csr<T>(n: usize, rows: Vec<Vec<(usize, T)>>) -> CsMat<T>
+where
+    T: Copy,
+{
+    let mut indptr = vec![0];
+    let mut indices = Vec::new();
+    let mut data = Vec::new();
+    for row in rows {
+        for (j, v) in row {
+            indices.push(j);
+            data.push(v);
+        }
+        indptr.push(indices.len());
+    }
+    CsMat::new((n, n), indptr, indices, data)
+}
+
+impl<T, U> Ilut<T, U>
+where
+    T: ComplexOrReal<U>,
+    U: Float,
+{
+    /// Fails with `ZeroRow` if a row of `A` is empty. A pivot that vanishes
+    /// during the factorization is replaced and reported in `zero_pivots`
+    /// instead; many of those mean `M` is a poor preconditioner, and a
+    /// smaller `drop_tol`, a larger `fill` or pivoting should help.
+    pub fn new(a: &CsMat<T>, params: &IlutParams<U>) -> Result<Ilut<T, U>, PrecondErr> {
+        if !(params.drop_tol >= U::zero() && params.drop_tol.is_finite()) {
+            return Err(PrecondErr::InvalidParameter(
+                "drop_tol must be non-negative and finite",
+            ));
+        }
+        if !(params.perm_tol >= U::zero() && params.perm_tol <= U::one()) {
+            return Err(PrecondErr::InvalidParameter("perm_tol must be in [0, 1]"));
+        }
+        let start = Instant::now();
+        let a = square_csr(a)?;
+        let n = a.rows();
+        let mut perm: Vec<usize> = (0..n).collect();
+        let mut iperm = perm.clone();
+        // current row, indexed by permuted column
+        let mut w = vec![T::zero(); n];
+        let mut in_row = vec![false; n];
+        let mut l_rows = Vec::with_capacity(n);
+        // rows of U are kept by original column until the permutation is final
+        let mut u_rows: Vec<Vec<(usize, T)>> = Vec::with_capacity(n);
+        let mut diag = Vec::with_capacity(n);
+        let mut zero_pivots = Vec::new();
+        let mut flops = 0;
+        let small = U::from(1e-4).unwrap();
+        for i in 0..n {
+            let mut pattern = Vec::new();
+            let mut lower = BTreeSet::new();
+            let mut tnorm = U::zero();
+            for (j, &v) in a.outer_view(i).unwrap().iter() {
+                let j = iperm[j];
+                w[j] = v;
+                in_row[j] = true;
+                pattern.push(j);
+                if j < i {
+                    lower.insert(j);
+                }
+                tnorm = tnorm + v.abs() * v.abs();
+            }
+            tnorm = tnorm.sqrt();
+            if tnorm == U::zero() {
+                return Err(PrecondErr::ZeroRow(i));
+            }
+            if !tnorm.is_finite() {
+                return Err(PrecondErr::NonFinite(i));
+            }
+            let tol = params.drop_tol * tnorm;
+
+            // eliminate in increasing column order; fill-in left of the
+            // diagonal joins `lower` and is eliminated in turn
+            while let Some(&k) = lower.iter().next() {
+                lower.remove(&k);
+                let lik = w[k] / diag[k];
+                flops += 1;
+                if lik.abs() < tol {
+                    w[k] = T::zero();
+                    continue;
+                }
+                w[k] = lik;
+                for &(c, u) in &u_rows[k] {
+                    let j = iperm[c];
+                    if !in_row[j] {
+                        in_row[j] = true;
+                        w[j] = T::zero();
+                        pattern.push(j);
+                        if j < i {
+                            lower.insert(j);
+                        }
+                    }
+                    w[j] = w[j] - lik * u;
+                    flops += 2;
+                }
+            }
+
+            // the magnitude comparisons below need finite entries; a
+            // non-finite pivot is handled like a zero one
+            if pattern.iter().any(|&j| j != i && !w[j].abs().is_finite()) {
+                return Err(PrecondErr::NonFinite(i));
+            }
+            let keep = |j: usize| w[j] != T::zero() && w[j].abs() >= tol;
+            let mut l_row: Vec<(usize, T)> = pattern
+                .iter()
+                .filter(|&&j| j < i && keep(j))
+                .map(|&j| (j, w[j]))
+                .collect();
+            let mut u_row: Vec<(usize, T)> = pattern
+                .iter()
+                .filter(|&&j| j > i && keep(j))
+                .map(|&j| (j, w[j]))
+                .collect();
+            let mut d = w[i];
+            for &j in &pattern {
+                in_row[j] = false;
+                w[j] = T::zero();
+            }
+            keep_largest(&mut l_row, params.fill);
+            keep_largest(&mut u_row, params.fill);
+
+            if params.perm_tol > U::zero() {
+                let m = (0..u_row.len())
+                    .max_by(|&x, &y| u_row[x].1.abs().partial_cmp(&u_row[y].1.abs()).unwrap());
+                if let Some(m) = m {
+                    if params.perm_tol * u_row[m].1.abs() > d.abs() {
+                        // swap columns i and j; the old pivot moves to column j
+                        let j = u_row[m].0;
+                        std::mem::swap(&mut u_row[m].1, &mut d);
+                        perm.swap(i, j);
+                        iperm[perm[i]] = i;
+                        iperm[perm[j]] = j;
+                        u_row.retain(|x| x.1 != T::zero());
+                    }
+                }
+            }
+
+            if d.abs() == U::zero() || !d.abs().is_finite() {
+                d = T::from((small + params.drop_tol) * tnorm);
+                zero_pivots.push(i);
+            }
+            diag.push(d);
+            l_rows.push(l_row);
+            u_rows.push(u_row.into_iter().map(|(j, v)| (perm[j], v)).collect());
+        }
+
+        let u_rows = u_rows
+            .into_iter()
+            .map(|row| {
+                let mut row: Vec<(usize, T)> =
+                    row.into_iter().map(|(c, v)| (iperm[c], v)).collect();
+                row.sort_by_key(|x| x.0);
+                row
+            })
+            .collect();
+        let l = rows_to_csr(n, l_rows);
+        let u = rows_to_csr(n, u_rows);
+        let nnz = l.nnz() + u.nnz() + n;
+        let fill_ratio = nnz as f64 / a.nnz().max(1) as f64;
+        let info = SetupInfo {
+            nnz,
+            flops,
+            time: start.elapsed(),
+        };
+        Ok(Ilut {
+            l,
+            u,
+            diag,
+            perm,
+            zero_pivots,
+            fill_ratio,
+            info,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Forward solve, `L^-1 x`.
+    pub fn solve_lower(&self, x: ArrayView1<T>) -> Array1<T> {
+        lower_solve(&self.l, None, x)
+    }
+
+    /// Back solve including the column permutation, `Q U^-1 x`.
+    pub fn solve_upper(&self, x: ArrayView1<T>) -> Array1<T> {
+        let z = upper_solve(&self.u, Some(&self.diag), x);
+        let mut y = Array1::<T>::zeros(z.len());
+        for (j, &p) in self.perm.iter().enumerate() {
+            y[p] = z[j];
+        }
+        y
+    }
+}
+
+impl<T, U> LinearOperator<T> for Ilut<T, U>
+where
+    T: ComplexOrReal<U>,
+    U: Float,
+{
+    fn shape(&self) -> (usize, usize) {
+        (self.diag.len(), self.diag.len())
+    }
+
+    fn apply(&self, x: ArrayView1<T>) -> Array1<T> {
+        let y = self.solve_lower(x);
+        self.solve_upper(y.view())
+    }
+
+    fn apply_adjoint(&self, x: ArrayView1<T>) -> Array1<T> {
+        let xp = self.perm.iter().map(|&p| x[p]).collect::<Array1<T>>();
+        let y = upper_adjoint_solve(&self.u, Some(&self.diag), xp.view());
+        lower_adjoint_solve(&self.l, None, y.view())
+    }
+}
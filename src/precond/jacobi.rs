@@ -0,0 +1,59 @@
+use super::{csr_diag, square_csr, PrecondErr, SetupInfo};
+use crate::operator::LinearOperator;
+use crate::utils::ComplexOrReal;
+use ndarray::{Array1, ArrayView1};
+use num_traits::Float;
+use sprs::CsMat;
+use std::marker::PhantomData;
+use std::time::Instant;
+
+/// Point Jacobi, `M = diag(A)`.
+pub struct Jacobi<T, U> {
+    pub dinv: Array1<T>,
+    pub info: SetupInfo,
+    pub phantom: PhantomData<U>,
+}
+
+impl<T, U> Jacobi<T, U>
+where
+    T: ComplexOrReal<U>,
+    U: Float,
+{
+    /// Fails with `ZeroPivot` on a zero or missing diagonal entry.
+    pub fn new(a: &CsMat<T>) -> Result<Jacobi<T, U>, PrecondErr> {
+        let start = Instant::now();
+        let a = square_csr(a)?;
+        let dinv = csr_diag(&a)?
+            .into_iter()
+            .map(|d| T::one() / d)
+            .collect::<Array1<T>>();
+        let info = SetupInfo {
+            nnz: dinv.len(),
+            flops: dinv.len(),
+            time: start.elapsed(),
+        };
+        Ok(Jacobi {
+            dinv,
+            info,
+            phantom: PhantomData,
+        })
+    }
+}
+
+impl<T, U> LinearOperator<T> for Jacobi<T, U>
+where
+    T: ComplexOrReal<U>,
+    U: Float,
+{
+    fn shape(&self) -> (usize, usize) {
+        (self.dinv.len(), self.dinv.len())
+    }
+
+    fn apply(&self, x: ArrayView1<T>) -> Array1<T> {
+        &x * &self.dinv
+    }
+
+    fn apply_adjoint(&self, x: ArrayView1<T>) -> Array1<T> {
+        &x * &self.dinv.map(|d| d.conj())
+    }
+}
@@ -0,0 +1,179 @@
+//! Preconditioners built from a `sprs::CsMat`. Each one is a
+//! `LinearOperator` whose `apply` computes `M^-1 x`, so it can be passed
+//! wherever a solver takes `M`.
+
+pub mod block_jacobi;
+pub mod ic0;
+pub mod ilu0;
+pub mod ilut;
+pub mod jacobi;
+pub mod ssor;
+
+pub use block_jacobi::BlockJacobi;
+pub use ic0::Ic0;
+pub use ilu0::Ilu0;
+pub use ilut::{Ilut, IlutParams};
+pub use jacobi::Jacobi;
+pub use ssor::Ssor;
+
+use crate::utils::HasConj;
+use ndarray::{Array1, ArrayView1};
+use num_traits::Num;
+use sprs::CsMat;
+use std::time::Duration;
+
+/// Why a preconditioner could not be built.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PrecondErr {
+    /// the matrix is `nrows x ncols`, not square
+    NotSquare(usize, usize),
+    /// zero or missing pivot (diagonal entry) in this row
+    ZeroPivot(usize),
+    /// this row has no nonzero entry
+    ZeroRow(usize),
+    /// this diagonal block is singular
+    SingularBlock(usize),
+    /// a non-positive pivot in this row; the matrix is not Hermitian
+    /// positive definite, or the incomplete factorization broke down
+    NotPositiveDefinite(usize),
+    InvalidParameter(&'static str),
+    /// a NaN or infinite entry in this row of the matrix or of its factors
+    NonFinite(usize),
+}
+
+impl std::fmt::Display for PrecondErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            PrecondErr::NotSquare(m, n) => write!(f, "matrix is {}x{}, not square", m, n),
+            PrecondErr::ZeroPivot(i) => write!(f, "zero pivot in row {}", i),
+            PrecondErr::ZeroRow(i) => write!(f, "row {} is zero", i),
+            PrecondErr::SingularBlock(k) => write!(f, "diagonal block {} is singular", k),
+            PrecondErr::NotPositiveDefinite(i) => {
+                write!(f, "non-positive pivot in row {}", i)
+            }
+            PrecondErr::InvalidParameter(s) => write!(f, "invalid parameter: {}", s),
+            PrecondErr::NonFinite(i) => write!(f, "non-finite entry in row {}", i),
+        }
+    }
+}
+
+impl std::error::Error for PrecondErr {}
+
+/// Cost of building a preconditioner.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SetupInfo {
+    /// number of stored values
+    pub nnz: usize,
+    /// floating point operations of the setup, a multiply-add counting as two
+    pub flops: usize,
+    pub time: Duration,
+}
+
+/// CSR copy of a square `a`.
+pub(crate) fn square_csr<T>(a: &CsMat<T>) -> Result<CsMat<T>, PrecondErr>
+where
+    T: Clone + Default,
+{
+    if a.rows() != a.cols() {
+        return Err(PrecondErr::NotSquare(a.rows(), a.cols()));
+    }
+    Ok(a.to_csr())
+}
+
+/// Diagonal of a CSR matrix; fails on a zero or missing diagonal entry.
+pub(crate) fn csr_diag<T>(a: &CsMat<T>) -> Result<Vec<T>, PrecondErr>
+where
+    T: Num + Copy,
+{
+    let (indptr, indices, data) = (a.indptr(), a.indices(), a.data());
+    (0..a.rows())
+        .map(|i| {
+            (indptr[i]..indptr[i + 1])
+                .find(|&k| indices[k] == i)
+                .map(|k| data[k])
+                .filter(|d| !d.is_zero())
+                .ok_or(PrecondErr::ZeroPivot(i))
+        })
+        .collect()
+}
+
+/// Solves `(D + L) y = x`, `L` the strictly lower part of the CSR matrix `a`.
+/// In these triangular solves `d = None` stands for `D = I`.
+pub(crate) fn lower_solve<T>(a: &CsMat<T>, d: Option<&[T]>, x: ArrayView1<T>) -> Array1<T>
+where
+    T: Num + Copy,
+{
+    let (indptr, indices, data) = (a.indptr(), a.indices(), a.data());
+    let mut y = Array1::<T>::zeros(x.len());
+    for i in 0..x.len() {
+        let mut s = x[i];
+        for k in indptr[i]..indptr[i + 1] {
+            if indices[k] < i {
+                s = s - data[k] * y[indices[k]];
+            }
+        }
+        y[i] = d.map_or(s, |d| s / d[i]);
+    }
+    y
+}
+
+/// Solves `(D + U) y = x`, `U` the strictly upper part of the CSR matrix `a`.
+pub(crate) fn upper_solve<T>(a: &CsMat<T>, d: Option<&[T]>, x: ArrayView1<T>) -> Array1<T>
+where
+    T: Num + Copy,
+{
+    let (indptr, indices, data) = (a.indptr(), a.indices(), a.data());
+    let mut y = Array1::<T>::zeros(x.len());
+    for i in (0..x.len()).rev() {
+        let mut s = x[i];
+        for k in indptr[i]..indptr[i + 1] {
+            if indices[k] > i {
+                s = s - data[k] * y[indices[k]];
+            }
+        }
+        y[i] = d.map_or(s, |d| s / d[i]);
+    }
+    y
+}
+
+/// Solves `(D + L)^H y = x`, column by column.
+pub(crate) fn lower_adjoint_solve<T>(a: &CsMat<T>, d: Option<&[T]>, x: ArrayView1<T>) -> Array1<T>
+where
+    T: Num + Copy + HasConj,
+{
+    let (indptr, indices, data) = (a.indptr(), a.indices(), a.data());
+    let mut y = x.to_owned();
+    for i in (0..x.len()).rev() {
+        if let Some(d) = d {
+            y[i] = y[i] / d[i].conj();
+        }
+        for k in indptr[i]..indptr[i + 1] {
+            let j = indices[k];
+            if j < i {
+                y[j] = y[j] - data[k].conj() * y[i];
+            }
+        }
+    }
+    y
+}
+
+/// Solves `(D + U)^H y = x`, column by column.
+pub(crate) fn upper_adjoint_solve<T>(a: &CsMat<T>, d: Option<&[T]>, x: ArrayView1<T>) -> Array1<T>
+where
+    T: Num + Copy + HasConj,
+{
+    let (indptr, indices, data) = (a.indptr(), a.indices(), a.data());
+    let mut y = x.to_owned();
+    for i in 0..x.len() {
+        if let Some(d) = d {
+            y[i] = y[i] / d[i].conj();
+        }
+        for k in indptr[i]..indptr[i + 1] {
+            let j = indices[k];
+            if j > i {
+                y[j] = y[j] - data[k].conj() * y[i];
+            }
+        }
+    }
+    y
+}
@@ -0,0 +1,84 @@
+use super::{
+    csr_diag, lower_adjoint_solve, lower_solve, square_csr, upper_adjoint_solve, upper_solve,
+    PrecondErr, SetupInfo,
+};
+use crate::operator::LinearOperator;
+use crate::utils::ComplexOrReal;
+use ndarray::{Array1, ArrayView1};
+use num_traits::Float;
+use sprs::CsMat;
+use std::marker::PhantomData;
+use std::time::Instant;
+
+/// SSOR(ω), `M = ω/(2-ω) (D/ω + L) (D/ω)^-1 (D/ω + U)` with `A = L + D + U`.
+/// For Hermitian positive definite `A` and `0 < ω < 2`, `M` is Hermitian
+/// positive definite too.
+pub struct Ssor<T, U> {
+    pub omega: U,
+    pub a: CsMat<T>,
+    /// D / ω
+    pub dw: Vec<T>,
+    pub info: SetupInfo,
+    pub phantom: PhantomData<U>,
+}
+
+impl<T, U> Ssor<T, U>
+where
+    T: ComplexOrReal<U>,
+    U: Float,
+{
+    /// Fails if `ω` is not in `(0, 2)` or on a zero diagonal entry.
+    pub fn new(a: &CsMat<T>, omega: U) -> Result<Ssor<T, U>, PrecondErr> {
+        if !(omega > U::zero() && omega < U::one() + U::one()) {
+            return Err(PrecondErr::InvalidParameter("omega must be in (0, 2)"));
+        }
+        let start = Instant::now();
+        let a = square_csr(a)?;
+        let dw: Vec<T> = csr_diag(&a)?
+            .into_iter()
+            .map(|d| d / T::from(omega))
+            .collect();
+        let info = SetupInfo {
+            nnz: a.nnz(),
+            flops: dw.len(),
+            time: start.elapsed(),
+        };
+        Ok(Ssor {
+            omega,
+            a,
+            dw,
+            info,
+            phantom: PhantomData,
+        })
+    }
+
+    fn scale(&self) -> T {
+        T::from((U::one() + U::one() - self.omega) / self.omega)
+    }
+}
+
+impl<T, U> LinearOperator<T> for Ssor<T, U>
+where
+    T: ComplexOrReal<U>,
+    U: Float,
+{
+    fn shape(&self) -> (usize, usize) {
+        (self.dw.len(), self.dw.len())
+    }
+
+    fn apply(&self, x: ArrayView1<T>) -> Array1<T> {
+        let mut y = lower_solve(&self.a, Some(&self.dw), x);
+        for (y, &d) in y.iter_mut().zip(self.dw.iter()) {
+            *y = *y * d;
+        }
+        upper_solve(&self.a, Some(&self.dw), y.view()) * self.scale()
+    }
+
+    fn apply_adjoint(&self, x: ArrayView1<T>) -> Array1<T> {
+        let mut y = upper_adjoint_solve(&self.a, Some(&self.dw), x);
+        for (y, &d) in y.iter_mut().zip(self.dw.iter()) {
+            *y = *y * d.conj();
+        }
+        lower_adjoint_solve(&self.a, Some(&self.dw), y.view()) * self.scale()
+    }
+}
@@ -0,0 +1,270 @@
+#![allow(non_snake_case)]
+
+//! Choice of the damping `λ` of `min ‖A x - b‖^2 + λ^2 ‖x‖^2` for ill-posed
+//! least-squares problems, by the corner of the L-curve or by minimizing
+//! generalized cross validation. All solves are done by damped LSQR, so only
+//! products with `A` and `A^T` are needed.
+
+use crate::lsqr::{eculid_norm, Lsqr};
+use crate::operator::LinearOperator;
+use crate::solver::IterativeSolver;
+use crate::stopping::StoppingCriterion;
+use ndarray::ScalarOperand;
+use ndarray::{Array1, ArrayView1};
+use num_traits::Float;
+
+/// The damped solution for one `λ`.
+#[derive(Debug, Clone)]
+pub struct SweepPoint<T> {
+    pub lambda: T,
+    pub x: Array1<T>,
+    /// ‖b - A x‖ (not damped)
+    pub rnorm: T,
+    /// ‖x‖
+    pub xnorm: T,
+    pub iterations: usize,
+}
+
+/// `n` values from `lo` to `hi`, equally spaced on a logarithmic scale.
+pub fn log_space<T>(lo: T, hi: T, n: usize) -> Vec<T>
+where
+    T: Float,
+{
+    assert!(lo > T::zero() && hi > lo && n >= 2);
+    let (a, b) = (lo.ln(), hi.ln());
+    let h = (b - a) / T::from(n - 1).unwrap();
+    (0..n)
+        .map(|i| (a + h * T::from(i).unwrap()).exp())
+        .collect()
+}
+
+fn damped_solve<T>(
+    A: &dyn LinearOperator<T>,
+    b: ArrayView1<T>,
+    lambda: T,
+    criterion: &StoppingCriterion<T>,
+) -> SweepPoint<T>
+where
+    T: Float + Copy + Default + ScalarOperand + std::fmt::Debug,
+{
+    let x0 = Array1::zeros(A.ncols());
+    let report = Lsqr::new()
+        .with_damping(lambda)
+        .solve(A, b, x0.view(), criterion);
+    SweepPoint {
+        lambda,
+        xnorm: eculid_norm(&report.x),
+        x: report.x,
+        rnorm: report.resid_norm,
+        iterations: report.iterations,
+    }
+}
+
+/// Damped LSQR solutions for each of `lambdas`, in the given order.
+pub fn lambda_sweep<T>(
+    A: &dyn LinearOperator<T>,
+    b: ArrayView1<T>,
+    lambdas: &[T],
+    criterion: &StoppingCriterion<T>,
+) -> Vec<SweepPoint<T>>
+where
+    T: Float + Copy + Default + ScalarOperand + std::fmt::Debug,
+{
+    lambdas
+        .iter()
+        .map(|&l| damped_solve(A, b, l, criterion))
+        .collect()
+}
+
+/// Signed curvature of the L-curve `(log ‖r‖, log ‖x‖)` parametrized by
+/// `log λ`, from three-point finite differences on the (possibly uneven)
+/// grid. The points must be sorted by increasing `λ`; the two end points
+/// have no curvature and get `None`.
+pub fn lcurve_curvature<T>(points: &[SweepPoint<T>]) -> Vec<Option<T>>
+where
+    T: Float,
+{
+    let t: Vec<T> = points.iter().map(|p| p.lambda.ln()).collect();
+    let rho: Vec<T> = points.iter().map(|p| p.rnorm.ln()).collect();
+    let eta: Vec<T> = points.iter().map(|p| p.xnorm.ln()).collect();
+    let two = T::one() + T::one();
+    (0..points.len())
+        .map(|i| {
+            if i == 0 || i + 1 == points.len() {
+                return None;
+            }
+            let (h1, h2) = (t[i] - t[i - 1], t[i + 1] - t[i]);
+            let d1 = |f: &[T]| {
+                -h2 / (h1 * (h1 + h2)) * f[i - 1]
+                    + (h2 - h1) / (h1 * h2) * f[i]
+                    + h1 / (h2 * (h1 + h2)) * f[i + 1]
+            };
+            let d2 = |f: &[T]| {
+                two * (f[i - 1] / (h1 * (h1 + h2)) - f[i] / (h1 * h2) + f[i + 1] / (h2 * (h1 + h2)))
+            };
+            let (r1, r2, e1, e2) = (d1(&rho), d2(&rho), d1(&eta), d2(&eta));
+            Some((r1 * e2 - r2 * e1) / (r1 * r1 + e1 * e1).powf(T::from(1.5).unwrap()))
+        })
+        .collect()
+}
+
+/// Index of the L-curve corner, the point of maximum curvature. Needs at
+/// least three points sorted by increasing `λ`.
+pub fn lcurve_corner<T>(points: &[SweepPoint<T>]) -> usize
+where
+    T: Float,
+{
+    assert!(points.len() >= 3, "the L-curve corner needs three points");
+    lcurve_curvature(points)
+        .iter()
+        .enumerate()
+        .filter_map(|(i, k)| k.map(|k| (i, k)))
+        .fold(None, |acc: Option<(usize, T)>, (i, k)| match acc {
+            Some((_, km)) if km >= k => acc,
+            _ => Some((i, k)),
+        })
+        .map(|(i, _)| i)
+        .unwrap()
+}
+
+/// How `trace(A A_λ^#)` of the influence matrix, `A_λ^# = (A^T A + λ^2 I)^-1 A^T`,
+/// is obtained. Both probe `A A_λ^#` with vectors `z` through damped solves
+/// `A_λ^# z`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceEstimator {
+    /// probe with all unit vectors: exact up to the solver tolerance but
+    /// `m` solves per `λ`; for small problems only
+    Exact,
+    /// Hutchinson's estimator with this many Rademacher vectors, for
+    /// matrix-free operators. The vectors are drawn from a seeded generator,
+    /// so all `λ` see the same ones and the GCV function stays smooth.
+    Hutchinson(usize),
+}
+
+/// The GCV function at one `λ`.
+#[derive(Debug, Clone)]
+pub struct GcvPoint<T> {
+    pub point: SweepPoint<T>,
+    /// (estimate of) `trace(I - A A_λ^#)`, the residual degrees of freedom
+    pub dof: T,
+    /// `‖b - A x‖^2 / dof^2`
+    pub value: T,
+}
+
+fn rademacher<T>(m: usize, k: usize) -> Vec<Array1<T>>
+where
+    T: Float,
+{
+    let mut seed: u64 = 0x9e37_79b9_7f4a_7c15;
+    (0..k)
+        .map(|_| {
+            (0..m)
+                .map(|_| {
+                    seed = seed
+                        .wrapping_mul(6_364_136_223_846_793_005)
+                        .wrapping_add(1_442_695_040_888_963_407);
+                    if seed >> 63 == 0 {
+                        T::one()
+                    } else {
+                        -T::one()
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Evaluates the generalized cross validation function
+/// `G(λ) = ‖b - A x_λ‖^2 / trace(I - A A_λ^#)^2`.
+pub fn gcv<T>(
+    A: &dyn LinearOperator<T>,
+    b: ArrayView1<T>,
+    lambda: T,
+    criterion: &StoppingCriterion<T>,
+    trace: TraceEstimator,
+) -> GcvPoint<T>
+where
+    T: Float + Copy + Default + ScalarOperand + std::fmt::Debug,
+{
+    let m = A.nrows();
+    let probes = match trace {
+        TraceEstimator::Exact => (0..m)
+            .map(|i| {
+                let mut e = Array1::zeros(m);
+                e[i] = T::one();
+                e
+            })
+            .collect(),
+        TraceEstimator::Hutchinson(k) => {
+            assert!(k >= 1, "Hutchinson's estimator needs a probe vector");
+            rademacher(m, k)
+        }
+    };
+    let scale = match trace {
+        TraceEstimator::Exact => T::one(),
+        TraceEstimator::Hutchinson(k) => T::one() / T::from(k).unwrap(),
+    };
+    let influence = probes.iter().fold(T::zero(), |acc, z| {
+        let xz = damped_solve(A, z.view(), lambda, criterion).x;
+        acc + z.dot(&A.apply(xz.view())) * scale
+    });
+    let dof = T::from(m).unwrap() - influence;
+    let point = damped_solve(A, b, lambda, criterion);
+    GcvPoint {
+        value: point.rnorm * point.rnorm / (dof * dof),
+        dof,
+        point,
+    }
+}
+
+/// Minimizes the GCV function: first over `lambdas` (sorted increasingly),
+/// then by `refine` golden-section steps in `log λ` between the neighbours
+/// of the best grid point.
+pub fn gcv_minimize<T>(
+    A: &dyn LinearOperator<T>,
+    b: ArrayView1<T>,
+    lambdas: &[T],
+    criterion: &StoppingCriterion<T>,
+    trace: TraceEstimator,
+    refine: usize,
+) -> GcvPoint<T>
+where
+    T: Float + Copy + Default + ScalarOperand + std::fmt::Debug,
+{
+    assert!(!lambdas.is_empty());
+    let eval = |l: T| gcv(A, b, l, criterion, trace);
+    let mut grid: Vec<GcvPoint<T>> = lambdas.iter().map(|&l| eval(l)).collect();
+    let best = (0..grid.len()).fold(0, |i, j| if grid[j].value < grid[i].value { j } else { i });
+    if refine == 0 || grid.len() < 2 {
+        return grid.swap_remove(best);
+    }
+
+    let mut lo = lambdas[best.saturating_sub(1)].ln();
+    let mut hi = lambdas[(best + 1).min(lambdas.len() - 1)].ln();
+    let g = T::from((5.0f64.sqrt() - 1.0) / 2.0).unwrap();
+    let mut c = hi - g * (hi - lo);
+    let mut d = lo + g * (hi - lo);
+    let mut fc = eval(c.exp());
+    let mut fd = eval(d.exp());
+    for _ in 0..refine {
+        if fc.value < fd.value {
+            hi = d;
+            d = c;
+            fd = fc;
+            c = hi - g * (hi - lo);
+            fc = eval(c.exp());
+        } else {
+            lo = c;
+            c = d;
+            fc = fd;
+            d = lo + g * (hi - lo);
+            fd = eval(d.exp());
+        }
+    }
+    let refined = if fc.value < fd.value { fc } else { fd };
+    if refined.value < grid[best].value {
+        refined
+    } else {
+        grid.swap_remove(best)
+    }
+}
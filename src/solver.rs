@@ -0,0 +1,54 @@
+#![allow(non_snake_case)]
+
+use crate::monitor::{Monitor, SilentMonitor};
+use crate::operator::LinearOperator;
+use crate::stopping::{StopReason, StoppingCriterion};
+use ndarray::{Array1, ArrayView1};
+
+/// Outcome of `IterativeSolver::solve`.
+#[derive(Debug, Clone)]
+pub struct SolveReport<T, U> {
+    pub x: Array1<T>,
+    pub iterations: usize,
+    /// norm of the true residual `b - A x` at the returned solution
+    pub resid_norm: U,
+    /// the test that ended the iteration
+    pub reason: StopReason,
+    /// residual norm estimate recorded after each iteration (after each restart
+    /// cycle for the restarted GMRES variants)
+    pub history: Vec<U>,
+}
+
+impl<T, U> SolveReport<T, U> {
+    pub fn converged(&self) -> bool {
+        self.reason.is_converged()
+    }
+}
+
+/// Common entry point of the Krylov solvers.
+///
+/// Each method keeps its own state type (`BiCGStabState`, `LsqrState`, ...)
+/// for step-by-step use; `solve` drives that state to completion.
+pub trait IterativeSolver<T, U> {
+    /// the state handed to monitors after each iteration
+    type State;
+
+    fn solve_with_monitor(
+        &self,
+        A: &dyn LinearOperator<T>,
+        b: ArrayView1<T>,
+        x0: ArrayView1<T>,
+        criterion: &StoppingCriterion<U>,
+        monitor: &mut dyn Monitor<Self::State, U>,
+    ) -> SolveReport<T, U>;
+
+    fn solve(
+        &self,
+        A: &dyn LinearOperator<T>,
+        b: ArrayView1<T>,
+        x0: ArrayView1<T>,
+        criterion: &StoppingCriterion<U>,
+    ) -> SolveReport<T, U> {
+        self.solve_with_monitor(A, b, x0, criterion, &mut SilentMonitor)
+    }
+}
@@ -0,0 +1,355 @@
+#![allow(non_snake_case)]
+
+//! Classical stationary iterations `x += M^-1 (b - A x)` on a
+//! `sprs::CsMat`: damped Jacobi, Gauss–Seidel and SOR in forward, backward
+//! and symmetric order, and Richardson. The sweeps work in place and serve
+//! as smoothers; `Stationary` wraps them as an `IterativeSolver` and, by a
+//! fixed number of sweeps from zero, as a preconditioner.
+
+use crate::monitor::{Monitor, MonitorAction};
+use crate::operator::{check_shape, LinearOperator};
+use crate::precond::{csr_diag, square_csr, PrecondErr};
+use crate::solver::{IterativeSolver, SolveReport};
+use crate::stopping::{IterInfo, StopReason, StoppingCriterion};
+use crate::utils::{norm, ComplexOrReal};
+use ndarray::{Array1, ArrayView1};
+use num_traits::Float;
+use sprs::CsMat;
+use std::time::Instant;
+
+/// Order of the unknowns in a Gauss–Seidel or SOR sweep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sweep {
+    Forward,
+    Backward,
+    /// a forward followed by a backward sweep (SGS, SSOR); the iteration
+    /// matrix is then self-adjoint in the energy inner product of a
+    /// Hermitian positive definite `A`
+    Symmetric,
+}
+
+/// `x += ω D^-1 (b - A x)`
+pub fn jacobi_sweep<T, U>(a: &CsMat<T>, diag: &[T], omega: U, b: ArrayView1<T>, x: &mut Array1<T>)
+where
+    T: ComplexOrReal<U>,
+    U: Float,
+{
+    let r = &b - &a.apply(x.view());
+    for i in 0..x.len() {
+        x[i] = x[i] + T::from(omega) * r[i] / diag[i];
+    }
+}
+
+/// One SOR sweep, `x_i = (1 - ω) x_i + ω (b_i - Σ_{j≠i} a_ij x_j) / a_ii`
+/// with the newest values of `x`; `ω = 1` is Gauss–Seidel.
+pub fn sor_sweep<T, U>(
+    a: &CsMat<T>,
+    diag: &[T],
+    omega: U,
+    sweep: Sweep,
+    b: ArrayView1<T>,
+    x: &mut Array1<T>,
+) where
+    T: ComplexOrReal<U>,
+    U: Float,
+{
+    let (indptr, indices, data) = (a.indptr(), a.indices(), a.data());
+    let w = T::from(omega);
+    let relax = |i: usize, x: &mut Array1<T>| {
+        let mut s = b[i];
+        for k in indptr[i]..indptr[i + 1] {
+            if indices[k] != i {
+                s = s - data[k] * x[indices[k]];
+            }
+        }
+        x[i] = x[i] + w * (s / diag[i] - x[i]);
+    };
+    let n = x.len();
+    if sweep != Sweep::Backward {
+        (0..n).for_each(|i| relax(i, x));
+    }
+    if sweep != Sweep::Forward {
+        (0..n).rev().for_each(|i| relax(i, x));
+    }
+}
+
+/// `x += ω (b - A x)`
+pub fn richardson_step<T, U>(a: &CsMat<T>, omega: U, b: ArrayView1<T>, x: &mut Array1<T>)
+where
+    T: ComplexOrReal<U>,
+    U: Float,
+{
+    let r = &b - &a.apply(x.view());
+    *x = &*x + &(&r * T::from(omega));
+}
+
+/// Estimates the optimal SOR parameter `ω = 2 / (1 + sqrt(1 - ρ^2))`, with
+/// `ρ` the spectral radius of the Jacobi iteration matrix `I - D^-1 A`.
+/// The formula is Young's, for consistently ordered matrices with a real
+/// Jacobi spectrum (e.g. Hermitian positive definite and block
+/// tridiagonal); `ρ` is found by `iters` steps of the power method on the
+/// square of the Jacobi matrix, with Rayleigh quotients in the `|D|` inner
+/// product. Fails if the Jacobi iteration does not converge.
+pub fn optimal_sor_omega<T, U>(a: &CsMat<T>, iters: usize) -> Result<U, PrecondErr>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    let a = square_csr(a)?;
+    let diag = csr_diag(&a)?;
+    let n = diag.len();
+    let jacobi = |v: &Array1<T>| {
+        let av = a.apply(v.view());
+        Array1::from_shape_fn(n, |i| v[i] - av[i] / diag[i])
+    };
+    let dot = |v: &Array1<T>, w: &Array1<T>| {
+        (0..n).fold(U::zero(), |s, i| {
+            let p: num_complex::Complex<U> = (v[i].conj() * w[i]).into();
+            s + diag[i].abs() * p.re
+        })
+    };
+    // start with all ones, which is not orthogonal to the dominant
+    // eigenvector of an M-matrix
+    let mut v = Array1::<T>::from_elem(n, T::one());
+    let mut rho2 = U::zero();
+    for _ in 0..iters.max(1) {
+        let w = jacobi(&jacobi(&v));
+        rho2 = dot(&v, &w) / dot(&v, &v);
+        let nw: U = norm(w.view());
+        if nw == U::zero() {
+            break;
+        }
+        v = w.map(|&x| x / T::from(nw));
+    }
+    if rho2 >= U::one() {
+        return Err(PrecondErr::InvalidParameter(
+            "the Jacobi iteration diverges, no optimal omega",
+        ));
+    }
+    let two = U::one() + U::one();
+    Ok(two / (U::one() + (U::one() - rho2.max(U::zero())).sqrt()))
+}
+
+/// The splitting of a stationary method.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Method<U> {
+    /// damped Jacobi with weight `ω`
+    Jacobi(U),
+    /// SOR with parameter `ω`; Gauss–Seidel for `ω = 1`, SSOR for a
+    /// symmetric sweep
+    Sor(U, Sweep),
+    /// Richardson with step `ω`; converges for `0 < ω < 2 / λ_max` if `A` is
+    /// Hermitian positive definite
+    Richardson(U),
+}
+
+/// A stationary method on a fixed matrix. As a `LinearOperator` it applies
+/// `sweeps` steps from a zero initial guess, i.e. `M^-1` for one sweep;
+/// that is symmetric for Jacobi, Richardson and symmetric sweeps of a
+/// Hermitian `A`, so only those may precondition CG or MINRES.
+pub struct Stationary<T, U> {
+    pub a: CsMat<T>,
+    pub diag: Vec<T>,
+    pub method: Method<U>,
+    pub sweeps: usize,
+}
+
+impl<T, U> Stationary<T, U>
+where
+    T: ComplexOrReal<U>,
+    U: Float,
+{
+    /// Fails on a non-square matrix, on a zero diagonal entry (except for
+    /// Richardson) or if `ω` is outside `(0, 2)` for SOR.
+    pub fn new(a: &CsMat<T>, method: Method<U>) -> Result<Stationary<T, U>, PrecondErr> {
+        let a = square_csr(a)?;
+        let diag = match method {
+            Method::Richardson(_) => Vec::new(),
+            Method::Jacobi(_) | Method::Sor(_, _) => csr_diag(&a)?,
+        };
+        if let Method::Sor(omega, _) = method {
+            if !(omega > U::zero() && omega < U::one() + U::one()) {
+                return Err(PrecondErr::InvalidParameter("omega must be in (0, 2)"));
+            }
+        }
+        Ok(Stationary {
+            a,
+            diag,
+            method,
+            sweeps: 1,
+        })
+    }
+
+    pub fn jacobi(a: &CsMat<T>, omega: U) -> Result<Stationary<T, U>, PrecondErr> {
+        Self::new(a, Method::Jacobi(omega))
+    }
+
+    pub fn gauss_seidel(a: &CsMat<T>, sweep: Sweep) -> Result<Stationary<T, U>, PrecondErr> {
+        Self::new(a, Method::Sor(U::one(), sweep))
+    }
+
+    pub fn sor(a: &CsMat<T>, omega: U, sweep: Sweep) -> Result<Stationary<T, U>, PrecondErr> {
+        Self::new(a, Method::Sor(omega, sweep))
+    }
+
+    pub fn ssor(a: &CsMat<T>, omega: U) -> Result<Stationary<T, U>, PrecondErr> {
+        Self::new(a, Method::Sor(omega, Sweep::Symmetric))
+    }
+
+    pub fn richardson(a: &CsMat<T>, omega: U) -> Result<Stationary<T, U>, PrecondErr> {
+        Self::new(a, Method::Richardson(omega))
+    }
+
+    /// number of sweeps done by `apply`
+    pub fn with_sweeps(mut self, sweeps: usize) -> Stationary<T, U> {
+        self.sweeps = sweeps;
+        self
+    }
+
+    /// One step of the method on `A x = b`.
+    pub fn sweep(&self, b: ArrayView1<T>, x: &mut Array1<T>) {
+        match self.method {
+            Method::Jacobi(omega) => jacobi_sweep(&self.a, &self.diag, omega, b, x),
+            Method::Sor(omega, sweep) => sor_sweep(&self.a, &self.diag, omega, sweep, b, x),
+            Method::Richardson(omega) => richardson_step(&self.a, omega, b, x),
+        }
+    }
+}
+
+impl<T, U> LinearOperator<T> for Stationary<T, U>
+where
+    T: ComplexOrReal<U>,
+    U: Float,
+{
+    fn shape(&self) -> (usize, usize) {
+        (self.a.rows(), self.a.rows())
+    }
+
+    fn apply(&self, x: ArrayView1<T>) -> Array1<T> {
+        let mut y = Array1::zeros(x.len());
+        for _ in 0..self.sweeps {
+            self.sweep(x, &mut y);
+        }
+        y
+    }
+
+    /// Only correct for the symmetric cases named above.
+    fn apply_adjoint(&self, x: ArrayView1<T>) -> Array1<T> {
+        self.apply(x)
+    }
+}
+
+pub struct StationaryState<T, U>
+where
+    T: ComplexOrReal<U>,
+    U: Float,
+{
+    pub x: Array1<T>,
+    /// recursively updated residual
+    pub res: Array1<T>,
+    pub tol: U,
+    pub converged: bool,
+}
+
+impl<T, U> StationaryState<T, U>
+where
+    T: ComplexOrReal<U>,
+    U: Float,
+{
+    pub fn new(
+        lhs: &dyn LinearOperator<T>,
+        x: ArrayView1<T>,
+        b: ArrayView1<T>,
+        tol: U,
+    ) -> StationaryState<T, U> {
+        check_shape(lhs, b.len(), x.len());
+        let res = &b - &lhs.apply(x);
+        let converged = norm(res.view()) < tol;
+        StationaryState {
+            x: x.to_owned(),
+            res,
+            tol,
+            converged,
+        }
+    }
+
+    /// `x += M^-1 (b - A x)`, i.e. one sweep of `method` on the residual
+    /// equation from zero. Returns `None` (and keeps the old state) if the
+    /// iterate is no longer finite, i.e. the method diverged.
+    pub fn next(&mut self, lhs: &dyn LinearOperator<T>, method: &Stationary<T, U>) -> Option<()> {
+        let mut dx = Array1::zeros(self.x.len());
+        method.sweep(self.res.view(), &mut dx);
+        let res = &self.res - &lhs.apply(dx.view());
+        if dx.iter().chain(res.iter()).all(|x| x.abs().is_finite()) {
+            self.converged = norm(res.view()) < self.tol;
+            self.x = &self.x + &dx;
+            self.res = res;
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    pub fn calc_resid(&self, lhs: &dyn LinearOperator<T>, b: &Array1<T>) -> Array1<T> {
+        b - &lhs.apply(self.x.view())
+    }
+}
+
+/// The stationary method as an `IterativeSolver`, one sweep per iteration.
+/// `A` is normally the matrix the method was built from.
+impl<T, U> IterativeSolver<T, U> for Stationary<T, U>
+where
+    T: ComplexOrReal<U>,
+    U: Float,
+{
+    type State = StationaryState<T, U>;
+
+    fn solve_with_monitor(
+        &self,
+        A: &dyn LinearOperator<T>,
+        b: ArrayView1<T>,
+        x0: ArrayView1<T>,
+        criterion: &StoppingCriterion<U>,
+        monitor: &mut dyn Monitor<StationaryState<T, U>, U>,
+    ) -> SolveReport<T, U> {
+        let start = Instant::now();
+        let b_norm = norm(b);
+        let mut state = StationaryState::new(A, x0, b, U::zero());
+        let r0_norm = norm(state.res.view());
+        let mut history = vec![r0_norm];
+        let mut iterations = 0;
+        let reason = loop {
+            let info = IterInfo::new(
+                iterations,
+                history[iterations],
+                r0_norm,
+                b_norm,
+                start.elapsed(),
+            );
+            if let Some(reason) = criterion.check(&info) {
+                break reason;
+            }
+            if state.next(A, self).is_none() {
+                break StopReason::Breakdown;
+            }
+            iterations += 1;
+            let r = norm(state.res.view());
+            history.push(r);
+            if monitor.observe(iterations, r, &state) == MonitorAction::Abort {
+                break StopReason::Aborted;
+            }
+        };
+        let resid_norm = norm(state.calc_resid(A, &b.to_owned()).view());
+        let reason = match reason {
+            StopReason::Breakdown => criterion.breakdown_reason(resid_norm, r0_norm, b_norm),
+            _ => reason,
+        };
+        SolveReport {
+            x: state.x,
+            iterations,
+            resid_norm,
+            reason,
+            history,
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use num_traits::Float;
+use std::time::Duration;
+
+/// Which test ended an iteration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// ‖r‖ <= abs_tol
+    AbsoluteResidual,
+    /// ‖r‖ <= rel_tol ‖r0‖
+    RelativeResidual,
+    /// ‖r‖ <= rhs_tol ‖b‖
+    RhsRelativeResidual,
+    /// ‖r‖ = 0 with no residual test set
+    ExactSolution,
+    /// Paige–Saunders S1: Ax = b is consistent to within atol/btol
+    CompatibleSystem,
+    /// Paige–Saunders S2: x solves the least-squares problem to within atol
+    LeastSquaresSolution,
+    /// Paige–Saunders S3: the condition estimate exceeded conlim
+    ConditionLimit,
+    MaxIterations,
+    TimeLimit,
+    /// the recurrence broke down (division by zero, non-finite values, ...)
+    Breakdown,
+    /// a `Monitor` asked to stop
+    Aborted,
+}
+
+impl StopReason {
+    /// true if the solver stopped because a solution of the requested accuracy was found
+    pub fn is_converged(self) -> bool {
+        match self {
+            StopReason::AbsoluteResidual
+            | StopReason::RelativeResidual
+            | StopReason::RhsRelativeResidual
+            | StopReason::ExactSolution
+            | StopReason::CompatibleSystem
+            | StopReason::LeastSquaresSolution => true,
+            StopReason::ConditionLimit
+            | StopReason::MaxIterations
+            | StopReason::TimeLimit
+            | StopReason::Breakdown
+            | StopReason::Aborted => false,
+        }
+    }
+
+    /// The `istop` code of the original LSQR/LSMR for the reasons that have
+    /// one: 1 compatible system, 2 least-squares solution, 3 condition limit
+    /// and 7 iteration limit. The codes 4 to 6 (tests at machine precision)
+    /// are reported as 1 to 3, since `StoppingCriterion` clamps the
+    /// tolerances to machine precision.
+    pub fn istop(self) -> Option<usize> {
+        match self {
+            StopReason::CompatibleSystem => Some(1),
+            StopReason::LeastSquaresSolution => Some(2),
+            StopReason::ConditionLimit => Some(3),
+            StopReason::MaxIterations => Some(7),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for StopReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            StopReason::AbsoluteResidual => write!(f, "absolute residual tolerance reached"),
+            StopReason::RelativeResidual => write!(f, "relative residual tolerance reached"),
+            StopReason::RhsRelativeResidual => write!(f, "residual relative to |b| reached"),
+            StopReason::ExactSolution => write!(f, "exact solution"),
+            StopReason::CompatibleSystem => write!(f, "Ax=b solved to within atol/btol"),
+            StopReason::LeastSquaresSolution => write!(f, "least-squares solution to within atol"),
+            StopReason::ConditionLimit => write!(f, "condition estimate exceeded conlim"),
+            StopReason::MaxIterations => write!(f, "max iterations reached"),
+            StopReason::TimeLimit => write!(f, "time limit reached"),
+            StopReason::Breakdown => write!(f, "breakdown"),
+            StopReason::Aborted => write!(f, "aborted by monitor"),
+        }
+    }
+}
+
+/// Norm estimates produced by the least-squares solvers (LSQR, LSMR) and consumed
+/// by the Paige–Saunders tests.
+#[derive(Debug, Clone, Copy)]
+pub struct LsqEstimates<U> {
+    /// ‖A^H r‖
+    pub arnorm: U,
+    /// ‖A‖_F
+    pub anorm: U,
+    /// cond(A); zero if not estimated
+    pub acond: U,
+    /// ‖x‖
+    pub xnorm: U,
+}
+
+/// What a solver knows about its progress after an iteration.
+#[derive(Debug, Clone, Copy)]
+pub struct IterInfo<U> {
+    pub iter: usize,
+    /// current (possibly estimated) residual norm
+    pub resid_norm: U,
+    pub r0_norm: U,
+    pub b_norm: U,
+    pub elapsed: Duration,
+    pub lsq: Option<LsqEstimates<U>>,
+}
+
+impl<U> IterInfo<U> {
+    pub fn new(
+        iter: usize,
+        resid_norm: U,
+        r0_norm: U,
+        b_norm: U,
+        elapsed: Duration,
+    ) -> IterInfo<U> {
+        IterInfo {
+            iter,
+            resid_norm,
+            r0_norm,
+            b_norm,
+            elapsed,
+            lsq: None,
+        }
+    }
+}
+
+/// The standard LSQR stopping parameters.
+#[derive(Debug, Clone, Copy)]
+pub struct PaigeSaunders<U> {
+    pub atol: U,
+    pub btol: U,
+    pub conlim: U,
+}
+
+/// A combination of stopping tests; the iteration stops as soon as any of
+/// the enabled tests fires.
+#[derive(Debug, Clone, Copy)]
+pub struct StoppingCriterion<U> {
+    pub abs_tol: Option<U>,
+    pub rel_tol: Option<U>,
+    pub rhs_tol: Option<U>,
+    pub paige_saunders: Option<PaigeSaunders<U>>,
+    pub max_iter: usize,
+    pub max_time: Option<Duration>,
+}
+
+impl<U> StoppingCriterion<U>
+where
+    U: Float,
+{
+    /// only the iteration limit
+    pub fn new(max_iter: usize) -> StoppingCriterion<U> {
+        StoppingCriterion {
+            abs_tol: None,
+            rel_tol: None,
+            rhs_tol: None,
+            paige_saunders: None,
+            max_iter,
+            max_time: None,
+        }
+    }
+
+    /// the usual `‖b - A x‖ <= tol ‖b‖` test
+    pub fn rhs_relative(tol: U, max_iter: usize) -> StoppingCriterion<U> {
+        Self::new(max_iter).with_rhs_tol(tol)
+    }
+
+    pub fn with_abs_tol(mut self, tol: U) -> StoppingCriterion<U> {
+        self.abs_tol = Some(tol);
+        self
+    }
+
+    pub fn with_rel_tol(mut self, tol: U) -> StoppingCriterion<U> {
+        self.rel_tol = Some(tol);
+        self
+    }
+
+    pub fn with_rhs_tol(mut self, tol: U) -> StoppingCriterion<U> {
+        self.rhs_tol = Some(tol);
+        self
+    }
+
+    pub fn with_paige_saunders(mut self, atol: U, btol: U, conlim: U) -> StoppingCriterion<U> {
+        self.paige_saunders = Some(PaigeSaunders { atol, btol, conlim });
+        self
+    }
+
+    pub fn with_max_time(mut self, max_time: Duration) -> StoppingCriterion<U> {
+        self.max_time = Some(max_time);
+        self
+    }
+
+    /// The loosest absolute residual bound implied by the residual tests, with
+    /// the test that produces it. Used by solvers (e.g. GMRES) that only test
+    /// an absolute threshold internally.
+    pub fn residual_threshold(&self, r0_norm: U, b_norm: U) -> Option<(U, StopReason)> {
+        let candidates = [
+            self.abs_tol.map(|t| (t, StopReason::AbsoluteResidual)),
+            self.rel_tol
+                .map(|t| (t * r0_norm, StopReason::RelativeResidual)),
+            self.rhs_tol
+                .map(|t| (t * b_norm, StopReason::RhsRelativeResidual)),
+        ];
+        candidates
+            .iter()
+            .filter_map(|&c| c)
+            .fold(None, |acc, c| match acc {
+                Some((t, _)) if t >= c.0 => acc,
+                _ => Some(c),
+            })
+    }
+
+    pub fn check(&self, info: &IterInfo<U>) -> Option<StopReason> {
+        if let Some((th, reason)) = self.residual_threshold(info.r0_norm, info.b_norm) {
+            if info.resid_norm <= th {
+                return Some(reason);
+            }
+        }
+
+        if let (Some(ps), Some(est)) = (self.paige_saunders, info.lsq) {
+            // tolerances below machine precision could never be met
+            let eps = U::epsilon();
+            let (atol, btol) = (ps.atol.max(eps), ps.btol.max(eps));
+            if info.resid_norm <= btol * info.b_norm + atol * est.anorm * est.xnorm {
+                return Some(StopReason::CompatibleSystem);
+            }
+            if est.arnorm <= atol * est.anorm * info.resid_norm {
+                return Some(StopReason::LeastSquaresSolution);
+            }
+            if ps.conlim > U::zero() && est.acond >= ps.conlim.min(U::one() / eps) {
+                return Some(StopReason::ConditionLimit);
+            }
+        }
+
+        if info.iter >= self.max_iter {
+            return Some(StopReason::MaxIterations);
+        }
+
+        match self.max_time {
+            Some(t) if info.elapsed >= t => Some(StopReason::TimeLimit),
+            _ => None,
+        }
+    }
+
+    /// A breakdown at a point whose true residual `resid_norm` already passes
+    /// one of the residual tests is reported as that test.
+    pub fn breakdown_reason(&self, resid_norm: U, r0_norm: U, b_norm: U) -> StopReason {
+        match self.residual_threshold(r0_norm, b_norm) {
+            Some((th, reason)) if resid_norm <= th => reason,
+            _ => StopReason::Breakdown,
+        }
+    }
+
+    /// The reason for stopping at a residual that vanished exactly: the
+    /// residual test it passes, or `ExactSolution` if there is none.
+    pub fn exact_reason(&self, r0_norm: U, b_norm: U) -> StopReason {
+        self.residual_threshold(r0_norm, b_norm)
+            .map_or(StopReason::ExactSolution, |(_, reason)| reason)
+    }
+}
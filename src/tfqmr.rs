@@ -0,0 +1,238 @@
+#![allow(non_snake_case)]
+#![allow(clippy::many_single_char_names)]
+
+use crate::monitor::{Monitor, MonitorAction};
+use crate::operator::{check_shape, LinearOperator};
+use crate::solver::{IterativeSolver, SolveReport};
+use crate::stopping::{IterInfo, StopReason, StoppingCriterion};
+use crate::utils::{inner, norm, ComplexOrReal};
+use ndarray::{Array1, ArrayView1};
+use num_traits::Float;
+use std::time::Instant;
+
+/// State of Freund's transpose-free QMR. It runs on the CGS recurrences but
+/// picks its iterates by a quasi-minimal residual condition, which smooths
+/// out the irregular convergence of CGS. The residual itself is not
+/// available; `resid` is the upper bound `τ sqrt(m + 1)` on its norm after
+/// `m` half-steps.
+pub struct TfqmrState<T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    pub x: Array1<T>,
+    /// the shadow residual `r~`
+    pub res_shadow: Array1<T>,
+    pub w: Array1<T>,
+    pub y: Array1<T>,
+    /// `A y`
+    pub ay: Array1<T>,
+    pub v: Array1<T>,
+    pub d: Array1<T>,
+    /// `<r~, w>`
+    pub rho: T,
+    pub tau: U,
+    pub theta: U,
+    pub eta: T,
+    /// number of half-steps done
+    pub m: usize,
+    pub resid: U,
+    pub tol: U,
+    pub converged: bool,
+}
+
+pub fn tfqmr_iter<T, U>(
+    lhs: &dyn LinearOperator<T>,
+    s_last: &TfqmrState<T, U>,
+) -> Option<TfqmrState<T, U>>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    let sigma = inner(s_last.res_shadow.view(), s_last.v.view());
+    if sigma == T::zero() || s_last.rho == T::zero() {
+        return None;
+    }
+    let alpha = s_last.rho / sigma;
+    let y2 = &s_last.y - &(&s_last.v * alpha);
+    let ay2 = lhs.apply(y2.view());
+
+    let mut w = s_last.w.clone();
+    let mut d = s_last.d.clone();
+    let mut x = s_last.x.clone();
+    let (mut tau, mut theta, mut eta) = (s_last.tau, s_last.theta, s_last.eta);
+    let mut m = s_last.m;
+    for (y, ay) in [(&s_last.y, &s_last.ay), (&y2, &ay2)].iter() {
+        w = w - &(*ay * alpha);
+        d = *y + &(&d * (T::from(theta * theta) * eta / alpha));
+        theta = norm::<T, U>(w.view()) / tau;
+        let c = U::one() / (U::one() + theta * theta).sqrt();
+        tau = tau * theta * c;
+        eta = T::from(c * c) * alpha;
+        x = x + &(&d * eta);
+        m += 1;
+    }
+
+    let rho = inner(s_last.res_shadow.view(), w.view());
+    let beta = rho / s_last.rho;
+    let y = &w + &(&y2 * beta);
+    let ay = lhs.apply(y.view());
+    let v = &ay + &(&(&ay2 + &(&s_last.v * beta)) * beta);
+    let resid = tau * U::from(m + 1).unwrap().sqrt();
+    let converged = resid < s_last.tol;
+
+    Some(TfqmrState {
+        x,
+        res_shadow: s_last.res_shadow.clone(),
+        w,
+        y,
+        ay,
+        v,
+        d,
+        rho,
+        tau,
+        theta,
+        eta,
+        m,
+        resid,
+        tol: s_last.tol,
+        converged,
+    })
+}
+
+impl<T, U> TfqmrState<T, U>
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    pub fn new(
+        lhs: &dyn LinearOperator<T>,
+        x: ArrayView1<T>,
+        b: ArrayView1<T>,
+        tol: U,
+    ) -> TfqmrState<T, U> {
+        check_shape(lhs, b.len(), x.len());
+        let res = &b - &lhs.apply(x);
+        let ay = lhs.apply(res.view());
+        let tau = norm(res.view());
+        TfqmrState {
+            x: x.to_owned(),
+            rho: inner(res.view(), res.view()),
+            res_shadow: res.clone(),
+            w: res.clone(),
+            y: res.clone(),
+            v: ay.clone(),
+            ay,
+            d: Array1::zeros(res.len()),
+            tau,
+            theta: U::zero(),
+            eta: T::zero(),
+            m: 0,
+            resid: tau,
+            tol,
+            converged: tau < tol,
+        }
+    }
+
+    /// Two half-steps, i.e. two products with `A`. Returns `None` (and keeps
+    /// the old state) on a breakdown, i.e. `<r~, w> = 0` or `<r~, v> = 0`.
+    pub fn next(&mut self, lhs: &dyn LinearOperator<T>) -> Option<()> {
+        let ns = tfqmr_iter(lhs, self)?;
+        if ns.valid() {
+            *self = ns;
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    pub fn calc_resid(&self, lhs: &dyn LinearOperator<T>, b: &Array1<T>) -> Array1<T> {
+        b - &lhs.apply(self.x.view())
+    }
+
+    pub fn converged(&self, lhs: &dyn LinearOperator<T>, b: &Array1<T>, th: U) -> bool {
+        norm(self.calc_resid(lhs, b).view()) < th
+    }
+
+    pub fn valid(&self) -> bool {
+        self.x.iter().all(|x| x.abs().is_finite())
+            && self.w.iter().all(|x| x.abs().is_finite())
+            && self.v.iter().all(|x| x.abs().is_finite())
+            && self.d.iter().all(|x| x.abs().is_finite())
+            && self.tau.is_finite()
+    }
+}
+
+/// TFQMR as an `IterativeSolver`. The residual tests use the bound `resid`,
+/// which is computed from the CGS recurrences and in finite precision may
+/// fall below the true residual, e.g. when the residuals grow strongly in
+/// between. A passed test is therefore confirmed with the true residual
+/// `b - A x`; if that fails, TFQMR restarts from the current iterate.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Tfqmr;
+
+impl<T, U> IterativeSolver<T, U> for Tfqmr
+where
+    T: ComplexOrReal<U> + std::fmt::Debug,
+    U: Float + std::fmt::Debug,
+{
+    type State = TfqmrState<T, U>;
+
+    fn solve_with_monitor(
+        &self,
+        A: &dyn LinearOperator<T>,
+        b: ArrayView1<T>,
+        x0: ArrayView1<T>,
+        criterion: &StoppingCriterion<U>,
+        monitor: &mut dyn Monitor<TfqmrState<T, U>, U>,
+    ) -> SolveReport<T, U> {
+        let start = Instant::now();
+        let b = b.to_owned();
+        let b_norm = norm(b.view());
+        let mut state = TfqmrState::new(A, x0, b.view(), U::zero());
+        let r0_norm = state.resid;
+        let mut history = vec![r0_norm];
+        let mut iterations = 0;
+        let reason = loop {
+            let info = IterInfo::new(
+                iterations,
+                history[iterations],
+                r0_norm,
+                b_norm,
+                start.elapsed(),
+            );
+            if let Some(reason) = criterion.check(&info) {
+                if !reason.is_converged() {
+                    break reason;
+                }
+                let r = norm(state.calc_resid(A, &b).view());
+                let info = IterInfo::new(iterations, r, r0_norm, b_norm, start.elapsed());
+                if let Some(reason) = criterion.check(&info) {
+                    break reason;
+                }
+                state = TfqmrState::new(A, state.x.view(), b.view(), U::zero());
+                history[iterations] = state.resid;
+            }
+            if state.next(A).is_none() {
+                break StopReason::Breakdown;
+            }
+            iterations += 1;
+            history.push(state.resid);
+            if monitor.observe(iterations, state.resid, &state) == MonitorAction::Abort {
+                break StopReason::Aborted;
+            }
+        };
+        let resid_norm = norm(state.calc_resid(A, &b).view());
+        let reason = match reason {
+            StopReason::Breakdown => criterion.breakdown_reason(resid_norm, r0_norm, b_norm),
+            _ => reason,
+        };
+        SolveReport {
+            x: state.x,
+            iterations,
+            resid_norm,
+            reason,
+            history,
+        }
+    }
+}
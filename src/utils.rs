@@ -67,7 +67,7 @@ where
 {
     type Output = T;
     fn abs(&self) -> T {
-        num_complex::Complex::<T>::norm(self)
+        num_complex::Complex::<T>::norm(*self)
     }
 }
 
@@ -96,6 +96,18 @@ where
     result.sqrt()
 }
 
+/// Hermitian inner product x^H y
+pub fn inner<T, U>(x: ArrayView1<T>, y: ArrayView1<T>) -> T
+where
+    T: ComplexOrReal<U>,
+    U: Float,
+{
+    assert_eq!(x.len(), y.len());
+    x.iter()
+        .zip(y.iter())
+        .fold(T::zero(), |acc, (&a, &b)| acc + a.conj() * b)
+}
+
 pub fn sprs2dense<T>(s: &sprs::CsMat<T>) -> Array2<T>
 where
     T: ComplexOrReal<T> + Float,
@@ -122,19 +134,18 @@ where
     assert_eq!(A.cols(), b.len());
     let mut result = Array1::from(vec![T::default(); A.rows()]);
     for (&x, (i, j)) in A.iter() {
-        result[(i)] = result[(i)] + x * b[(j)];
+        result[i] = result[i] + x * b[j];
     }
     result
 }
 
-pub fn sp_mul_a2<U, T, I, IptrStorage, IndStorage, DataStorage>(
+pub fn sp_mul_a2<T, I, IptrStorage, IndStorage, DataStorage>(
     A: &sprs::CsMatBase<T, I, IptrStorage, IndStorage, DataStorage>,
     B: ArrayView2<T>,
 ) -> Array2<T>
 where
-    T: ComplexOrReal<U>,
-    U: Float,
-    I: sprs::SpIndex + ndarray::NdIndex<ndarray::Dim<[usize; 1]>> + Into<usize>,
+    T: Num + Copy,
+    I: sprs::SpIndex,
     IptrStorage: std::ops::Deref<Target = [I]>,
     IndStorage: std::ops::Deref<Target = [I]>,
     DataStorage: std::ops::Deref<Target = [T]>,
@@ -143,8 +154,9 @@ where
     let mut result = Array2::zeros((A.rows(), B.ncols()));
 
     for (&x, (i, k)) in A.iter() {
+        let (i, k) = (i.index(), k.index());
         for j in 0..B.ncols() {
-            result[(i.into(), j)] = result[(i.into(), j)] + x * B[(k.into(), j)];
+            result[(i, j)] = result[(i, j)] + x * B[(k, j)];
         }
     }
     result